@@ -0,0 +1,151 @@
+//! Plays the game automatically, using only the public [BoardView] and event API.
+//!
+//! Each step applies the basic minesweeper rules to every revealed block:
+//! if its hidden neighbors must all be mines, mark them, and if all its mines are already
+//! marked, clear the rest. When neither rule applies, it guesses.
+//!
+//! Every game is recorded with the [AnalyticsPlugin], and written to `autoplay.csv` on exit.
+//!
+//! Run with `cargo run --example autoplay`.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::prelude::*;
+use sweeper_3d::{
+    Analytics, AnalyticsPlugin, BoardView, CampaignPlugin, CellView, Contains, FieldEvent,
+    FieldSettings, GamePlugin, GameResult, GameState, HudPlugin, InputPlugin, LoaderPlugin,
+    MenuPlugin, SettingsPlugin,
+};
+
+/// Seconds between moves, so the game can be followed on screen
+const MOVE_INTERVAL: f32 = 0.2;
+
+fn main() {
+    App::new()
+        .init_state::<GameState>()
+        .add_plugins(DefaultPlugins)
+        .add_plugins((
+            MenuPlugin,
+            SettingsPlugin,
+            GamePlugin,
+            InputPlugin,
+            LoaderPlugin,
+            CampaignPlugin,
+            HudPlugin,
+            AnalyticsPlugin {
+                csv_path: Some("autoplay.csv".into()),
+            },
+        ))
+        .insert_resource(FieldSettings::small())
+        .insert_resource(MoveTimer(Timer::from_seconds(
+            MOVE_INTERVAL,
+            TimerMode::Repeating,
+        )))
+        .add_systems(Startup, setup)
+        .add_systems(OnEnter(GameState::MenuMain), start_game)
+        .add_systems(Update, autoplay.run_if(GameState::playable()))
+        .add_systems(OnEnter(GameState::GameOver), report)
+        .run();
+}
+
+#[derive(Resource)]
+struct MoveTimer(Timer);
+
+fn setup(mut commands: Commands) {
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(-1.0, 1.0, 1.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn start_game(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::GameStart);
+}
+
+fn autoplay(
+    mut timer: ResMut<MoveTimer>,
+    time: Res<Time>,
+    board: BoardView,
+    mut field_events: EventWriter<FieldEvent>,
+    mut analytics: ResMut<Analytics>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let cells: HashMap<[usize; 3], CellView> = board.snapshot().into_iter().collect();
+    let neighbors = |[i, j, k]: [usize; 3]| {
+        let mut adjacent = Vec::new();
+        for i_off in -1..=1isize {
+            for j_off in -1..=1isize {
+                for k_off in -1..=1isize {
+                    if i_off == 0 && j_off == 0 && k_off == 0 {
+                        continue;
+                    }
+                    let index = [
+                        i.wrapping_add_signed(i_off),
+                        j.wrapping_add_signed(j_off),
+                        k.wrapping_add_signed(k_off),
+                    ];
+                    if let Some(view) = cells.get(&index) {
+                        adjacent.push((index, *view));
+                    }
+                }
+            }
+        }
+        adjacent
+    };
+
+    for (&index, view) in &cells {
+        let CellView::Revealed(Contains::Empty { adjacent_mines }) = view else {
+            continue;
+        };
+        let adjacent = neighbors(index);
+        let flagged = adjacent
+            .iter()
+            .filter(|(_, view)| *view == CellView::Flagged)
+            .count();
+        let hidden: Vec<_> = adjacent
+            .iter()
+            .filter(|(_, view)| *view == CellView::Hidden)
+            .map(|(index, _)| *index)
+            .collect();
+        if hidden.is_empty() {
+            continue;
+        }
+        if flagged + hidden.len() == usize::from(*adjacent_mines) {
+            for index in hidden {
+                field_events.send(FieldEvent::MarkBlock(index));
+            }
+            return;
+        }
+        if flagged == usize::from(*adjacent_mines) {
+            for index in hidden {
+                field_events.send(FieldEvent::ClearBlock(index));
+            }
+            return;
+        }
+    }
+
+    // No safe move is known, so guess
+    let candidates: Vec<_> = cells
+        .iter()
+        .filter(|(_, view)| **view == CellView::Hidden)
+        .map(|(index, _)| *index)
+        .collect();
+    if let Some(index) = candidates.choose(&mut thread_rng()) {
+        info!("Guessing {index:?}");
+        analytics.record_guess();
+        field_events.send(FieldEvent::ClearBlock(*index));
+    }
+}
+
+fn report(result: Res<GameResult>, analytics: Res<Analytics>) {
+    info!("Autoplay finished: {:?}", *result);
+    info!(
+        "{} won, {} lost, {:?} moves to win on average, {:?} of moves guessed",
+        analytics.count(GameResult::Victory),
+        analytics.count(GameResult::Failure),
+        analytics.average_moves_to_win(),
+        analytics.guess_rate(),
+    );
+}
@@ -0,0 +1,276 @@
+use std::path::PathBuf;
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{game::GameResult, FieldEvent, GameLostEvent, GameState};
+
+/// Opt-in plugin that collects [Analytics] over every game played in a session,
+/// for tuning difficulty presets. Not added by default.
+pub struct AnalyticsPlugin {
+    /// Where to write the collected records as CSV when the app exits, if anywhere
+    pub csv_path: Option<PathBuf>,
+}
+impl Plugin for AnalyticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Analytics {
+            csv_path: self.csv_path.clone(),
+            ..default()
+        })
+        .add_systems(OnEnter(GameState::GameStart), start_game)
+        // After every move of the frame has been sent, but before the game can end
+        .add_systems(PostUpdate, count_moves.run_if(GameState::playable()))
+        .add_systems(OnEnter(GameState::GameOver), finish_game)
+        .add_systems(Last, write_on_exit);
+    }
+}
+
+/// Kind of move that ended a game in a loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossCause {
+    /// The very first block cleared was a mine
+    FirstMove,
+    /// A block cleared directly
+    Clear,
+    /// A block cleared by chording
+    Chord,
+    /// [crate::GameSettings::time_limit] ran out
+    Timeout,
+}
+
+/// Counters for a single finished game.
+#[derive(Debug, Clone, Copy)]
+pub struct GameRecord {
+    /// How the game ended
+    pub result: GameResult,
+    /// Blocks cleared directly, including the first
+    pub clears: usize,
+    /// Chords made
+    pub chords: usize,
+    /// Blocks marked or unmarked
+    pub marks: usize,
+    /// Moves reported as guesses with [Analytics::record_guess]
+    pub guesses: usize,
+    /// What caused the loss, if the game was lost
+    pub loss_cause: Option<LossCause>,
+}
+
+/// Counters accumulated across every game of a session.
+#[derive(Debug, Default, Resource)]
+pub struct Analytics {
+    /// Every finished game, in order
+    pub games: Vec<GameRecord>,
+    /// Counters for the game in progress
+    current: Option<GameRecord>,
+    /// Kind of the last clearing move in the game in progress
+    last_move: Option<LossCause>,
+    csv_path: Option<PathBuf>,
+}
+impl Analytics {
+    /// Count the next move as a guess. Called by automated players when no move is known
+    /// to be safe.
+    pub fn record_guess(&mut self) {
+        if let Some(current) = &mut self.current {
+            current.guesses += 1;
+        }
+    }
+    /// Number of finished games with the given result
+    pub fn count(&self, result: GameResult) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.result == result)
+            .count()
+    }
+    /// Average number of clears and chords in games that were won
+    pub fn average_moves_to_win(&self) -> Option<f32> {
+        let wins = self.count(GameResult::Victory);
+        let moves: usize = self
+            .games
+            .iter()
+            .filter(|game| game.result == GameResult::Victory)
+            .map(|game| game.clears + game.chords)
+            .sum();
+        (wins > 0).then(|| moves as f32 / wins as f32)
+    }
+    /// Fraction of clearing moves that were guesses, over every finished game
+    pub fn guess_rate(&self) -> Option<f32> {
+        let moves: usize = self
+            .games
+            .iter()
+            .map(|game| game.clears + game.chords)
+            .sum();
+        let guesses: usize = self.games.iter().map(|game| game.guesses).sum();
+        (moves > 0).then(|| guesses as f32 / moves as f32)
+    }
+    /// Number of losses with the given cause
+    pub fn losses_by(&self, cause: LossCause) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.loss_cause == Some(cause))
+            .count()
+    }
+    /// Every finished game as CSV, one row per game, with a header.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("game,result,clears,chords,marks,guesses,loss_cause\n");
+        for (n, game) in self.games.iter().enumerate() {
+            let loss_cause = game
+                .loss_cause
+                .map(|cause| format!("{cause:?}"))
+                .unwrap_or_default();
+            csv += &format!(
+                "{n},{:?},{},{},{},{},{loss_cause}\n",
+                game.result, game.clears, game.chords, game.marks, game.guesses,
+            );
+        }
+        csv
+    }
+}
+
+fn start_game(mut analytics: ResMut<Analytics>) {
+    analytics.current = Some(GameRecord {
+        result: GameResult::Unfinished,
+        clears: 0,
+        chords: 0,
+        marks: 0,
+        guesses: 0,
+        loss_cause: None,
+    });
+    analytics.last_move = None;
+}
+
+fn count_moves(mut analytics: ResMut<Analytics>, mut field_events: EventReader<FieldEvent>) {
+    let Analytics {
+        current, last_move, ..
+    } = &mut *analytics;
+    let Some(current) = current else {
+        field_events.clear();
+        return;
+    };
+    for event in field_events.read() {
+        match event {
+            FieldEvent::ClearBlock(_) => {
+                *last_move = Some(match current.clears + current.chords {
+                    0 => LossCause::FirstMove,
+                    _ => LossCause::Clear,
+                });
+                current.clears += 1;
+            }
+            FieldEvent::ChordBlock(_) => {
+                *last_move = Some(LossCause::Chord);
+                current.chords += 1;
+            }
+            FieldEvent::MarkBlock(_) => current.marks += 1,
+            _ => {}
+        }
+    }
+}
+
+fn finish_game(
+    mut analytics: ResMut<Analytics>,
+    game_result: Res<GameResult>,
+    mut lost_events: EventReader<GameLostEvent>,
+) {
+    let last_move = analytics.last_move;
+    let Some(mut record) = analytics.current.take() else {
+        return;
+    };
+    record.result = *game_result;
+    let timed_out = lost_events
+        .read()
+        .any(|event| *event == GameLostEvent::Timeout);
+    if *game_result == GameResult::Failure {
+        record.loss_cause = if timed_out {
+            Some(LossCause::Timeout)
+        } else {
+            last_move
+        };
+    }
+    info!("Analytics: {record:?}");
+    analytics.games.push(record);
+}
+
+fn write_on_exit(analytics: Res<Analytics>, mut exit_events: EventReader<AppExit>) {
+    if exit_events.read().last().is_none() {
+        return;
+    }
+    let Some(path) = &analytics.csv_path else {
+        return;
+    };
+    match std::fs::write(path, analytics.to_csv()) {
+        Ok(()) => info!("Wrote analytics to {}", path.display()),
+        Err(err) => error!("Unable to write analytics to {}: {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play a game through `moves`, reporting `guesses` of them as guesses,
+    /// and end it with `result` and, for a loss, `lost`
+    fn play(
+        app: &mut App,
+        moves: &[FieldEvent],
+        guesses: usize,
+        result: GameResult,
+        lost: Option<GameLostEvent>,
+    ) {
+        let set_state = |app: &mut App, state| {
+            app.world.resource_mut::<NextState<GameState>>().set(state);
+            app.update();
+        };
+        set_state(app, GameState::GameStart);
+        for _ in 0..guesses {
+            app.world.resource_mut::<Analytics>().record_guess();
+        }
+        app.world
+            .resource_mut::<Events<FieldEvent>>()
+            .send_batch(moves.iter().copied());
+        set_state(app, GameState::GamePlaying);
+        *app.world.resource_mut::<GameResult>() = result;
+        if let Some(lost) = lost {
+            app.world.send_event(lost);
+        }
+        set_state(app, GameState::GameOver);
+    }
+
+    #[test]
+    fn counts_a_scripted_session() {
+        let mut app = App::new();
+        app.init_state::<GameState>()
+            .init_resource::<GameResult>()
+            .add_event::<FieldEvent>()
+            .add_event::<GameLostEvent>()
+            .add_event::<AppExit>()
+            .add_plugins(AnalyticsPlugin { csv_path: None });
+        let clear = FieldEvent::ClearBlock([0, 0, 0]);
+        let moves = [
+            clear,
+            FieldEvent::MarkBlock([1, 0, 0]),
+            FieldEvent::ChordBlock([0, 0, 0]),
+            clear,
+        ];
+        play(&mut app, &moves, 1, GameResult::Victory, None);
+        let detonation = Some(GameLostEvent::Detonation);
+        play(&mut app, &[clear], 0, GameResult::Failure, detonation);
+        let chord_loss = [clear, FieldEvent::ChordBlock([0, 0, 0])];
+        play(&mut app, &chord_loss, 0, GameResult::Failure, detonation);
+        let timeout = Some(GameLostEvent::Timeout);
+        play(&mut app, &[clear], 0, GameResult::Failure, timeout);
+
+        let analytics = app.world.resource::<Analytics>();
+        assert_eq!(analytics.games.len(), 4);
+        assert_eq!(analytics.count(GameResult::Victory), 1);
+        assert_eq!(analytics.count(GameResult::Failure), 3);
+        assert_eq!(analytics.average_moves_to_win(), Some(3.0));
+        // One guess in 3 + 1 + 2 + 1 moves
+        assert_eq!(analytics.guess_rate(), Some(1.0 / 7.0));
+        assert_eq!(analytics.losses_by(LossCause::FirstMove), 1);
+        assert_eq!(analytics.losses_by(LossCause::Chord), 1);
+        assert_eq!(analytics.losses_by(LossCause::Timeout), 1);
+        assert_eq!(analytics.losses_by(LossCause::Clear), 0);
+        assert_eq!(
+            analytics.to_csv().lines().nth(1),
+            Some("0,Victory,2,1,1,1,")
+        );
+    }
+}
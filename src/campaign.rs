@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+use crate::{game::GameResult, storage, FieldSettings, FieldShape, GameState};
+
+/// File in the data directory where campaign progress is saved
+const CAMPAIGN_FILE: &str = "campaign.txt";
+
+/// Campaign levels, played in order. Each entry is a field size and mine density.
+pub const CAMPAIGN_LEVELS: &[([usize; 3], f32)] = &[
+    ([3, 3, 3], 0.1),
+    ([4, 4, 4], 0.1),
+    ([5, 5, 5], 0.1),
+    ([5, 5, 5], 0.15),
+    ([6, 6, 6], 0.15),
+    ([8, 8, 8], 0.15),
+    ([10, 10, 10], 0.15),
+    ([10, 10, 10], 0.2),
+];
+
+pub struct CampaignPlugin;
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Campaign>()
+            .add_systems(Startup, load_progress)
+            .add_systems(OnEnter(GameState::GameOver), advance);
+    }
+}
+
+/// Progress through [CAMPAIGN_LEVELS].
+#[derive(Debug, Default, Resource)]
+pub struct Campaign {
+    /// Whether the current game is part of the campaign
+    pub active: bool,
+    /// Index into [CAMPAIGN_LEVELS] of the level being played
+    pub level: usize,
+}
+impl Campaign {
+    /// Field settings for the current level
+    pub fn field_settings(&self) -> FieldSettings {
+        let (field_size, mine_density) = CAMPAIGN_LEVELS[self.level.min(CAMPAIGN_LEVELS.len() - 1)];
+        FieldSettings {
+            field_size,
+            mine_density,
+            shape: FieldShape::Box,
+            connectivity: None,
+        }
+    }
+    /// Whether the last level has been won
+    pub fn completed(&self) -> bool {
+        self.level >= CAMPAIGN_LEVELS.len()
+    }
+    fn save(&self) {
+        if let Err(err) = storage::write(CAMPAIGN_FILE, &self.level.to_string()) {
+            error!("Unable to save campaign progress: {err}");
+        }
+    }
+}
+
+fn load_progress(mut campaign: ResMut<Campaign>) {
+    let Some(contents) = storage::read(CAMPAIGN_FILE) else {
+        return;
+    };
+    match contents.trim().parse() {
+        Ok(level) => {
+            info!("Loaded campaign progress: level {level}");
+            campaign.level = level;
+        }
+        Err(err) => error!("Invalid campaign progress {contents:?}: {err}"),
+    }
+}
+
+/// Move on to the next level after a campaign game is won
+fn advance(mut campaign: ResMut<Campaign>, game_result: Res<GameResult>) {
+    if campaign.active && matches!(*game_result, GameResult::Victory) && !campaign.completed() {
+        campaign.level += 1;
+        info!("Campaign advanced to level {}", campaign.level);
+        campaign.save();
+    }
+}
@@ -1,14 +1,49 @@
+use bevy::hierarchy::despawn_with_children_recursive;
 use bevy::prelude::*;
 
 mod block;
+mod board;
 mod camera;
+mod effects;
+mod headless;
+mod heatmap;
+mod jingle;
 mod minefield;
+mod outline;
+mod replay;
+mod save;
+mod timeline;
+
+pub use block::{Block, BlockEvent, BlockMeta, ClickMissedEvent};
+pub use board::{BoardView, CellView};
+pub use camera::CameraTarget;
+pub use headless::HeadlessPlugin;
+pub use heatmap::Heatmap;
+pub use minefield::{
+    BoardSeed, Contains, FieldEvent, FlagLimitReached, GenerationReport, Minefield,
+};
+pub use replay::Replay;
+pub use save::Autosave;
+pub use timeline::RevealTimeline;
+
+#[cfg(test)]
+pub(crate) use minefield::testing;
 
 use block::BlockPlugin;
 use camera::CameraPlugin;
+use effects::EffectsPlugin;
+use heatmap::HeatmapPlugin;
+use jingle::JinglePlugin;
 use minefield::FieldPlugin;
+use outline::OutlinePlugin;
+use replay::ReplayPlugin;
+use save::SavePlugin;
+use timeline::TimelinePlugin;
 
-use crate::GameState;
+use crate::{FieldSettings, GameSettings, GameState, InputEvent};
+
+/// Seconds after a first [InputEvent::Surrender] during which a second one confirms it
+const SURRENDER_CONFIRM_TIME: f32 = 3.0;
 
 /// Marker component indicating an entity to be removed when the game is reset.
 #[derive(Component)]
@@ -17,34 +52,388 @@ pub struct GamePiece;
 /// [camera::camera_controls] consumes [crate::InputEvent] and produces [RayEvent].  
 /// [block::handle_ray_events] consumes [RayEvent] and produces [FieldEvent] and/or [BlockEvent].  
 /// [minefield::handle_field_events] consumes [FieldEvent] and produces [BlockEvent].  
-/// [block::handle_block_events] consumes [BlockEvent] and potentially changes [GameState].  
+/// [block::handle_block_events] consumes [BlockEvent], potentially changes [GameState],
+/// and produces [block::BlockVisualChanged].  
+/// [block::apply_block_visuals] consumes [block::BlockVisualChanged] and changes how blocks look.  
 pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<GameResult>();
-        app.add_systems(OnEnter(GameState::GameStart), cleanup);
-        app.add_plugins((BlockPlugin, CameraPlugin, FieldPlugin));
+        app.init_resource::<Paused>();
+        app.init_resource::<GameStats>();
+        app.init_resource::<LastReveal>();
+        app.init_resource::<GameTimer>();
+        app.init_resource::<SurrenderPrompt>();
+        app.init_resource::<Inspecting>();
+        app.add_event::<GameLostEvent>();
+        app.add_systems(
+            Update,
+            (tick_timer, enforce_time_limit)
+                .chain()
+                .run_if(in_state(GameState::GamePlaying).and_then(resource_equals(Paused(false)))),
+        );
+        app.add_systems(
+            Update,
+            surrender
+                .run_if(in_state(GameState::GamePlaying).and_then(resource_equals(Paused(false)))),
+        );
+        app.add_systems(
+            Update,
+            toggle_inspect
+                .before(camera::camera_controls)
+                .run_if(GameState::playable().and_then(resource_equals(Paused(false)))),
+        );
+        app.add_systems(
+            OnEnter(GameState::GameStart),
+            (limit_field_size, cleanup).chain(),
+        );
+        // Leave nothing from the previous game behind the menus
+        app.add_systems(OnEnter(GameState::MenuMain), cleanup);
+        app.add_plugins((
+            BlockPlugin,
+            CameraPlugin,
+            EffectsPlugin,
+            FieldPlugin,
+            HeatmapPlugin,
+            JinglePlugin,
+            OutlinePlugin,
+            ReplayPlugin,
+            SavePlugin,
+            TimelinePlugin,
+        ));
+    }
+}
+
+/// Shrink the field to [GameSettings::max_cells] before anything is spawned,
+/// so an oversized field can't exhaust memory.
+fn limit_field_size(game_settings: Res<GameSettings>, mut field_settings: ResMut<FieldSettings>) {
+    let requested = field_settings.field_size;
+    if field_settings.clamp_cells(game_settings.max_cells) {
+        error!(
+            "Field size {requested:?} exceeds the limit of {} cells; reduced to {:?}",
+            game_settings.max_cells, field_settings.field_size
+        );
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn cleanup(
     to_despawn: Query<Entity, With<GamePiece>>,
     mut commands: Commands,
     mut result: ResMut<GameResult>,
+    mut paused: ResMut<Paused>,
+    mut stats: ResMut<GameStats>,
+    mut last_reveal: ResMut<LastReveal>,
+    mut timer: ResMut<GameTimer>,
+    mut surrender_prompt: ResMut<SurrenderPrompt>,
+    mut inspecting: ResMut<Inspecting>,
 ) {
-    for entity in &to_despawn {
-        commands.entity(entity).despawn_recursive();
-    }
+    // Despawn everything in a single command, rather than queueing one per entity
+    let entities: Vec<Entity> = to_despawn.iter().collect();
+    debug!("Despawning {} game pieces", entities.len());
+    commands.add(move |world: &mut World| {
+        for entity in entities {
+            despawn_with_children_recursive(world, entity);
+        }
+    });
     *result = GameResult::default();
+    paused.0 = false;
+    *stats = GameStats::default();
+    *last_reveal = LastReveal::default();
+    *timer = GameTimer::default();
+    *surrender_prompt = SurrenderPrompt::default();
+    *inspecting = Inspecting::default();
+}
+
+/// Time spent playing the current game, not counting time paused.
+/// Starts when the first block is cleared.
+#[derive(Debug, Default, Resource)]
+pub struct GameTimer {
+    /// Seconds played
+    pub elapsed: f32,
+}
+impl GameTimer {
+    /// Seconds left before [GameSettings::time_limit] runs out, if there is a limit
+    pub fn remaining(&self, game_settings: &GameSettings) -> Option<f32> {
+        (game_settings.time_limit > 0.0).then(|| (game_settings.time_limit - self.elapsed).max(0.0))
+    }
+}
+
+fn tick_timer(mut timer: ResMut<GameTimer>, time: Res<Time>) {
+    timer.elapsed += time.delta_seconds();
+}
+
+/// Lose the game once [GameSettings::time_limit] runs out
+fn enforce_time_limit(
+    timer: Res<GameTimer>,
+    game_settings: Res<GameSettings>,
+    mut game_result: ResMut<GameResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut lost_events: EventWriter<GameLostEvent>,
+) {
+    if timer.remaining(&game_settings) == Some(0.0) {
+        lose(
+            GameLostEvent::Timeout,
+            &mut game_result,
+            &mut next_state,
+            &mut lost_events,
+        );
+    }
+}
+
+/// Sent when the game is lost, with what lost it
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameLostEvent {
+    /// A mine was cleared
+    Detonation,
+    /// [GameSettings::time_limit] ran out
+    Timeout,
+}
+
+/// End the game in a [GameResult::Failure] because of `cause`, unless it's already lost,
+/// e.g. by another mine cleared by the same chord
+pub(super) fn lose(
+    cause: GameLostEvent,
+    game_result: &mut GameResult,
+    next_state: &mut NextState<GameState>,
+    lost_events: &mut EventWriter<GameLostEvent>,
+) {
+    if *game_result == GameResult::Failure {
+        return;
+    }
+    match cause {
+        GameLostEvent::Detonation => info!("Detonated a mine!"),
+        GameLostEvent::Timeout => info!("Out of time!"),
+    }
+    *game_result = GameResult::Failure;
+    debug!("Transition to GameState::GameOver");
+    next_state.set(GameState::GameOver);
+    lost_events.send(cause);
+}
+
+/// Seconds left to confirm a surrender by sending [InputEvent::Surrender] again, if one
+/// was just requested.
+#[derive(Debug, Default, Resource)]
+pub struct SurrenderPrompt(pub Option<f32>);
+
+/// End the game with [GameResult::Surrender] on a confirmed [InputEvent::Surrender]
+fn surrender(
+    mut input_events: EventReader<InputEvent>,
+    mut prompt: ResMut<SurrenderPrompt>,
+    mut game_result: ResMut<GameResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if let Some(remaining) = &mut prompt.0 {
+        *remaining -= time.delta_seconds();
+        if *remaining <= 0.0 {
+            prompt.0 = None;
+        }
+    }
+    for event in input_events.read() {
+        if !matches!(event, InputEvent::Surrender) {
+            continue;
+        }
+        if prompt.0.take().is_some() {
+            info!("Surrendered");
+            *game_result = GameResult::Surrender;
+            next_state.set(GameState::GameOver);
+        } else {
+            prompt.0 = Some(SURRENDER_CONFIRM_TIME);
+        }
+    }
+}
+
+/// Whether inspect mode is on. While inspecting, the camera can be moved freely but no
+/// block can be cleared, and blocks can only be marked if
+/// [GameSettings::inspect_allows_marking] is set.
+#[derive(Debug, Default, Resource, PartialEq, Eq)]
+pub struct Inspecting(pub bool);
+
+fn toggle_inspect(mut input_events: EventReader<InputEvent>, mut inspecting: ResMut<Inspecting>) {
+    for event in input_events.read() {
+        if matches!(event, InputEvent::ToggleInspect) {
+            inspecting.0 = !inspecting.0;
+            info!("Inspect mode {}", if inspecting.0 { "on" } else { "off" });
+        }
+    }
+}
+
+/// Whether the game is paused. While paused, camera and block controls are ignored.
+#[derive(Debug, Default, Resource, PartialEq, Eq)]
+pub struct Paused(pub bool);
+
+/// Running counts of block states in the current game.
+#[derive(Debug, Default, Resource)]
+pub struct GameStats {
+    /// Number of blocks in the field
+    pub blocks: usize,
+    /// Number of blocks that have been cleared
+    pub revealed: usize,
+    /// Number of blocks currently marked as mines
+    pub marked: usize,
+    /// Number of mines in the field. Until mines are placed by the first clear,
+    /// this is the number the field is expected to have.
+    pub mines: usize,
+    /// Number of marked blocks that really contain a mine.
+    /// Only meant to be shown with [crate::WinCondition::MarkAll], since it gives mines away.
+    pub correct_flags: usize,
+    /// Number of [crate::GameSettings::free_safe_moves] left to use
+    pub free_moves: usize,
+    /// Least number of clears that solve the field, see [Minefield::three_bv].
+    /// Zero until mines are placed by the first clear.
+    pub three_bv: usize,
+    /// Number of clears, chords and marks that changed something
+    pub clicks: usize,
+    /// Counts for each layer of the field, indexed by the block's Z index
+    pub layers: Vec<LayerStats>,
+}
+impl GameStats {
+    /// Number of mines not yet marked. Negative if more blocks are marked than there are mines.
+    pub fn mines_remaining(&self) -> isize {
+        self.mines as isize - self.marked as isize
+    }
+    /// Whether more blocks are marked than there are mines, so some marks must be wrong
+    pub fn over_marked(&self) -> bool {
+        self.marked > self.mines
+    }
+    /// [GameStats::three_bv] as a percentage of [GameStats::clicks], once there are any.
+    /// Solving the field in as few clicks as possible is 100%.
+    pub fn efficiency(&self) -> Option<f32> {
+        (self.clicks > 0).then(|| self.three_bv as f32 / self.clicks as f32 * 100.0)
+    }
+    /// Get the stats for a layer, adding it if it isn't tracked yet
+    pub(crate) fn layer_mut(&mut self, layer: usize) -> &mut LayerStats {
+        if self.layers.len() <= layer {
+            self.layers.resize_with(layer + 1, Default::default);
+        }
+        &mut self.layers[layer]
+    }
+}
+
+/// The block most recently cleared by the player, if any.
+/// Blocks revealed as a consequence (e.g. by flooding) don't replace it.
+#[derive(Debug, Default, Resource)]
+pub struct LastReveal(pub Option<RevealInfo>);
+
+/// Details of a cleared block, for display.
+#[derive(Debug, Clone, Copy)]
+pub struct RevealInfo {
+    /// Field index of the block
+    pub index: [usize; 3],
+    /// What the block contained
+    pub contains: Contains,
+    /// Number of neighboring blocks marked at the time it was cleared
+    pub flagged_neighbors: usize,
+}
+
+/// Counts of block states within a single layer of the field.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LayerStats {
+    pub blocks: usize,
+    pub revealed: usize,
+    pub marked: usize,
+}
+impl LayerStats {
+    /// Blocks that are neither revealed nor marked
+    pub fn remaining(&self) -> usize {
+        self.blocks.saturating_sub(self.revealed + self.marked)
+    }
 }
 
 /// When the game ends, what was the result?
-#[derive(Default, Resource)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
 pub enum GameResult {
     #[default]
     Unfinished,
     Victory,
     Failure,
+    /// The player gave up, revealing the solution
+    Surrender,
 }
 
 // TAB - step away ______ -> asfgrdsgg
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_out_of_time_loses_once() {
+        let mut app = App::new();
+        app.init_state::<GameState>()
+            .add_event::<GameLostEvent>()
+            .init_resource::<GameResult>()
+            .insert_resource(GameSettings {
+                time_limit: 30.0,
+                ..default()
+            })
+            .insert_resource(GameTimer { elapsed: 29.0 })
+            .add_systems(Update, enforce_time_limit);
+        app.update();
+        assert_eq!(*app.world.resource::<GameResult>(), GameResult::Unfinished);
+
+        app.world.resource_mut::<GameTimer>().elapsed = 30.5;
+        app.update();
+        app.update();
+        assert_eq!(*app.world.resource::<GameResult>(), GameResult::Failure);
+        assert_eq!(
+            app.world.resource::<State<GameState>>().get(),
+            &GameState::GameOver
+        );
+        let events = app.world.resource::<Events<GameLostEvent>>();
+        let sent: Vec<_> = events.get_reader().read(events).copied().collect();
+        assert_eq!(sent, [GameLostEvent::Timeout]);
+    }
+
+    #[test]
+    fn restarting_leaves_no_blocks_behind() {
+        use minefield::testing::field_app;
+        let field_settings = FieldSettings {
+            field_size: [12, 12, 12],
+            ..default()
+        };
+        let mut app = field_app(default(), field_settings);
+        app.init_resource::<Paused>()
+            .init_resource::<SurrenderPrompt>()
+            .add_systems(OnEnter(GameState::MenuMain), cleanup);
+        let count = |app: &mut App| {
+            let world = &mut app.world;
+            (
+                world.query::<&Block>().iter(world).count(),
+                world.query::<&Minefield>().iter(world).count(),
+            )
+        };
+        assert_eq!(count(&mut app), (12 * 12 * 12, 1));
+        for _ in 0..2 {
+            app.world
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::MenuMain);
+            app.update();
+            assert_eq!(count(&mut app), (0, 0));
+            app.world
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::GameStart);
+            app.update();
+            assert_eq!(count(&mut app), (12 * 12 * 12, 1));
+        }
+    }
+
+    #[test]
+    fn over_marking_warns_until_the_extra_flag_is_removed() {
+        use minefield::testing::{board, board_app, play};
+        use minefield::FieldEvent::MarkBlock;
+        let mut app = board_app(default(), board([4, 1, 1], &[[0, 0, 0], [3, 0, 0]]));
+        let over_marked = |app: &App| app.world.resource::<GameStats>().over_marked();
+        for (mark, over) in [
+            ([0, 0, 0], false),
+            ([1, 0, 0], false),
+            ([2, 0, 0], true),
+            // Unmarking goes back under the count
+            ([1, 0, 0], false),
+        ] {
+            play(&mut app, [MarkBlock(mark)]);
+            assert_eq!(over_marked(&app), over, "after toggling {mark:?}");
+        }
+    }
+}
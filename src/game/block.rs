@@ -1,18 +1,61 @@
-use bevy::audio::PlaybackMode;
-use bevy::math::bounding::{Aabb3d, Bounded3d, RayCast3d};
+use std::collections::VecDeque;
+
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::ecs::system::{EntityCommands, SystemParam};
+use bevy::math::bounding::{Aabb3d, Bounded3d, BoundingVolume, RayCast3d};
 use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::{egui, EguiContexts, EguiSettings};
 
-use super::camera::RayEvent;
+use super::board::CellView;
+use super::camera::{MainCamera, PickDistance, RayEvent};
+use super::effects::{spawn_ripple, RippleMesh};
 use super::minefield::{Contains, FieldEvent};
-use super::{GamePiece, GameResult, GameState};
-use crate::{FieldSettings, GameAssets};
+use super::{
+    lose, GameLostEvent, GamePiece, GameResult, GameState, GameStats, GameTimer, Inspecting,
+    LastReveal, Paused, RevealInfo,
+};
+use crate::{
+    input::ui_position, BlockStyle, ColorScheme, EmptyMarker, FieldSettings, GameAssets,
+    GameSettings, InputEvent, RevealStyle, UpAxis,
+};
+
+/// Scale of the cube left behind by a revealed block with [RevealStyle::ShrunkCube]
+const SHRUNK_CUBE_SCALE: f32 = 0.4;
+/// Increase in reveal sound playback speed (and so pitch) per adjacent mine
+const ADJACENCY_PITCH_STEP: f32 = 0.06;
+/// Apparent size of a [RevealStyle::Billboard] disc, as the angle it covers from the camera
+const BILLBOARD_ANGULAR_SIZE: f32 = 0.04;
+/// Smallest and largest size of a [RevealStyle::Billboard] disc, relative to a cell
+const BILLBOARD_SIZE_RANGE: (f32, f32) = (0.1, 0.9);
+/// Seconds taken for every block to appear at the start of a game
+const SPAWN_DURATION: f32 = 1.0;
+/// Seconds a cleared block takes to animate away with [GameSettings::reveal_anim]
+const REVEAL_ANIM_DURATION: f32 = 0.2;
+/// Most clicks held in the [ClickBuffer] at once. Any more are dropped.
+const MAX_BUFFERED_CLICKS: usize = 8;
+/// Seconds after marking a block during which further marks on it are ignored
+const MARK_COOLDOWN: f32 = 0.2;
+/// Opacity of hidden blocks while peeking inside the field
+const PEEK_ALPHA: f32 = 0.1;
+/// Size of number labels with [GameSettings::high_contrast], relative to the usual size
+const HIGH_CONTRAST_LABEL_SCALE: f32 = 1.25;
+/// Shade of hidden blocks with [GameSettings::high_contrast]
+const HIGH_CONTRAST_HIDDEN_SHADE: f32 = 0.85;
 
 pub struct BlockPlugin;
 impl Plugin for BlockPlugin {
     fn build(&self, app: &mut App) {
         // Add Block systems
-        app.add_systems(Startup, create_materials);
-        app.add_systems(OnEnter(GameState::GameStart), setup.after(super::cleanup));
+        app.add_systems(
+            OnEnter(GameState::GameStart),
+            (release_assets, (create_materials, create_meshes), setup)
+                .chain()
+                .after(super::cleanup),
+        );
         app.add_systems(
             Update,
             handle_ray_events
@@ -25,7 +68,69 @@ impl Plugin for BlockPlugin {
                 .after(super::minefield::handle_field_events)
                 .run_if(GameState::in_game()),
         );
+        app.add_systems(
+            Update,
+            apply_block_visuals
+                .after(handle_block_events)
+                .run_if(GameState::in_game()),
+        );
+        app.add_systems(
+            Update,
+            animate_reveals
+                .after(apply_block_visuals)
+                .run_if(GameState::in_game()),
+        );
+        app.add_systems(
+            Update,
+            draw_number_labels
+                .after(apply_block_visuals)
+                .run_if(GameState::in_game()),
+        );
+        app.add_systems(
+            Update,
+            face_billboards
+                .after(super::camera::camera_controls)
+                .run_if(GameState::in_game()),
+        );
+        app.add_systems(Update, peek.run_if(GameState::in_game()));
+        app.add_systems(Update, stream_spawn.run_if(GameState::in_game()));
+        app.add_systems(
+            Update,
+            box_mark
+                .before(super::minefield::handle_field_events)
+                .run_if(GameState::playable().and_then(resource_equals(Paused(false)))),
+        );
+        app.add_systems(OnExit(GameState::GamePlaying), stop_peeking);
         app.add_event::<BlockEvent>();
+        app.add_event::<ClickMissedEvent>();
+        app.add_event::<BlockVisualChanged>();
+        app.init_resource::<SweepSession>();
+        app.init_resource::<SpawnQueue>();
+        app.init_resource::<ClickBuffer>();
+        app.add_systems(
+            Update,
+            release_buffered_clicks
+                .after(super::camera::camera_controls)
+                .before(handle_ray_events)
+                .run_if(GameState::playable()),
+        );
+        app.add_systems(
+            Update,
+            buffer_clicks_while_generating
+                .after(super::camera::camera_controls)
+                .run_if(in_state(GameState::GameGenerating)),
+        );
+        app.init_resource::<HoveredMark>();
+        app.add_systems(
+            Update,
+            (track_hovered_mark, display_mark_tooltip)
+                .chain()
+                .after(super::camera::camera_controls)
+                .run_if(
+                    GameState::playable()
+                        .and_then(|game_settings: Res<GameSettings>| game_settings.mark_tooltip),
+                ),
+        );
         #[cfg(feature = "debug-draw")]
         app.add_systems(Update, block_gizmos.run_if(GameState::playable()));
     }
@@ -42,6 +147,8 @@ pub struct Block {
     bb: Aabb3d,
     /// Field index of this block
     index: [usize; 3],
+    /// [GameTimer::elapsed] when the player revealed this block
+    revealed_at: Option<f32>,
 }
 impl Block {
     pub fn new(bb: Aabb3d, index: [usize; 3]) -> Self {
@@ -50,11 +157,30 @@ impl Block {
             revealed: None,
             bb,
             index,
+            revealed_at: None,
         }
     }
+    /// Field index of this block, as used by [FieldEvent]
     pub fn index(&self) -> [usize; 3] {
         self.index
     }
+    /// Whether this block is currently marked as a mine
+    pub fn marked(&self) -> bool {
+        self.marked
+    }
+    /// Contents of this block, if it has been revealed
+    pub fn revealed(&self) -> Option<Contains> {
+        self.revealed
+    }
+    /// Seconds into the game when the player revealed this block, if they did.
+    /// Blocks only shown once the game is over have none.
+    pub fn revealed_at(&self) -> Option<f32> {
+        self.revealed_at
+    }
+    /// Whether this block shows a number, and so can be chorded
+    fn chordable(&self) -> bool {
+        matches!(self.revealed, Some(Contains::Empty { adjacent_mines }) if adjacent_mines > 0)
+    }
 }
 
 #[derive(Debug, Event)]
@@ -66,115 +192,309 @@ pub enum BlockEvent {
     Mark(Entity),
     /// Show the contents of a block after the game has ended.
     EndReveal(Entity, Contains),
+    /// Label a mine shown after the game has ended with how many mines are next to it,
+    /// with [GameSettings::annotate_mines].
+    AnnotateMine(Entity, u8),
+    /// Highlight a hidden block that would be cleared by chording (or remove the highlight).
+    Preview(Entity, bool),
+    /// Change the contents shown by an already revealed block, e.g. after a mine was moved.
+    Update(Entity, Contains),
 }
 impl BlockEvent {
     pub fn block_id(&self) -> Entity {
         match self {
-            Self::Clear(e, _) | Self::Mark(e) | Self::EndReveal(e, _) => *e,
+            Self::Clear(e, _)
+            | Self::Mark(e)
+            | Self::EndReveal(e, _)
+            | Self::AnnotateMine(e, _)
+            | Self::Preview(e, _)
+            | Self::Update(e, _) => *e,
         }
     }
 }
 
+/// Change to how blocks look, following a change to their state.
+/// Sent by [handle_block_events], and applied by [apply_block_visuals].
+#[derive(Debug, Event)]
+pub(super) enum BlockVisualChanged {
+    /// Show `block` as `display`, first removing anything shown over it if `replace` is set.
+    /// If `animate` is set, the block animates away first, when animations are enabled.
+    Display {
+        block: Entity,
+        display: BlockDisplay,
+        replace: bool,
+        animate: bool,
+    },
+    /// Play the sound of clearing a block showing this, once per click
+    RevealSound(Contains),
+}
+
+/// Sent when a clear or mark click hits no block, after any sweep in progress and the
+/// chord preview have been dropped. Other plugins can listen for it to deselect.
+#[derive(Debug, Event)]
+pub struct ClickMissedEvent(pub Ray3d);
+
+/// Arbitrary data attached to a [Block] by other plugins, such as custom themes or puzzle
+/// annotations, as string values by key.
+///
+/// Nothing in this crate reads or changes it. Revealing or marking a block only swaps its
+/// meshes and materials, never the entity, so it stays with the block for the whole game.
+/// Blocks are despawned along with it when the next game starts.
+#[derive(Component, Debug, Default, Clone)]
+pub struct BlockMeta(HashMap<String, String>);
+impl BlockMeta {
+    /// Value stored under `key`, if any
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+    /// Store `value` under `key`, returning the value it replaces, if any
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.0.insert(key.into(), value.into())
+    }
+    /// Remove the value stored under `key`, returning it if there was one
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.0.remove(key)
+    }
+    /// Every key and value stored
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+    /// Store `value` under `key` on `block`, adding a [BlockMeta] to it if it has none.
+    /// Does nothing if `block` has been despawned by the time the command is applied.
+    pub fn attach(
+        commands: &mut Commands,
+        block: Entity,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) {
+        let (key, value) = (key.into(), value.into());
+        commands.add(move |world: &mut World| {
+            let Some(mut entity) = world.get_entity_mut(block) else {
+                return;
+            };
+            match entity.get_mut::<BlockMeta>() {
+                Some(mut meta) => {
+                    meta.insert(key, value);
+                }
+                None => {
+                    let mut meta = BlockMeta::default();
+                    meta.insert(key, value);
+                    entity.insert(meta);
+                }
+            }
+        });
+    }
+}
+
+/// Number of adjacent mines drawn over a revealed block with [RevealStyle::NumberText]
+/// or [RevealStyle::SphereWithNumber], or over a mine with [GameSettings::annotate_mines].
+#[derive(Component)]
+pub(super) struct NumberLabel {
+    adjacent_mines: u8,
+    /// Drawn smaller and outlined, so it stays legible over spheres of the same color
+    over_spheres: bool,
+}
+
+/// Disc shown by a revealed block with [RevealStyle::Billboard], kept facing the camera at
+/// a constant size on screen by [face_billboards].
+#[derive(Component)]
+pub(super) struct Billboard;
+
+/// Blocks already visited while sweeping the cursor with the reveal button held.
+/// A new session starts whenever the reveal button is pressed.
+#[derive(Resource, Default)]
+pub(super) struct SweepSession {
+    visited: HashSet<Entity>,
+}
+
 #[derive(Resource)]
 pub(super) struct BlockMaterials {
     hidden: Handle<StandardMaterial>,
+    /// Hidden blocks that would be cleared by chording the hovered block
+    preview: Handle<StandardMaterial>,
     marked: Handle<StandardMaterial>,
-    blue: Handle<StandardMaterial>,
-    green: Handle<StandardMaterial>,
-    red: Handle<StandardMaterial>,
-    orange: Handle<StandardMaterial>,
-    purple: Handle<StandardMaterial>,
+    /// Revealed numbers, indexed like [GameSettings::count_colors]
+    counts: Vec<Handle<StandardMaterial>>,
+    /// Unlit versions of [BlockMaterials::counts], for [RevealStyle::Billboard] discs
+    billboards: Vec<Handle<StandardMaterial>>,
     mine: Handle<StandardMaterial>,
+    marked_mine: Handle<StandardMaterial>,
+    missed_mine: Handle<StandardMaterial>,
+    empty: Handle<StandardMaterial>,
+    /// Stripes on marked blocks, if [ColorScheme::flag_pattern] is set
+    marked_pattern: Option<Handle<Image>>,
+}
+impl BlockMaterials {
+    fn handles(&self) -> impl Iterator<Item = &Handle<StandardMaterial>> {
+        [
+            &self.hidden,
+            &self.preview,
+            &self.marked,
+            &self.mine,
+            &self.marked_mine,
+            &self.missed_mine,
+            &self.empty,
+        ]
+        .into_iter()
+        .chain(&self.counts)
+        .chain(&self.billboards)
+    }
+    /// Material of hidden blocks
+    pub(super) fn hidden(&self) -> Handle<StandardMaterial> {
+        self.hidden.clone()
+    }
+    /// Material of hidden blocks in the chord preview
+    pub(super) fn preview(&self) -> Handle<StandardMaterial> {
+        self.preview.clone()
+    }
+    /// Material used to indicate a number of adjacent mines
+    fn count(&self, adjacent_mines: u8) -> Handle<StandardMaterial> {
+        self.counts[count_index(adjacent_mines, self.counts.len())].clone()
+    }
+    /// Unlit material used to indicate a number of adjacent mines on a [Billboard]
+    fn billboard(&self, adjacent_mines: u8) -> Handle<StandardMaterial> {
+        self.billboards[count_index(adjacent_mines, self.billboards.len())].clone()
+    }
+}
+
+/// Index of the color for `adjacent_mines` among `colors` colors from
+/// [GameSettings::count_colors]
+fn count_index(adjacent_mines: u8, colors: usize) -> usize {
+    usize::from(adjacent_mines).clamp(1, colors) - 1
 }
 
-enum BlockDisplay {
+/// Meshes generated at startup rather than loaded from [GameAssets]
+#[derive(Resource)]
+pub(super) struct BlockMeshes {
+    /// Marker for revealed blocks with no adjacent mines, and its offset within the cell.
+    empty: Option<(Handle<Mesh>, Transform)>,
+    /// Replacements for the modeled sphere clusters, if [BlockStyle::sphere_detail] is set
+    spheres: Option<SphereClusters>,
+    /// Disc with a diameter of 1, facing +Z, for [RevealStyle::Billboard]
+    billboard: Handle<Mesh>,
+}
+
+/// Sphere clusters matching the `SingleN` and `OrbitN` meshes of [GameAssets],
+/// indexed by number of spheres minus one
+struct SphereClusters {
+    singles: [Handle<Mesh>; 4],
+    orbits: [Handle<Mesh>; 4],
+}
+
+/// Everything needed to change how a block is displayed
+#[derive(SystemParam)]
+pub(super) struct BlockVisuals<'w> {
+    game_assets: Res<'w, GameAssets>,
+    materials: Res<'w, BlockMaterials>,
+    meshes: Res<'w, BlockMeshes>,
+    settings: Res<'w, GameSettings>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum BlockDisplay {
     Hidden,
+    Preview,
     Marked,
-    Revealed { adjacent_mines: u8 },
+    Revealed {
+        adjacent_mines: u8,
+    },
     RevealedMine,
     MarkedMine,
     MissedMine,
+    /// Label over a mine, leaving its mesh as it is
+    MineCount {
+        adjacent_mines: u8,
+    },
 }
 impl BlockDisplay {
-    fn spawn(
-        &self,
-        game_assets: &Res<GameAssets>,
-        mat: &Res<BlockMaterials>,
-        block: Entity,
-        commands: &mut Commands,
-    ) {
+    fn spawn(&self, visuals: &BlockVisuals, block: Entity, commands: &mut Commands) {
         let mut e = commands.get_or_spawn(block);
+        let game_assets = &visuals.game_assets;
+        let mat = &visuals.materials;
+        let meshes = &visuals.meshes;
         let sweeper_objects = game_assets.sweeper_objects.unwrap();
         match self {
             Self::Hidden => e.insert((sweeper_objects.block_merged.clone(), mat.hidden.clone())),
+            Self::Preview => e.insert(mat.preview.clone()),
             Self::Marked => e.insert(mat.marked.clone()),
             Self::Revealed { adjacent_mines } => {
-                e.remove::<Handle<Mesh>>();
-                e.remove::<Handle<StandardMaterial>>();
-                let fives_place = adjacent_mines / 5;
-                let ones_place = adjacent_mines % 5;
-                if fives_place == 0 {
-                    if let Some((child_mesh, child_mat)) = match adjacent_mines {
-                        0 => None,
-                        1 => Some((sweeper_objects.single1.clone(), mat.blue.clone())),
-                        2 => Some((sweeper_objects.single2.clone(), mat.green.clone())),
-                        3 => Some((sweeper_objects.single3.clone(), mat.red.clone())),
-                        4 => Some((sweeper_objects.single4.clone(), mat.orange.clone())),
-                        _ => panic!("if fives_place is 0, adjacent should be 0..5"),
-                    } {
+                // Five or more mines shown as spheres put a ring on the block itself, overwriting
+                // its mesh and material. Anything else leaves the block with no mesh of its own.
+                let keeps_mesh = matches!(
+                    visuals.settings.reveal_style,
+                    RevealStyle::Sphere | RevealStyle::SphereWithNumber
+                ) && *adjacent_mines >= 5;
+                if !keeps_mesh {
+                    e.remove::<(Handle<Mesh>, Handle<StandardMaterial>)>();
+                }
+                if *adjacent_mines == 0 {
+                    if let Some((empty_mesh, empty_transform)) = &meshes.empty {
                         let child = e
                             .commands()
                             .spawn(PbrBundle {
-                                mesh: child_mesh,
-                                material: child_mat,
-                                transform: Transform::from_scale(Vec3::splat(1.5)),
+                                mesh: empty_mesh.clone(),
+                                material: mat.empty.clone(),
+                                transform: *empty_transform,
                                 ..default()
                             })
                             .id();
-                        e.add_child(child)
-                    } else {
-                        &mut e
+                        e.add_child(child);
                     }
                 } else {
-                    if let Some((orbit_mesh, orbit_mat)) = match ones_place {
-                        0 => None,
-                        1 => Some((sweeper_objects.orbit1.clone(), mat.blue.clone())),
-                        2 => Some((sweeper_objects.orbit2.clone(), mat.green.clone())),
-                        3 => Some((sweeper_objects.orbit3.clone(), mat.red.clone())),
-                        4 => Some((sweeper_objects.orbit4.clone(), mat.orange.clone())),
-                        _ => panic!("ones_place must be be 0..5"),
-                    } {
-                        let orbit = e
-                            .commands()
-                            .spawn(PbrBundle {
-                                mesh: orbit_mesh,
-                                material: orbit_mat,
-                                ..default()
-                            })
-                            .id();
-                        e.add_child(orbit);
+                    match visuals.settings.reveal_style {
+                        RevealStyle::Sphere => {
+                            Self::spawn_spheres(&mut e, *adjacent_mines, visuals)
+                        }
+                        RevealStyle::ShrunkCube => {
+                            let child = e
+                                .commands()
+                                .spawn(PbrBundle {
+                                    mesh: sweeper_objects.block_merged.clone(),
+                                    material: mat.count(*adjacent_mines),
+                                    transform: Transform::from_scale(Vec3::splat(
+                                        SHRUNK_CUBE_SCALE,
+                                    )),
+                                    ..default()
+                                })
+                                .id();
+                            e.add_child(child);
+                        }
+                        RevealStyle::NumberText => {
+                            e.insert(NumberLabel {
+                                adjacent_mines: *adjacent_mines,
+                                over_spheres: false,
+                            });
+                        }
+                        RevealStyle::SphereWithNumber => {
+                            Self::spawn_spheres(&mut e, *adjacent_mines, visuals);
+                            e.insert(NumberLabel {
+                                adjacent_mines: *adjacent_mines,
+                                over_spheres: true,
+                            });
+                        }
+                        RevealStyle::Billboard => {
+                            let child = e
+                                .commands()
+                                .spawn((
+                                    PbrBundle {
+                                        mesh: meshes.billboard.clone(),
+                                        material: mat.billboard(*adjacent_mines),
+                                        ..default()
+                                    },
+                                    Billboard,
+                                ))
+                                .id();
+                            e.add_child(child);
+                            e.insert(NumberLabel {
+                                adjacent_mines: *adjacent_mines,
+                                over_spheres: true,
+                            });
+                        }
                     }
-                    e.insert((sweeper_objects.ring.clone(), mat.purple.clone()));
-                    let (child_mesh, child_mat) = match fives_place {
-                        1 => (sweeper_objects.single1.clone(), mat.blue.clone()),
-                        2 => (sweeper_objects.single2.clone(), mat.green.clone()),
-                        3 => (sweeper_objects.single3.clone(), mat.red.clone()),
-                        4 => (sweeper_objects.single4.clone(), mat.orange.clone()),
-                        _ => panic!(
-                            "more than 24 adjacent mines is not supported (should not be possible)"
-                        ),
-                    };
-                    let child = e
-                        .commands()
-                        .spawn(PbrBundle {
-                            mesh: child_mesh,
-                            material: child_mat,
-                            transform: Transform::from_scale(Vec3::splat(3.0)),
-                            ..default()
-                        })
-                        .id();
-                    e.add_child(child)
                 }
+                &mut e
             }
             Self::RevealedMine => e.insert((
                 game_assets.sweeper_objects.unwrap().mine_merged.clone(),
@@ -182,149 +502,760 @@ impl BlockDisplay {
             )),
             Self::MarkedMine => e.insert((
                 game_assets.sweeper_objects.unwrap().mine_merged.clone(),
-                mat.green.clone(),
+                mat.marked_mine.clone(),
             )),
             Self::MissedMine => e.insert((
                 game_assets.sweeper_objects.unwrap().mine_merged.clone(),
-                mat.red.clone(),
+                mat.missed_mine.clone(),
             )),
+            Self::MineCount { adjacent_mines } => e.insert(NumberLabel {
+                adjacent_mines: *adjacent_mines,
+                over_spheres: true,
+            }),
+        };
+    }
+    /// Show `view` on a block, replacing whatever it showed before.
+    /// Only changes how the block looks, not the state of the [Block].
+    pub(super) fn show_view(
+        view: CellView,
+        visuals: &BlockVisuals,
+        block: Entity,
+        commands: &mut Commands,
+    ) {
+        commands
+            .entity(block)
+            .despawn_descendants()
+            .remove::<NumberLabel>();
+        Self::Hidden.spawn(visuals, block, commands);
+        let display = match view {
+            CellView::Hidden => return,
+            CellView::Flagged => Self::Marked,
+            CellView::Revealed(Contains::Mine) => Self::RevealedMine,
+            CellView::Revealed(Contains::Empty { adjacent_mines }) => {
+                Self::Revealed { adjacent_mines }
+            }
+        };
+        display.spawn(visuals, block, commands);
+    }
+    /// Show the number of adjacent mines as clusters of spheres, ringed for every five.
+    fn spawn_spheres(e: &mut EntityCommands, adjacent_mines: u8, visuals: &BlockVisuals) {
+        let mat = &visuals.materials;
+        let sweeper_objects = visuals.game_assets.sweeper_objects.unwrap();
+        let (singles, orbits) = match &visuals.meshes.spheres {
+            Some(spheres) => (spheres.singles.clone(), spheres.orbits.clone()),
+            None => (
+                [
+                    sweeper_objects.single1.clone(),
+                    sweeper_objects.single2.clone(),
+                    sweeper_objects.single3.clone(),
+                    sweeper_objects.single4.clone(),
+                ],
+                [
+                    sweeper_objects.orbit1.clone(),
+                    sweeper_objects.orbit2.clone(),
+                    sweeper_objects.orbit3.clone(),
+                    sweeper_objects.orbit4.clone(),
+                ],
+            ),
         };
+        let fives_place = adjacent_mines / 5;
+        let ones_place = adjacent_mines % 5;
+        if fives_place == 0 {
+            if let Some((child_mesh, child_mat)) = match adjacent_mines {
+                0 => None,
+                1 => Some((singles[0].clone(), mat.count(1))),
+                2 => Some((singles[1].clone(), mat.count(2))),
+                3 => Some((singles[2].clone(), mat.count(3))),
+                4 => Some((singles[3].clone(), mat.count(4))),
+                _ => panic!("if fives_place is 0, adjacent should be 0..5"),
+            } {
+                let child = e
+                    .commands()
+                    .spawn(PbrBundle {
+                        mesh: child_mesh,
+                        material: child_mat,
+                        transform: Transform::from_scale(Vec3::splat(1.5)),
+                        ..default()
+                    })
+                    .id();
+                e.add_child(child);
+            }
+        } else {
+            if let Some((orbit_mesh, orbit_mat)) = match ones_place {
+                0 => None,
+                1 => Some((orbits[0].clone(), mat.count(1))),
+                2 => Some((orbits[1].clone(), mat.count(2))),
+                3 => Some((orbits[2].clone(), mat.count(3))),
+                4 => Some((orbits[3].clone(), mat.count(4))),
+                _ => panic!("ones_place must be be 0..5"),
+            } {
+                let orbit = e
+                    .commands()
+                    .spawn(PbrBundle {
+                        mesh: orbit_mesh,
+                        material: orbit_mat,
+                        ..default()
+                    })
+                    .id();
+                e.add_child(orbit);
+            }
+            e.insert((sweeper_objects.ring.clone(), mat.count(5)));
+            let (child_mesh, child_mat) = match fives_place {
+                1 => (singles[0].clone(), mat.count(1)),
+                2 => (singles[1].clone(), mat.count(2)),
+                3 => (singles[2].clone(), mat.count(3)),
+                4 => (singles[3].clone(), mat.count(4)),
+                _ => {
+                    panic!("more than 24 adjacent mines is not supported (should not be possible)")
+                }
+            };
+            let child = e
+                .commands()
+                .spawn(PbrBundle {
+                    mesh: child_mesh,
+                    material: child_mat,
+                    transform: Transform::from_scale(Vec3::splat(3.0)),
+                    ..default()
+                })
+                .id();
+            e.add_child(child);
+        }
     }
 }
 
-fn calculate_position(index: [usize; 3], dim: [usize; 3]) -> Vec3 {
-    Vec3::new(
-        (index[0] as isize - dim[0] as isize / 2) as f32,
-        (index[1] as isize - dim[1] as isize / 2) as f32,
-        (index[2] as isize - dim[2] as isize / 2) as f32,
-    )
+/// Position of the center of the block at `index`, such that the field is centered on the origin,
+/// with `scale` world units between neighboring blocks and `up_axis` pointing up.
+pub(super) fn calculate_position(
+    index: [usize; 3],
+    dim: [usize; 3],
+    scale: f32,
+    up_axis: UpAxis,
+) -> Vec3 {
+    let center = |axis: usize| index[axis] as f32 - (dim[axis] as f32 - 1.0) / 2.0;
+    up_axis.to_world(Vec3::new(center(0), center(1), center(2))) * scale
+}
+
+/// Bounding box of a block centered on `pos`, matching its rendered cube of `cube_size`
+pub(super) fn block_bounds(pos: Vec3, cube_size: f32) -> Aabb3d {
+    Cuboid::from_size(Vec3::splat(cube_size)).aabb_3d(pos, Quat::IDENTITY)
+}
+
+/// Remove the previous game's [BlockMaterials] and [BlockMeshes] from their [Assets],
+/// so they don't accumulate over many games.
+fn release_assets(
+    mut commands: Commands,
+    block_materials: Option<Res<BlockMaterials>>,
+    block_meshes: Option<Res<BlockMeshes>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if let Some(block_materials) = block_materials {
+        for handle in block_materials.handles() {
+            materials.remove(handle);
+        }
+        if let Some(pattern) = &block_materials.marked_pattern {
+            images.remove(pattern);
+        }
+        commands.remove_resource::<BlockMaterials>();
+    }
+    if let Some(block_meshes) = block_meshes {
+        if let Some((empty, _)) = &block_meshes.empty {
+            meshes.remove(empty);
+        }
+        if let Some(spheres) = &block_meshes.spheres {
+            for handle in spheres.singles.iter().chain(&spheres.orbits) {
+                meshes.remove(handle);
+            }
+        }
+        meshes.remove(&block_meshes.billboard);
+        commands.remove_resource::<BlockMeshes>();
+    }
 }
 
-/// Initialize materials that are re-used between games
+/// Initialize materials shared by every block in a game, according to [BlockStyle]
+#[allow(clippy::too_many_arguments)]
 pub(super) fn create_materials(
     mut commands: Commands,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
     asset_server: Res<AssetServer>,
+    block_style: Res<BlockStyle>,
+    color_scheme: Res<ColorScheme>,
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
 ) {
-    commands.insert_resource(BlockMaterials {
-        hidden: materials.add(StandardMaterial {
-            base_color_texture: Some(asset_server.load("concrete_02_albedo.png")),
-            metallic_roughness_texture: Some(asset_server.load("concrete_02_orm.png")),
+    let color_scheme = if game_settings.high_contrast {
+        ColorScheme::high_contrast()
+    } else {
+        color_scheme.clone()
+    };
+    if let Err(err) = color_scheme.validate() {
+        warn!("Blocks in different states will look the same: {err}");
+    }
+    let connectivity = game_settings.connectivity_for(&field_settings);
+    let count_colors = game_settings.count_colors(&color_scheme, connectivity);
+    let marked_pattern = color_scheme
+        .flag_pattern
+        .then(|| images.add(stripe_pattern()));
+    let hidden = if game_settings.high_contrast {
+        StandardMaterial {
+            base_color: Color::rgb(
+                HIGH_CONTRAST_HIDDEN_SHADE,
+                HIGH_CONTRAST_HIDDEN_SHADE,
+                HIGH_CONTRAST_HIDDEN_SHADE,
+            ),
+            perceptual_roughness: 1.0,
+            metallic: 0.0,
+            reflectance: 0.0,
+            ..default()
+        }
+    } else if game_settings.flat_shading {
+        // Without textures or highlights, each face of a cube is a single uniform shade
+        StandardMaterial {
+            base_color: Color::rgb(0.6, 0.6, 0.6),
             perceptual_roughness: 1.0,
             metallic: 0.0,
+            reflectance: 0.0,
+            ..default()
+        }
+    } else {
+        StandardMaterial {
+            base_color_texture: Some(asset_server.load("concrete_02_albedo.png")),
+            metallic_roughness_texture: Some(asset_server.load("concrete_02_orm.png")),
+            perceptual_roughness: block_style.roughness,
+            metallic: block_style.metallic,
+            reflectance: block_style.reflectance,
             normal_map_texture: Some(asset_server.load("concrete_02_normal.png")),
             ..default()
+        }
+    };
+    commands.insert_resource(BlockMaterials {
+        preview: materials.add(if game_settings.high_contrast {
+            // Lit the same from every side, so it stands out from the hidden blocks anywhere
+            StandardMaterial {
+                base_color: game_settings.highlight_color,
+                unlit: true,
+                ..default()
+            }
+        } else {
+            StandardMaterial {
+                base_color: game_settings.highlight_color,
+                emissive: game_settings.highlight_color * game_settings.highlight_intensity,
+                ..hidden.clone()
+            }
+        }),
+        hidden: materials.add(hidden),
+        marked: materials.add(StandardMaterial {
+            base_color: color_scheme.marked,
+            base_color_texture: marked_pattern.clone(),
+            ..default()
+        }),
+        counts: count_colors
+            .iter()
+            .map(|color| materials.add(*color))
+            .collect(),
+        billboards: count_colors
+            .iter()
+            .map(|color| {
+                materials.add(StandardMaterial {
+                    base_color: *color,
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .collect(),
+        mine: materials.add(color_scheme.mine),
+        marked_mine: materials.add(color_scheme.marked_mine),
+        missed_mine: materials.add(color_scheme.missed_mine),
+        empty: materials.add(StandardMaterial {
+            base_color: block_style.empty_marker_color,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
         }),
-        marked: materials.add(Color::RED),
-        blue: materials.add(Color::BLUE),
-        green: materials.add(Color::GREEN),
-        red: materials.add(Color::RED),
-        orange: materials.add(Color::ORANGE),
-        purple: materials.add(Color::PURPLE),
-        mine: materials.add(Color::DARK_GRAY),
+        marked_pattern,
     })
 }
 
+/// Diagonal light and dark stripes, to be tinted by a material's base color
+fn stripe_pattern() -> Image {
+    const SIZE: u32 = 32;
+    const STRIPE_WIDTH: u32 = 4;
+    let data = (0..SIZE * SIZE)
+        .flat_map(|n| {
+            let (x, y) = (n % SIZE, n / SIZE);
+            let shade = if ((x + y) / STRIPE_WIDTH).is_multiple_of(2) {
+                255
+            } else {
+                90
+            };
+            [shade, shade, shade, 255]
+        })
+        .collect();
+    Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Initialize meshes shared by every block in a game, according to [BlockStyle]
+pub(super) fn create_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    block_style: Res<BlockStyle>,
+    game_assets: Res<GameAssets>,
+) {
+    let empty = match block_style.empty_marker {
+        EmptyMarker::None => None,
+        EmptyMarker::Dot => Some((meshes.add(Sphere::new(0.03)), Transform::IDENTITY)),
+        EmptyMarker::Tile => Some((
+            meshes.add(Cuboid::new(0.6, 0.02, 0.6)),
+            Transform::from_xyz(0.0, -0.49, 0.0),
+        )),
+    };
+    let spheres = block_style.sphere_detail.map(|detail| {
+        let sweeper_objects = game_assets.sweeper_objects.unwrap();
+        let mut rebuild = |modeled: &Handle<Mesh>| {
+            let mesh = sphere_cluster(&meshes, modeled, &sweeper_objects.single1, detail);
+            meshes.add(mesh)
+        };
+        SphereClusters {
+            singles: [
+                &sweeper_objects.single1,
+                &sweeper_objects.single2,
+                &sweeper_objects.single3,
+                &sweeper_objects.single4,
+            ]
+            .map(&mut rebuild),
+            orbits: [
+                &sweeper_objects.orbit1,
+                &sweeper_objects.orbit2,
+                &sweeper_objects.orbit3,
+                &sweeper_objects.orbit4,
+            ]
+            .map(&mut rebuild),
+        }
+    });
+    commands.insert_resource(BlockMeshes {
+        empty,
+        spheres,
+        billboard: meshes.add(Circle::new(0.5)),
+    })
+}
+
+/// Build a copy of a modeled cluster of spheres out of icospheres with `detail` subdivisions.
+/// Every sphere in `modeled` is assumed to have as many vertices as the single sphere in
+/// `single`, so each run of that many vertices is one sphere.
+fn sphere_cluster(
+    meshes: &Assets<Mesh>,
+    modeled: &Handle<Mesh>,
+    single: &Handle<Mesh>,
+    detail: usize,
+) -> Mesh {
+    let positions = |mesh: &Mesh| -> Vec<Vec3> {
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|positions| positions.as_float3())
+            .map(|positions| positions.iter().copied().map(Vec3::from).collect())
+            .unwrap_or_default()
+    };
+    let single = positions(meshes.get(single).unwrap());
+    let radius = single.iter().map(|p| p.length()).fold(0.0, f32::max);
+    let centers: Vec<Vec3> = positions(meshes.get(modeled).unwrap())
+        .chunks(single.len().max(1))
+        .map(|sphere| sphere.iter().sum::<Vec3>() / sphere.len() as f32)
+        .collect();
+    let sphere = Sphere::new(radius)
+        .mesh()
+        .ico(detail)
+        .unwrap_or_else(|_| Sphere::new(radius).mesh().build());
+    let sphere_positions = positions(&sphere);
+    let sphere_normals: Vec<[f32; 3]> = sphere
+        .attribute(Mesh::ATTRIBUTE_NORMAL)
+        .and_then(|normals| normals.as_float3())
+        .unwrap_or_default()
+        .to_vec();
+    let sphere_indices: Vec<usize> = sphere
+        .indices()
+        .map(|i| i.iter().collect())
+        .unwrap_or_default();
+    let (mut out_positions, mut out_normals, mut out_indices) = (vec![], vec![], vec![]);
+    for center in centers {
+        let offset = out_positions.len() as u32;
+        out_positions.extend(sphere_positions.iter().map(|p| (*p + center).to_array()));
+        out_normals.extend_from_slice(&sphere_normals);
+        out_indices.extend(sphere_indices.iter().map(|&i| offset + i as u32));
+    }
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, out_positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, out_normals)
+    .with_inserted_indices(Indices::U32(out_indices))
+}
+
 /// Setup to be run when the game is started
+#[allow(clippy::too_many_arguments)]
 pub(super) fn setup(
     field_settings: Res<FieldSettings>,
+    game_settings: Res<GameSettings>,
     mut commands: Commands,
-    block_mat: Res<BlockMaterials>,
-    game_assets: Res<GameAssets>,
+    visuals: BlockVisuals,
     mut field_events: EventWriter<FieldEvent>,
+    mut stats: ResMut<GameStats>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut click_buffer: ResMut<ClickBuffer>,
 ) {
+    let scale = game_settings.world_scale;
+    let cube_size = game_settings.cube_size * scale;
+    // Every block exists from the start, but may be hidden until it's reached in the queue
+    let visibility = if game_settings.animations_enabled {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    spawn_queue.0.clear();
+    click_buffer.0.clear();
     let mut add_cube = |index, pos| {
-        let transform = Transform::from_translation(pos);
-        let bb = Cuboid::new(1.0, 1.0, 1.0).aabb_3d(transform.translation, transform.rotation);
+        // Assets are modeled as unit cubes, so scaling the block scales its mesh to cube_size
+        let transform = Transform::from_translation(pos).with_scale(Vec3::splat(cube_size));
+        let bb = block_bounds(pos, cube_size);
         let block = commands
             .spawn((
                 PbrBundle {
                     transform,
+                    visibility,
                     ..default()
                 },
                 Block::new(bb, index),
                 GamePiece,
             ))
             .id();
-        BlockDisplay::Hidden.spawn(&game_assets, &block_mat, block, &mut commands);
+        BlockDisplay::Hidden.spawn(&visuals, block, &mut commands);
+        stats.blocks += 1;
+        stats.layer_mut(index[2]).blocks += 1;
         debug!("Send FieldEvent::SpawnBlock");
         field_events.send(FieldEvent::SpawnBlock(block, index));
+        block
     };
 
     let field_size = field_settings.field_size;
+    let mut indices = vec![];
     for i in 0..field_size[0] {
         for j in 0..field_size[1] {
             for k in 0..field_size[2] {
-                let pos = calculate_position([i, j, k], field_size);
-                add_cube([i, j, k], pos);
+                if field_settings.shape.contains([i, j, k], field_size) {
+                    indices.push([i, j, k]);
+                }
             }
         }
     }
+    let up_axis = game_settings.up_axis;
+    game_settings
+        .spawn_order
+        .arrange(&mut indices, field_size, up_axis);
+    for index in indices {
+        let block = add_cube(index, calculate_position(index, field_size, scale, up_axis));
+        if game_settings.animations_enabled {
+            spawn_queue.0.push_back(block);
+        }
+    }
 }
 
+/// Blocks waiting to be shown at the start of a game, in [GameSettings::spawn_order]
+#[derive(Debug, Default, Resource)]
+pub(super) struct SpawnQueue(VecDeque<Entity>);
+
+/// Clear and mark clicks made while the field can't take them yet: while blocks are still
+/// appearing from the [SpawnQueue], or while the field is being generated. They're sent
+/// again as they were once it can, instead of being lost.
+#[derive(Default, Resource)]
+pub(super) struct ClickBuffer(VecDeque<RayEvent>);
+impl ClickBuffer {
+    /// Hold on to `event` for later, unless the buffer is full
+    fn push(&mut self, event: RayEvent) {
+        if self.0.len() < MAX_BUFFERED_CLICKS {
+            self.0.push_back(event);
+        } else {
+            debug!("Dropping click, too many are buffered");
+        }
+    }
+}
+
+/// Send the clicks in the [ClickBuffer] again, once every block has appeared
+fn release_buffered_clicks(
+    mut click_buffer: ResMut<ClickBuffer>,
+    spawn_queue: Res<SpawnQueue>,
+    mut ray_events: EventWriter<RayEvent>,
+) {
+    if !spawn_queue.0.is_empty() {
+        return;
+    }
+    for event in click_buffer.0.drain(..) {
+        debug!("Send buffered click");
+        ray_events.send(event);
+    }
+}
+
+/// Keep clicks made while the field is being generated in the [ClickBuffer], since
+/// nothing handles them in the meantime
+fn buffer_clicks_while_generating(
+    mut ray_events: EventReader<RayEvent>,
+    mut click_buffer: ResMut<ClickBuffer>,
+) {
+    for event in ray_events.read() {
+        if matches!(event, RayEvent::ClearBlock(_) | RayEvent::MarkBlock(_)) {
+            click_buffer.push(*event);
+        }
+    }
+}
+
+/// Show the blocks in the [SpawnQueue] a few at a time, so the field builds up
+/// over [SPAWN_DURATION] instead of appearing all at once.
+fn stream_spawn(
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut visibilities: Query<&mut Visibility, With<Block>>,
+    stats: Res<GameStats>,
+    time: Res<Time>,
+) {
+    if spawn_queue.0.is_empty() {
+        return;
+    }
+    let per_frame = (stats.blocks as f32 * time.delta_seconds() / SPAWN_DURATION).ceil() as usize;
+    for _ in 0..per_frame.max(1) {
+        let Some(block) = spawn_queue.0.pop_front() else {
+            break;
+        };
+        if let Ok(mut visibility) = visibilities.get_mut(block) {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(super) fn handle_ray_events(
     mut ray_events: EventReader<RayEvent>,
-    blocks: Query<(Entity, &Block)>,
-    mut block_events: EventWriter<BlockEvent>,
+    blocks: Query<(Entity, &Block, &InheritedVisibility)>,
     mut field_events: EventWriter<FieldEvent>,
+    mut missed_events: EventWriter<ClickMissedEvent>,
+    mut sweep: ResMut<SweepSession>,
+    game_settings: Res<GameSettings>,
+    ripple_mesh: Res<RippleMesh>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+    mut hovered_chord: Local<Option<[usize; 3]>>,
+    mut recently_marked: Local<HashMap<Entity, f32>>,
+    time: Res<Time>,
+    inspecting: Res<Inspecting>,
+    spawn_queue: Res<SpawnQueue>,
+    mut click_buffer: ResMut<ClickBuffer>,
+    pick_distance: Res<PickDistance>,
 ) {
+    let now = time.elapsed_seconds();
+    recently_marked.retain(|_, marked_at| now - *marked_at < MARK_COOLDOWN);
     for ray_event in ray_events.read() {
         match ray_event {
+            // Blocks still appearing can't be hit yet, so wait until they all have
+            RayEvent::ClearBlock(_) | RayEvent::MarkBlock(_) if !spawn_queue.0.is_empty() => {
+                debug!("Buffering click while blocks appear");
+                click_buffer.push(*ray_event);
+            }
+            RayEvent::ClearBlock(_) | RayEvent::SweepBlock(_) if inspecting.0 => {
+                debug!("Ignoring clear while inspecting");
+            }
+            RayEvent::MarkBlock(_) if inspecting.0 && !game_settings.inspect_allows_marking => {
+                debug!("Ignoring mark while inspecting");
+            }
             RayEvent::ClearBlock(ray) => {
-                if let Some((block, _entity, index)) = raycast_blocks(*ray, &blocks) {
-                    if !block.marked {
+                sweep.visited.clear();
+                let Some((block, entity, index, point)) =
+                    raycast_blocks(*ray, &blocks, pick_distance.0, &game_settings)
+                else {
+                    miss_click(
+                        *ray,
+                        &mut hovered_chord,
+                        &mut field_events,
+                        &mut missed_events,
+                    );
+                    continue;
+                };
+                if game_settings.animations_enabled {
+                    spawn_ripple(
+                        &mut commands,
+                        &ripple_mesh,
+                        &mut materials,
+                        point,
+                        game_settings.world_scale,
+                    );
+                }
+                sweep.visited.insert(entity);
+                if block.revealed.is_none() && !block.marked {
+                    debug!("Send FieldEvent::ClearBlock");
+                    field_events.send(FieldEvent::ClearBlock(index));
+                } else if game_settings.chording && block.chordable() {
+                    debug!("Send FieldEvent::ChordBlock");
+                    field_events.send(FieldEvent::ChordBlock(index));
+                }
+            }
+            RayEvent::SweepBlock(ray) => {
+                if let Some((block, entity, index, _)) =
+                    raycast_blocks(*ray, &blocks, pick_distance.0, &game_settings)
+                {
+                    if block.revealed.is_none() && sweep.visited.insert(entity) && !block.marked {
                         debug!("Send FieldEvent::ClearBlock");
                         field_events.send(FieldEvent::ClearBlock(index));
                     }
                 }
             }
             RayEvent::MarkBlock(ray) => {
-                if let Some((_block, entity, _index)) = raycast_blocks(*ray, &blocks) {
-                    debug!("Send BlockEvent::Mark");
-                    block_events.send(BlockEvent::Mark(entity));
+                let Some((block, entity, index, point)) =
+                    raycast_blocks(*ray, &blocks, pick_distance.0, &game_settings)
+                else {
+                    sweep.visited.clear();
+                    miss_click(
+                        *ray,
+                        &mut hovered_chord,
+                        &mut field_events,
+                        &mut missed_events,
+                    );
+                    continue;
+                };
+                if block.revealed.is_some() {
+                    continue;
+                }
+                // Ignore presses in quick succession, which would just undo the mark
+                if recently_marked.contains_key(&entity) {
+                    debug!("Ignoring repeated mark on {entity:?}");
+                    continue;
+                }
+                recently_marked.insert(entity, now);
+                if game_settings.animations_enabled {
+                    spawn_ripple(
+                        &mut commands,
+                        &ripple_mesh,
+                        &mut materials,
+                        point,
+                        game_settings.world_scale,
+                    );
+                }
+                debug!("Send FieldEvent::MarkBlock");
+                field_events.send(FieldEvent::MarkBlock(index));
+            }
+            RayEvent::HoverBlock(ray) => {
+                let chord = ray
+                    .and_then(|ray| raycast_blocks(ray, &blocks, pick_distance.0, &game_settings))
+                    .filter(|(block, ..)| game_settings.chording && block.chordable())
+                    .map(|(_, _, index, _)| index);
+                if *hovered_chord != chord {
+                    *hovered_chord = chord;
+                    field_events.send(FieldEvent::PreviewChord(chord));
                 }
             }
         }
     }
 }
 
+/// Handle a clear or mark that hit no block: no [FieldEvent] is sent except to drop the
+/// chord preview, and a [ClickMissedEvent] lets other systems cancel their own selections.
+fn miss_click(
+    ray: Ray3d,
+    hovered_chord: &mut Option<[usize; 3]>,
+    field_events: &mut EventWriter<FieldEvent>,
+    missed_events: &mut EventWriter<ClickMissedEvent>,
+) {
+    debug!("Click hit no block");
+    if hovered_chord.take().is_some() {
+        field_events.send(FieldEvent::PreviewChord(None));
+    }
+    missed_events.send(ClickMissedEvent(ray));
+}
+
+/// Find the nearest block hit by `ray` within `cast_len`, and the point where it was hit.
+/// See [pick_block] for which blocks can be hit.
 fn raycast_blocks<'a>(
     ray: Ray3d,
-    blocks: &'a Query<(Entity, &Block)>,
-) -> Option<(&'a Block, Entity, [usize; 3])> {
-    let cast = RayCast3d::from_ray(ray, 100.0);
+    blocks: &'a Query<(Entity, &Block, &InheritedVisibility)>,
+    cast_len: f32,
+    settings: &GameSettings,
+) -> Option<(&'a Block, Entity, [usize; 3], Vec3)> {
+    let (dist, hit, index) = pick_block(ray, cast_len, blocks, settings)?;
+    debug!("Block {hit:?} {index:?} hit at {dist}");
+    let (_, block, _) = blocks.get(hit).ok()?;
+    Some((block, hit, index, ray.get_point(dist)))
+}
 
-    let mut hits: Vec<_> = blocks
-        .iter()
-        .filter(|(_, block)| block.revealed.is_none())
-        .filter_map(|(entity, block)| {
+/// Find the nearest block hit by `ray` within `cast_len`, returning the distance to the hit,
+/// the block entity and its index.
+/// Hidden blocks can always be hit. Revealed blocks can be hit if they're numbered and
+/// chording is enabled, or if [GameSettings::revealed_blocks_occlude] is set.
+/// Blocks that are not visible (e.g. [Visibility::Hidden]) can't be hit.
+///
+/// Of the blocks hit within [GameSettings::pick_tolerance] of the nearest hit, the one whose
+/// center is closest to the ray wins, so grazing the edge of a block in front doesn't
+/// steal a click aimed at the block behind it, or the other way around.
+pub(super) fn pick_block<'a>(
+    ray: Ray3d,
+    cast_len: f32,
+    blocks: impl IntoIterator<Item = (Entity, &'a Block, &'a InheritedVisibility)>,
+    settings: &GameSettings,
+) -> Option<(f32, Entity, [usize; 3])> {
+    let cast = RayCast3d::from_ray(ray, cast_len);
+    let hits: Vec<_> = blocks
+        .into_iter()
+        .filter(|(_, block, visibility)| {
+            let pickable = block.revealed.is_none()
+                || settings.revealed_blocks_occlude
+                || (settings.chording && block.chordable());
+            pickable && visibility.get()
+        })
+        .filter_map(|(entity, block, _)| {
             cast.aabb_intersection_at(&block.bb)
+                .filter(|dist| dist.is_finite())
                 .map(|dist| (dist, entity, block))
         })
         .collect();
-
-    // Sort hits by distance
-    // Consider any unresolved comparisons to be equal (i.e. NaN == NaN)
-    hits.sort_unstable_by(|(a, _, _), (b, _, _)| {
-        a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
-    });
-
-    let (dist, hit, block) = hits.first()?;
-    let index = block.index;
-    debug!("Block {hit:?} {index:?} hit at {dist}");
-    Some((block, *hit, index))
+    let nearest = hits.iter().map(|(dist, ..)| *dist).min_by(f32::total_cmp)?;
+    // Tolerance is relative to a cell, so it's the same fraction of every block's size
+    let tolerance = settings.pick_tolerance * settings.cube_size * settings.world_scale;
+    let offset_from_ray = |block: &Block| {
+        let to_center = block.bb.center() - ray.origin;
+        to_center.reject_from_normalized(*ray.direction).length()
+    };
+    hits.into_iter()
+        .filter(|(dist, ..)| *dist <= nearest + tolerance)
+        .min_by(|(.., a), (.., b)| offset_from_ray(a).total_cmp(&offset_from_ray(b)))
+        .map(|(dist, entity, block)| (dist, entity, block.index))
 }
 
+/// Apply [BlockEvent]s to the state of each [Block] and the game, without touching how
+/// anything looks, so it can run without any assets (e.g. under [MinimalPlugins]).
+/// How the blocks should look now is sent on as [BlockVisualChanged], for
+/// [apply_block_visuals].
+#[allow(clippy::too_many_arguments)]
 pub(super) fn handle_block_events(
-    mut commands: Commands,
     mut block_events: EventReader<BlockEvent>,
+    mut visual_events: EventWriter<BlockVisualChanged>,
     mut blocks: Query<&mut Block>,
-    block_mat: Res<BlockMaterials>,
-    game_assets: Res<GameAssets>,
     mut next_state: ResMut<NextState<GameState>>,
     mut game_result: ResMut<GameResult>,
+    mut lost_events: EventWriter<GameLostEvent>,
+    mut stats: ResMut<GameStats>,
+    mut last_reveal: ResMut<LastReveal>,
+    timer: Res<GameTimer>,
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
 ) {
-    let mut any_blocks_cleared = false;
+    // Index and contents of the first block cleared this frame, which is the one that was clicked.
+    // Blocks revealed by flooding share its single sound.
+    let mut first_cleared = None;
     for event in block_events.read() {
         let id = event.block_id();
         let mut block = match blocks.get_mut(id) {
@@ -334,81 +1265,486 @@ pub(super) fn handle_block_events(
                 continue;
             }
         };
+        let mut show = |display| {
+            visual_events.send(BlockVisualChanged::Display {
+                block: id,
+                display,
+                replace: false,
+                animate: false,
+            });
+        };
         match event {
             BlockEvent::Clear(entity, contains) => {
                 debug!("Revealed block {entity:?}");
+                if block.revealed.is_none() {
+                    stats.revealed += 1;
+                    stats.layer_mut(block.index[2]).revealed += 1;
+                    block.revealed_at = Some(timer.elapsed);
+                }
+                // A revealed block can't also be marked
+                if block.marked {
+                    block.marked = false;
+                    stats.marked -= 1;
+                    stats.layer_mut(block.index[2]).marked -= 1;
+                }
                 block.revealed = Some(*contains);
-                any_blocks_cleared = true;
+                first_cleared.get_or_insert((block.index, *contains));
                 match *contains {
                     Contains::Mine => {
-                        BlockDisplay::RevealedMine.spawn(
-                            &game_assets,
-                            &block_mat,
-                            *entity,
-                            &mut commands,
+                        show(BlockDisplay::RevealedMine);
+                        lose(
+                            GameLostEvent::Detonation,
+                            &mut game_result,
+                            &mut next_state,
+                            &mut lost_events,
                         );
-                        *game_result = GameResult::Failure;
-                        next_state.set(GameState::GameOver);
                     }
-                    Contains::Empty { adjacent_mines } => BlockDisplay::Revealed { adjacent_mines }
-                        .spawn(&game_assets, &block_mat, *entity, &mut commands),
+                    Contains::Empty { adjacent_mines } => {
+                        visual_events.send(BlockVisualChanged::Display {
+                            block: id,
+                            display: BlockDisplay::Revealed { adjacent_mines },
+                            replace: false,
+                            animate: true,
+                        });
+                    }
                 }
             }
             BlockEvent::EndReveal(entity, contains) => {
                 // TODO: maybe on EndReveal we can maintain a wireframe of the blocks that weren't clicked?
                 debug!("Revealed block {entity:?} at end of game");
-                match *contains {
-                    Contains::Mine if block.revealed.is_some() => {
-                        BlockDisplay::MissedMine.spawn(
-                            &game_assets,
-                            &block_mat,
-                            *entity,
-                            &mut commands,
-                        );
-                    }
-                    Contains::Mine if block.marked => {
-                        BlockDisplay::MarkedMine.spawn(
-                            &game_assets,
-                            &block_mat,
-                            *entity,
-                            &mut commands,
-                        );
-                    }
-                    Contains::Mine => {
-                        BlockDisplay::RevealedMine.spawn(
-                            &game_assets,
-                            &block_mat,
-                            *entity,
-                            &mut commands,
-                        );
-                    }
-                    Contains::Empty { adjacent_mines } => BlockDisplay::Revealed { adjacent_mines }
-                        .spawn(&game_assets, &block_mat, *entity, &mut commands),
-                }
+                show(match *contains {
+                    Contains::Mine if block.revealed.is_some() => BlockDisplay::MissedMine,
+                    Contains::Mine if block.marked => BlockDisplay::MarkedMine,
+                    Contains::Mine => BlockDisplay::RevealedMine,
+                    Contains::Empty { adjacent_mines } => BlockDisplay::Revealed { adjacent_mines },
+                });
+                block.revealed = Some(*contains);
+            }
+            BlockEvent::AnnotateMine(_, adjacent_mines) => {
+                show(BlockDisplay::MineCount {
+                    adjacent_mines: *adjacent_mines,
+                });
+            }
+            BlockEvent::Update(entity, contains) => {
                 block.revealed = Some(*contains);
+                if let Contains::Empty { adjacent_mines } = *contains {
+                    visual_events.send(BlockVisualChanged::Display {
+                        block: *entity,
+                        display: BlockDisplay::Revealed { adjacent_mines },
+                        replace: true,
+                        animate: false,
+                    });
+                }
+            }
+            BlockEvent::Preview(_, highlight) => {
+                if block.revealed.is_none() && !block.marked {
+                    show(match highlight {
+                        true => BlockDisplay::Preview,
+                        false => BlockDisplay::Hidden,
+                    });
+                }
+            }
+            BlockEvent::Mark(entity) if block.revealed.is_some() => {
+                debug!("Ignoring mark on revealed block {entity:?}");
             }
             BlockEvent::Mark(entity) => match block.marked {
                 true => {
                     debug!("Unmark block {entity:?}");
                     block.marked = false;
-                    BlockDisplay::Hidden.spawn(&game_assets, &block_mat, *entity, &mut commands);
+                    stats.marked -= 1;
+                    stats.layer_mut(block.index[2]).marked -= 1;
+                    show(BlockDisplay::Hidden);
                 }
                 false => {
                     debug!("Mark block {entity:?}");
                     block.marked = true;
-                    BlockDisplay::Marked.spawn(&game_assets, &block_mat, *entity, &mut commands);
+                    stats.marked += 1;
+                    stats.layer_mut(block.index[2]).marked += 1;
+                    show(BlockDisplay::Marked);
                 }
             },
         }
     }
-    if any_blocks_cleared {
-        commands.spawn(AudioBundle {
-            source: game_assets.pop2.clone(),
-            settings: PlaybackSettings {
-                mode: PlaybackMode::Despawn,
-                ..default()
-            },
+    if let Some((index, contains)) = first_cleared {
+        let connectivity = game_settings.connectivity_for(&field_settings);
+        let flagged_neighbors = blocks
+            .iter()
+            .filter(|neighbor| {
+                let offset =
+                    [0, 1, 2].map(|axis| neighbor.index[axis] as isize - index[axis] as isize);
+                neighbor.marked && connectivity.includes(offset)
+            })
+            .count();
+        last_reveal.0 = Some(RevealInfo {
+            index,
+            contains,
+            flagged_neighbors,
         });
+        visual_events.send(BlockVisualChanged::RevealSound(contains));
+    }
+}
+
+/// A cleared block animating away with [GameSettings::reveal_anim], before it shows
+/// what it contained
+#[derive(Component)]
+pub(super) struct RevealAnimation {
+    timer: Timer,
+    /// Transform of the block before the animation, restored once it finishes
+    start: Transform,
+    end: Transform,
+    /// What to show once the animation finishes
+    display: BlockDisplay,
+}
+
+/// Change how blocks look to match their state, as sent by [handle_block_events]
+pub(super) fn apply_block_visuals(
+    mut visual_events: EventReader<BlockVisualChanged>,
+    visuals: BlockVisuals,
+    blocks: Query<(&Transform, Option<&RevealAnimation>), With<Block>>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut commands: Commands,
+) {
+    // Animations started this frame, which aren't on their blocks until the commands run
+    let mut started: HashMap<Entity, RevealAnimation> = HashMap::new();
+    for event in visual_events.read() {
+        match event {
+            BlockVisualChanged::Display {
+                block,
+                display,
+                replace,
+                animate,
+            } => {
+                let Ok((transform, animation)) = blocks.get(*block) else {
+                    continue;
+                };
+                // A newer change cuts short any animation still running on the block
+                let mut start = *transform;
+                if let Some(animation) = started.remove(block).as_ref().or(animation) {
+                    start = animation.start;
+                    commands
+                        .entity(*block)
+                        .insert(start)
+                        .remove::<RevealAnimation>();
+                }
+                if *animate && visuals.settings.animations_enabled {
+                    let to_camera = camera
+                        .get_single()
+                        .map(|camera| camera.translation() - start.translation)
+                        .unwrap_or(Vec3::Y);
+                    let end = visuals.settings.reveal_anim.end_transform(
+                        start,
+                        to_camera,
+                        visuals.settings.world_scale,
+                    );
+                    started.insert(
+                        *block,
+                        RevealAnimation {
+                            timer: Timer::from_seconds(REVEAL_ANIM_DURATION, TimerMode::Once),
+                            start,
+                            end,
+                            display: *display,
+                        },
+                    );
+                    continue;
+                }
+                if *replace {
+                    commands.entity(*block).despawn_descendants();
+                }
+                display.spawn(&visuals, *block, &mut commands);
+            }
+            BlockVisualChanged::RevealSound(contains) => {
+                let speed = match *contains {
+                    Contains::Empty { adjacent_mines } if visuals.settings.adjacency_pitch => {
+                        1.0 + ADJACENCY_PITCH_STEP * f32::from(adjacent_mines)
+                    }
+                    _ => 1.0,
+                };
+                commands.spawn(AudioBundle {
+                    source: visuals.game_assets.pop2.clone(),
+                    settings: PlaybackSettings {
+                        mode: PlaybackMode::Despawn,
+                        volume: Volume::new(visuals.settings.sfx_volume),
+                        speed,
+                        ..default()
+                    },
+                });
+            }
+        }
+    }
+    for (block, animation) in started {
+        commands.entity(block).insert(animation);
+    }
+}
+
+/// Move blocks through their [RevealAnimation], then show what they contained
+fn animate_reveals(
+    mut blocks: Query<(Entity, &mut Transform, &mut RevealAnimation)>,
+    visuals: BlockVisuals,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut transform, mut animation) in &mut blocks {
+        animation.timer.tick(time.delta());
+        if animation.timer.finished() {
+            *transform = animation.start;
+            commands.entity(entity).remove::<RevealAnimation>();
+            animation.display.spawn(&visuals, entity, &mut commands);
+            continue;
+        }
+        let t = animation.timer.fraction();
+        let t = t * t * (3.0 - 2.0 * t);
+        let (start, end) = (animation.start, animation.end);
+        transform.translation = start.translation.lerp(end.translation, t);
+        transform.rotation = start.rotation.slerp(end.rotation, t);
+        transform.scale = start.scale.lerp(end.scale, t);
+    }
+}
+
+/// Turn every [Billboard] to face the camera, and scale it to cover the same angle from
+/// the camera however far away it is, within [BILLBOARD_SIZE_RANGE].
+fn face_billboards(
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut billboards: Query<(&Parent, &mut Transform), With<Billboard>>,
+    blocks: Query<&GlobalTransform, With<Block>>,
+    game_settings: Res<GameSettings>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let (min, max) = BILLBOARD_SIZE_RANGE;
+    let cell = game_settings.world_scale;
+    for (parent, mut transform) in &mut billboards {
+        let Ok(block) = blocks.get(parent.get()) else {
+            continue;
+        };
+        let (block_scale, block_rotation, block_translation) =
+            block.to_scale_rotation_translation();
+        let distance = camera.translation().distance(block_translation);
+        let size = (distance * BILLBOARD_ANGULAR_SIZE).clamp(min * cell, max * cell);
+        // Undo the block's own transform, so only the disc's world size and rotation matter
+        transform.scale = Vec3::splat(size) / block_scale;
+        transform.rotation = block_rotation.inverse() * camera.compute_transform().rotation;
+    }
+}
+
+/// Marked block under the cursor, for [GameSettings::mark_tooltip]
+#[derive(Debug, Default, Resource)]
+struct HoveredMark(Option<Entity>);
+
+/// Keep [HoveredMark] on the marked block under the cursor, if there is one
+fn track_hovered_mark(
+    mut ray_events: EventReader<RayEvent>,
+    blocks: Query<(Entity, &Block, &InheritedVisibility)>,
+    game_settings: Res<GameSettings>,
+    pick_distance: Res<PickDistance>,
+    mut hovered: ResMut<HoveredMark>,
+) {
+    for event in ray_events.read() {
+        let RayEvent::HoverBlock(ray) = event else {
+            continue;
+        };
+        hovered.0 = ray
+            .and_then(|ray| pick_block(ray, pick_distance.0, &blocks, &game_settings))
+            .map(|(_, entity, _)| entity);
+    }
+}
+
+/// List the numbers of the revealed neighbors of the [HoveredMark] in a tooltip.
+/// Only shows what the player can already see, never what the marked block contains.
+fn display_mark_tooltip(
+    mut contexts: EguiContexts,
+    hovered: Res<HoveredMark>,
+    blocks: Query<&Block>,
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
+) {
+    let Some(marked) = hovered.0.and_then(|entity| blocks.get(entity).ok()) else {
+        return;
+    };
+    if !marked.marked {
+        return;
+    }
+    let connectivity = game_settings.connectivity_for(&field_settings);
+    let cells: HashMap<[usize; 3], CellView> = blocks
+        .iter()
+        .map(|block| (block.index, block.into()))
+        .collect();
+    let numbers: Vec<_> = connectivity
+        .neighbors(marked.index)
+        .filter_map(|index| match cells.get(&index) {
+            Some(CellView::Revealed(Contains::Empty { adjacent_mines })) if *adjacent_mines > 0 => {
+                let marks = connectivity
+                    .neighbors(index)
+                    .filter(|index| cells.get(index) == Some(&CellView::Flagged))
+                    .count();
+                Some((index, *adjacent_mines, marks))
+            }
+            _ => None,
+        })
+        .collect();
+    egui::show_tooltip_at_pointer(contexts.ctx_mut(), egui::Id::new("mark_tooltip"), |ui| {
+        ui.label(egui::RichText::new("Revealed neighbors of this mark").strong());
+        if numbers.is_empty() {
+            ui.label("No numbered blocks next to it are revealed.");
+        }
+        for ([i, j, k], adjacent_mines, marks) in numbers {
+            ui.label(format!(
+                "({i}, {j}, {k}) shows {adjacent_mines}, with {marks} marked around it"
+            ));
+        }
+    });
+}
+
+/// Paint the adjacent mine count over each revealed block with a [NumberLabel],
+/// in the [ColorScheme] color for that count.
+/// Labels are drawn farthest first so nearer numbers end up on top.
+fn draw_number_labels(
+    mut contexts: EguiContexts,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    labels: Query<(&GlobalTransform, &NumberLabel, &InheritedVisibility)>,
+    color_scheme: Res<ColorScheme>,
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
+    egui_settings: Res<EguiSettings>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    let mut visible: Vec<_> = labels
+        .iter()
+        .filter(|(_, _, visibility)| visibility.get())
+        .filter_map(|(transform, label, _)| {
+            let pos = transform.translation();
+            let screen_pos = camera.world_to_viewport(camera_transform, pos)?;
+            let dist = camera_transform.translation().distance(pos);
+            Some((dist, screen_pos, label))
+        })
+        .collect();
+    if visible.is_empty() {
+        return;
+    }
+    visible.sort_unstable_by(|(a, _, _), (b, _, _)| b.total_cmp(a));
+    let connectivity = game_settings.connectivity_for(&field_settings);
+    let count_colors = game_settings.count_colors(&color_scheme, connectivity);
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+    for (dist, screen_pos, label) in visible {
+        let [r, g, b, _] =
+            count_colors[count_index(label.adjacent_mines, count_colors.len())].as_rgba_u8();
+        let color = egui::Color32::from_rgb(r, g, b);
+        // Keep the apparent size the same whatever the scale of the world
+        let dist = dist / game_settings.world_scale;
+        let mut size = (160.0 / dist.max(1.0)).clamp(8.0, 48.0);
+        // Outlined everywhere in high contrast, and thicker
+        let outline = if game_settings.high_contrast {
+            size *= HIGH_CONTRAST_LABEL_SCALE;
+            2.0
+        } else {
+            1.0
+        };
+        let pos = ui_position(screen_pos, &egui_settings);
+        let text = label.adjacent_mines.to_string();
+        if label.over_spheres || game_settings.high_contrast {
+            let font = match label.over_spheres {
+                true => egui::FontId::proportional(size * 0.6),
+                false => egui::FontId::proportional(size),
+            };
+            for offset in [[-1.0, 0.0], [1.0, 0.0], [0.0, -1.0], [0.0, 1.0]] {
+                painter.text(
+                    pos + egui::Vec2::from(offset) * outline,
+                    egui::Align2::CENTER_CENTER,
+                    &text,
+                    font.clone(),
+                    egui::Color32::BLACK,
+                );
+            }
+            painter.text(pos, egui::Align2::CENTER_CENTER, text, font, color);
+        } else {
+            painter.text(
+                pos,
+                egui::Align2::CENTER_CENTER,
+                text,
+                egui::FontId::proportional(size),
+                color,
+            );
+        }
+    }
+}
+
+/// Mark every visible hidden block whose center is inside an [InputEvent::BoxMark] rectangle.
+/// Blocks that are already marked are left alone.
+fn box_mark(
+    mut input_events: EventReader<InputEvent>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    blocks: Query<(&Block, &GlobalTransform, &InheritedVisibility)>,
+    mut field_events: EventWriter<FieldEvent>,
+    inspecting: Res<Inspecting>,
+    game_settings: Res<GameSettings>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    for event in input_events.read() {
+        let InputEvent::BoxMark(rect) = event else {
+            continue;
+        };
+        if inspecting.0 && !game_settings.inspect_allows_marking {
+            continue;
+        }
+        for (block, transform, visibility) in &blocks {
+            if block.revealed.is_some() || block.marked || !visibility.get() {
+                continue;
+            }
+            let Some(screen_pos) =
+                camera.world_to_viewport(camera_transform, transform.translation())
+            else {
+                continue;
+            };
+            if rect.contains(screen_pos) {
+                field_events.send(FieldEvent::MarkBlock(block.index));
+            }
+        }
+    }
+}
+
+/// Make hidden blocks see-through while [InputEvent::Peek] is held. Only rendering changes.
+fn peek(
+    mut input_events: EventReader<InputEvent>,
+    block_materials: Res<BlockMaterials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in input_events.read() {
+        if let InputEvent::Peek(peeking) = event {
+            set_peeking(*peeking, &block_materials, &mut materials);
+        }
+    }
+}
+
+/// Make sure hidden blocks are opaque again if the game ends mid-peek
+fn stop_peeking(
+    block_materials: Res<BlockMaterials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    set_peeking(false, &block_materials, &mut materials);
+}
+
+fn set_peeking(
+    peeking: bool,
+    block_materials: &BlockMaterials,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    for handle in [&block_materials.hidden, &block_materials.preview] {
+        let Some(material) = materials.get_mut(handle) else {
+            continue;
+        };
+        if peeking {
+            material.base_color.set_a(PEEK_ALPHA);
+            material.alpha_mode = AlphaMode::Blend;
+        } else {
+            material.base_color.set_a(1.0);
+            material.alpha_mode = AlphaMode::Opaque;
+        }
     }
 }
 
@@ -418,3 +1754,429 @@ fn block_gizmos(mut gizmos: Gizmos, blocks: Query<&Transform, With<Block>>) {
         gizmos.cuboid(*tf, Color::RED);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::loader::{Loadable, SweeperObjects};
+
+    #[test]
+    fn bounds_match_the_rendered_cube() {
+        for (cube_size, world_scale) in [(1.0, 1.0), (0.7, 1.0), (0.5, 2.5)] {
+            let size = cube_size * world_scale;
+            let pos = calculate_position([1, 0, 2], [3, 3, 3], world_scale, UpAxis::Y);
+            let bb = block_bounds(pos, size);
+            // Blocks are unit cubes scaled by their transform
+            let rendered = Transform::from_translation(pos).with_scale(Vec3::splat(size));
+            assert_eq!(bb.center(), rendered.translation);
+            assert!((bb.half_size() * 2.0 - rendered.scale).abs().max_element() < 1e-6);
+        }
+    }
+
+    /// Unit blocks at `indices` of a field of `size`, spaced as they would be by [setup],
+    /// with an entity for each
+    fn unit_blocks(indices: &[[usize; 3]], size: [usize; 3]) -> Vec<(Entity, Block)> {
+        indices
+            .iter()
+            .enumerate()
+            .map(|(n, &index)| {
+                let pos = calculate_position(index, size, 1.0, UpAxis::Y);
+                let entity = Entity::from_raw(n as u32);
+                (entity, Block::new(block_bounds(pos, 1.0), index))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn picks_the_visible_block_behind_a_sliced_away_one() {
+        // Two blocks in a row along the ray, the front one hidden by a layer slice
+        let blocks = unit_blocks(&[[0, 0, 1], [0, 0, 0]], [1, 1, 2]);
+        let visibility = [InheritedVisibility::HIDDEN, InheritedVisibility::VISIBLE];
+        let ray = Ray3d::new(Vec3::new(0.0, 0.0, 10.0), Vec3::NEG_Z);
+        let pick = |visibility: [InheritedVisibility; 2]| {
+            let candidates = blocks
+                .iter()
+                .zip(&visibility)
+                .map(|((entity, block), visibility)| (*entity, block, visibility));
+            pick_block(ray, 100.0, candidates, &GameSettings::default()).map(|(_, _, index)| index)
+        };
+        assert_eq!(pick(visibility), Some([0, 0, 0]));
+        assert_eq!(pick([InheritedVisibility::VISIBLE; 2]), Some([0, 0, 1]));
+    }
+
+    #[test]
+    fn revealed_blocks_only_shadow_hidden_ones_if_asked() {
+        // A revealed zero in front of a hidden block, along the ray
+        let mut blocks = unit_blocks(&[[0, 0, 1], [0, 0, 0]], [1, 1, 2]);
+        blocks[0].1.revealed = Some(Contains::Empty { adjacent_mines: 0 });
+        let ray = Ray3d::new(Vec3::new(0.0, 0.0, 10.0), Vec3::NEG_Z);
+        let pick = |revealed_blocks_occlude| {
+            let settings = GameSettings {
+                revealed_blocks_occlude,
+                ..default()
+            };
+            let candidates = blocks
+                .iter()
+                .map(|(entity, block)| (*entity, block, &InheritedVisibility::VISIBLE));
+            pick_block(ray, 100.0, candidates, &settings).map(|(_, _, index)| index)
+        };
+        assert_eq!(pick(false), Some([0, 0, 0]));
+        assert_eq!(pick(true), Some([0, 0, 1]));
+    }
+
+    #[test]
+    fn fields_are_centered_on_the_origin() {
+        for dim in [[1, 1, 1], [2, 2, 2], [3, 4, 5], [10, 1, 7]] {
+            let [x, y, z] = dim;
+            let indices =
+                (0..x).flat_map(|i| (0..y).flat_map(move |j| (0..z).map(move |k| [i, j, k])));
+            let sum: Vec3 = indices
+                .map(|index| calculate_position(index, dim, 2.0, UpAxis::Z))
+                .sum();
+            let center = sum / (x * y * z) as f32;
+            assert!(
+                center.abs().max_element() < 1e-5,
+                "{dim:?} is centered on {center}"
+            );
+        }
+    }
+
+    /// App with the assets and settings to create block materials and meshes, and the
+    /// modeled meshes loaded as placeholders
+    fn asset_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<StandardMaterial>()
+            .init_asset::<Mesh>()
+            .init_asset::<Image>()
+            .init_resource::<GameSettings>()
+            .init_resource::<FieldSettings>()
+            .insert_resource(BlockStyle {
+                empty_marker: EmptyMarker::Tile,
+                sphere_detail: None,
+                ..default()
+            })
+            .insert_resource(ColorScheme {
+                flag_pattern: true,
+                ..default()
+            });
+        let mut meshes = app.world.resource_mut::<Assets<Mesh>>();
+        let mut mesh = || meshes.add(Cuboid::default());
+        let sweeper_objects = SweeperObjects {
+            block_merged: mesh(),
+            mine_merged: mesh(),
+            ring: mesh(),
+            single1: mesh(),
+            single2: mesh(),
+            single3: mesh(),
+            single4: mesh(),
+            orbit1: mesh(),
+            orbit2: mesh(),
+            orbit3: mesh(),
+            orbit4: mesh(),
+        };
+        app.insert_resource(GameAssets {
+            sweeper_objects: Loadable::Loaded(sweeper_objects),
+            ..default()
+        });
+        app
+    }
+
+    #[test]
+    fn restarts_do_not_accumulate_block_assets() {
+        let mut app = asset_app();
+        app.add_systems(
+            Update,
+            (release_assets, (create_materials, create_meshes)).chain(),
+        );
+        let counts = |app: &App| {
+            let world = &app.world;
+            (
+                world.resource::<Assets<StandardMaterial>>().len(),
+                world.resource::<Assets<Mesh>>().len(),
+                world.resource::<Assets<Image>>().len(),
+            )
+        };
+        app.update();
+        let first_game = counts(&app);
+        assert!(first_game.0 > 0 && first_game.1 > 0 && first_game.2 > 0);
+        for _ in 0..5 {
+            app.update();
+            assert_eq!(counts(&app), first_game);
+        }
+    }
+
+    #[test]
+    fn revealed_blocks_are_never_marked() {
+        use super::super::minefield::testing::*;
+        let mut app = board_app(default(), board([3, 1, 1], &[[0, 0, 0]]));
+        let one = Contains::Empty { adjacent_mines: 1 };
+        let zero = Contains::Empty { adjacent_mines: 0 };
+        let (marked_first, revealed_first) = (
+            entity_at(&mut app, [1, 0, 0]),
+            entity_at(&mut app, [2, 0, 0]),
+        );
+        app.world.resource_mut::<Events<BlockEvent>>().send_batch([
+            BlockEvent::Mark(marked_first),
+            BlockEvent::Clear(marked_first, one),
+            BlockEvent::Clear(revealed_first, zero),
+            BlockEvent::Mark(revealed_first),
+        ]);
+        app.update();
+        assert_eq!(block_at(&mut app, [1, 0, 0]), (Some(one), false));
+        assert_eq!(block_at(&mut app, [2, 0, 0]), (Some(zero), false));
+        assert_eq!(app.world.resource::<GameStats>().marked, 0);
+    }
+
+    /// App handling [RayEvent]s on unit blocks at `indices` of a field of `size`, releasing
+    /// buffered clicks as the blocks in the [SpawnQueue] appear
+    fn ray_app(indices: &[[usize; 3]], size: [usize; 3]) -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .insert_resource(RippleMesh(Handle::default()))
+            .insert_resource(PickDistance(100.0))
+            .init_resource::<GameSettings>()
+            .init_resource::<GameStats>()
+            .init_resource::<Inspecting>()
+            .init_resource::<SweepSession>()
+            .init_resource::<SpawnQueue>()
+            .init_resource::<ClickBuffer>()
+            .add_event::<RayEvent>()
+            .add_event::<FieldEvent>()
+            .add_event::<ClickMissedEvent>()
+            .add_systems(
+                Update,
+                (stream_spawn, release_buffered_clicks, handle_ray_events).chain(),
+            );
+        for (_, block) in unit_blocks(indices, size) {
+            app.world
+                .spawn((block, Visibility::Inherited, InheritedVisibility::VISIBLE));
+        }
+        app
+    }
+
+    /// Run a frame with `ray_events`, some `seconds` after the last one
+    fn click(app: &mut App, seconds: f32, ray_events: impl IntoIterator<Item = RayEvent>) {
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.world
+            .resource_mut::<Events<RayEvent>>()
+            .send_batch(ray_events);
+        app.update();
+    }
+
+    /// Every `E` sent since this was last called
+    fn sent<E: Event>(app: &mut App) -> Vec<E> {
+        app.world.resource_mut::<Events<E>>().drain().collect()
+    }
+
+    /// Ray straight down the z axis at the middle of the field
+    fn ray() -> Ray3d {
+        ray_at(0.0)
+    }
+
+    /// Ray straight down the z axis, `x` along from the middle of the field
+    fn ray_at(x: f32) -> Ray3d {
+        Ray3d::new(Vec3::new(x, 0.0, 10.0), Vec3::NEG_Z)
+    }
+
+    #[test]
+    fn quick_repeated_marks_toggle_once() {
+        let mut app = ray_app(&[[0, 0, 0]], [1, 1, 1]);
+        let mark = RayEvent::MarkBlock(ray());
+        click(&mut app, 0.0, [mark]);
+        click(&mut app, MARK_COOLDOWN / 2.0, [mark]);
+        assert!(matches!(
+            sent::<FieldEvent>(&mut app)[..],
+            [FieldEvent::MarkBlock([0, 0, 0])]
+        ));
+        // Presses far enough apart are separate toggles
+        click(&mut app, MARK_COOLDOWN, [mark]);
+        assert!(matches!(
+            sent::<FieldEvent>(&mut app)[..],
+            [FieldEvent::MarkBlock([0, 0, 0])]
+        ));
+    }
+
+    #[test]
+    fn grazing_the_edge_of_the_nearer_block_picks_the_one_aimed_at() {
+        let blocks = unit_blocks(&[[0, 0, 0], [1, 0, 0]], [2, 1, 1]);
+        // A steep ray entering the top of the left block 0.01 from the seam, which clips
+        // its corner just before running into the side of the right one
+        let ray = Ray3d::new(Vec3::new(-5.01, 0.0, 10.5), Vec3::new(0.5, 0.0, -1.0));
+        let pick = |pick_tolerance| {
+            let settings = GameSettings {
+                pick_tolerance,
+                ..default()
+            };
+            let candidates = blocks
+                .iter()
+                .map(|(entity, block)| (*entity, block, &InheritedVisibility::VISIBLE));
+            pick_block(ray, 100.0, candidates, &settings).map(|(_, _, index)| index)
+        };
+        // Nearest hit first would be the left block
+        assert_eq!(pick(0.0), Some([0, 0, 0]));
+        assert_eq!(
+            pick(GameSettings::default().pick_tolerance),
+            Some([1, 0, 0])
+        );
+    }
+
+    #[test]
+    fn missed_clicks_only_drop_pending_state() {
+        // A revealed 1 on the left, which can be chorded, and a hidden block on the right
+        let mut app = ray_app(&[[0, 0, 0], [1, 0, 0]], [2, 1, 1]);
+        let world = &mut app.world;
+        for mut block in world.query::<&mut Block>().iter_mut(world) {
+            if block.index == [0, 0, 0] {
+                block.revealed = Some(Contains::Empty { adjacent_mines: 1 });
+            }
+        }
+        click(&mut app, 0.0, [RayEvent::HoverBlock(Some(ray_at(-0.5)))]);
+        click(&mut app, 0.0, [RayEvent::ClearBlock(ray_at(0.5))]);
+        assert!(matches!(
+            sent::<FieldEvent>(&mut app)[..],
+            [
+                FieldEvent::PreviewChord(Some([0, 0, 0])),
+                FieldEvent::ClearBlock([1, 0, 0])
+            ]
+        ));
+        assert!(!app.world.resource::<SweepSession>().visited.is_empty());
+
+        let miss = ray_at(5.0);
+        click(&mut app, 0.0, [RayEvent::ClearBlock(miss)]);
+        // Nothing is cleared or marked, but the chord preview is dropped
+        assert!(matches!(
+            sent::<FieldEvent>(&mut app)[..],
+            [FieldEvent::PreviewChord(None)]
+        ));
+        assert!(app.world.resource::<SweepSession>().visited.is_empty());
+        click(&mut app, 0.0, [RayEvent::MarkBlock(miss)]);
+        assert!(sent::<FieldEvent>(&mut app).is_empty());
+        let missed = sent::<ClickMissedEvent>(&mut app);
+        assert_eq!(missed.len(), 2);
+        assert!(missed.iter().all(|missed| missed.0.origin == miss.origin));
+    }
+
+    #[test]
+    fn clicks_while_blocks_appear_take_effect_afterwards() {
+        let mut app = ray_app(&[[0, 0, 0], [1, 0, 0]], [2, 1, 1]);
+        let world = &mut app.world;
+        let blocks: Vec<_> = world
+            .query_filtered::<Entity, With<Block>>()
+            .iter(world)
+            .collect();
+        world.resource_mut::<SpawnQueue>().0.extend(blocks);
+        // One block appears per frame here, so both clicks land while the second is queued
+        click(
+            &mut app,
+            0.0,
+            [
+                RayEvent::ClearBlock(ray_at(-0.5)),
+                RayEvent::MarkBlock(ray_at(0.5)),
+            ],
+        );
+        assert!(sent::<FieldEvent>(&mut app).is_empty());
+        click(&mut app, 0.1, []);
+        assert!(matches!(
+            sent::<FieldEvent>(&mut app)[..],
+            [
+                FieldEvent::ClearBlock([0, 0, 0]),
+                FieldEvent::MarkBlock([1, 0, 0])
+            ]
+        ));
+        assert!(app.world.resource::<ClickBuffer>().0.is_empty());
+    }
+
+    #[test]
+    fn clicking_a_revealed_block_in_front_clears_nothing_behind_it() {
+        let mut app = ray_app(&[[0, 0, 1], [0, 0, 0]], [1, 1, 2]);
+        let world = &mut app.world;
+        for mut block in world.query::<&mut Block>().iter_mut(world) {
+            if block.index == [0, 0, 1] {
+                block.revealed = Some(Contains::Empty { adjacent_mines: 0 });
+            }
+        }
+        app.world
+            .resource_mut::<GameSettings>()
+            .revealed_blocks_occlude = true;
+        click(&mut app, 0.0, [RayEvent::ClearBlock(ray())]);
+        assert!(!sent::<FieldEvent>(&mut app)
+            .iter()
+            .any(|event| matches!(event, FieldEvent::ClearBlock(_))));
+        // Without it the click goes through to the hidden block
+        app.world
+            .resource_mut::<GameSettings>()
+            .revealed_blocks_occlude = false;
+        click(&mut app, 0.0, [RayEvent::ClearBlock(ray())]);
+        assert!(matches!(
+            sent::<FieldEvent>(&mut app)[..],
+            [FieldEvent::ClearBlock([0, 0, 0])]
+        ));
+    }
+
+    #[test]
+    fn revealed_blocks_keep_a_mesh_only_when_they_need_one() {
+        let mut app = asset_app();
+        app.world.resource_mut::<GameSettings>().reveal_style = RevealStyle::Sphere;
+        app.add_systems(Startup, (create_materials, create_meshes))
+            .add_event::<BlockVisualChanged>()
+            .add_systems(Update, apply_block_visuals);
+        app.update();
+
+        let sweeper_objects = app.world.resource::<GameAssets>().sweeper_objects.unwrap();
+        let (block_mesh, ring) = (
+            sweeper_objects.block_merged.clone(),
+            sweeper_objects.ring.clone(),
+        );
+        let hidden = app.world.resource::<BlockMaterials>().hidden.clone();
+        let empty_marker = app.world.resource::<BlockMeshes>().empty.clone().unwrap().0;
+        let five = app.world.resource::<BlockMaterials>().count(5);
+        let reveal = |app: &mut App, adjacent_mines| {
+            let block = app
+                .world
+                .spawn((
+                    Block::new(block_bounds(Vec3::ZERO, 1.0), [0, 0, 0]),
+                    PbrBundle {
+                        mesh: block_mesh.clone(),
+                        material: hidden.clone(),
+                        ..default()
+                    },
+                ))
+                .id();
+            app.world.send_event(BlockVisualChanged::Display {
+                block,
+                display: BlockDisplay::Revealed { adjacent_mines },
+                replace: true,
+                animate: false,
+            });
+            app.update();
+            let meshes: Vec<_> = app
+                .world
+                .get::<Children>(block)
+                .map(|children| children.iter())
+                .into_iter()
+                .flatten()
+                .map(|child| app.world.get::<Handle<Mesh>>(*child).unwrap().clone())
+                .collect();
+            let entity = app.world.entity(block);
+            let own = entity
+                .get::<Handle<Mesh>>()
+                .cloned()
+                .zip(entity.get::<Handle<StandardMaterial>>().cloned());
+            (own, meshes)
+        };
+        // A zero loses its mesh, leaving only the empty marker
+        assert_eq!(reveal(&mut app, 0), (None, vec![empty_marker]));
+        // A small number loses its mesh for the spheres around it
+        let (own, children) = reveal(&mut app, 2);
+        assert_eq!((own, children.len()), (None, 1));
+        // Five or more put the ring in place of the block's mesh and material
+        let (own, children) = reveal(&mut app, 5);
+        assert_eq!((own, children.len()), (Some((ring, five)), 1));
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use super::block::Block;
+use super::minefield::{Contains, Minefield};
+
+/// What the player can currently see of a single cell of the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellView {
+    /// Not yet revealed, and not marked.
+    Hidden,
+    /// Not yet revealed, but marked as a mine.
+    Flagged,
+    /// Revealed, showing what it contains.
+    Revealed(Contains),
+}
+impl From<&Block> for CellView {
+    fn from(block: &Block) -> Self {
+        match (block.revealed(), block.marked()) {
+            (Some(contains), _) => Self::Revealed(contains),
+            (None, true) => Self::Flagged,
+            (None, false) => Self::Hidden,
+        }
+    }
+}
+
+/// Read-only view of the board as the player sees it, for solvers and other tools.
+/// Nothing the player couldn't see (such as the location of hidden mines) is exposed.
+#[derive(SystemParam)]
+pub struct BoardView<'w, 's> {
+    blocks: Query<'w, 's, &'static Block>,
+    field: Query<'w, 's, &'static Minefield>,
+}
+impl BoardView<'_, '_> {
+    /// Every cell of the field, ordered by index
+    pub fn snapshot(&self) -> Vec<([usize; 3], CellView)> {
+        let mut cells: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|block| (block.index(), block.into()))
+            .collect();
+        cells.sort_unstable_by_key(|(index, _)| *index);
+        cells
+    }
+    /// The cell at `index`, if there is one
+    pub fn get(&self, index: [usize; 3]) -> Option<CellView> {
+        let block = self.field.get_single().ok()?.block_at(index)?;
+        self.blocks.get(block).ok().map(CellView::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::super::minefield::testing::*;
+    use super::super::minefield::FieldEvent;
+    use super::*;
+
+    #[test]
+    fn snapshot_shows_a_partially_played_board() {
+        let mut app = board_app(default(), board([4, 1, 1], &[[0, 0, 0]]));
+        play(
+            &mut app,
+            [
+                FieldEvent::MarkBlock([0, 0, 0]),
+                FieldEvent::ClearBlock([1, 0, 0]),
+            ],
+        );
+        let mut view = SystemState::<BoardView>::new(&mut app.world);
+        let view = view.get(&app.world);
+        let one = CellView::Revealed(Contains::Empty { adjacent_mines: 1 });
+        assert_eq!(
+            view.snapshot(),
+            [
+                ([0, 0, 0], CellView::Flagged),
+                ([1, 0, 0], one),
+                ([2, 0, 0], CellView::Hidden),
+                ([3, 0, 0], CellView::Hidden),
+            ]
+        );
+        assert_eq!(view.get([1, 0, 0]), Some(one));
+        assert_eq!(view.get([4, 0, 0]), None);
+    }
+}
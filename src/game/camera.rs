@@ -1,17 +1,48 @@
 use std::f32::consts::{PI, TAU};
 
+use bevy::core_pipeline::fxaa::Fxaa;
+use bevy::math::bounding::{BoundingSphere, RayCast3d};
 use bevy::prelude::*;
 
-use crate::{input::ScreenPosition, GameState, InputEvent};
+use crate::{input::ScreenPosition, FieldSettings, GameSettings, GameState, InputEvent};
 
-use super::GamePiece;
+use super::block::{pick_block, Block};
+use super::{GamePiece, Paused};
+
+/// Coasting speed below which the camera stops, in turns per second
+const MIN_COAST_SPEED: f32 = 0.001;
+/// Distance from a detonated mine to the camera focusing on it
+const LOSS_FOCUS_DISTANCE: f32 = 4.0;
+/// Seconds the camera stays on a detonated mine before zooming back out
+const LOSS_FOCUS_HOLD: f32 = 1.5;
+/// Speed of [GameSettings::idle_spin], in turns per second
+const IDLE_SPIN_SPEED: f32 = 0.02;
 
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         // Add Camera systems
         app.add_systems(OnEnter(GameState::GameStart), spawn.after(super::cleanup));
-        app.add_systems(Update, camera_controls.run_if(GameState::in_game()));
+        app.init_resource::<CameraTarget>();
+        app.init_resource::<CameraInertia>();
+        app.init_resource::<LossFocus>();
+        app.init_resource::<IdleTime>();
+        app.add_systems(OnEnter(GameState::GameOver), focus_on_detonation);
+        app.add_systems(
+            Update,
+            zoom_out_after_loss
+                .run_if(in_state(GameState::GameOver).and_then(resource_equals(Paused(false)))),
+        );
+        app.add_systems(
+            Update,
+            (
+                camera_controls,
+                move_to_target.after(camera_controls),
+                coast_camera.after(camera_controls),
+                idle_spin.after(camera_controls),
+            )
+                .run_if(GameState::in_game().and_then(resource_equals(Paused(false)))),
+        );
         app.add_event::<RayEvent>();
         app.insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)));
         #[cfg(feature = "debug-draw")]
@@ -24,6 +55,15 @@ pub struct MainCamera {
     zoom_speed: f32,
     zoom_limit_near: f32,
     zoom_limit_far: f32,
+    /// Where the camera started the game, for [InputEvent::ResetCamera]
+    home: Transform,
+    /// Point the camera orbits around and looks at. Starts at [MainCamera::home_center],
+    /// and moves with [GameSettings::zoom_to_cursor].
+    center: Vec3,
+    /// Centroid of the field's cells, moved by [GameSettings::camera_target_offset]
+    home_center: Vec3,
+    /// Distance from the center of the field to its farthest corner
+    field_radius: f32,
 }
 impl Default for MainCamera {
     fn default() -> Self {
@@ -31,68 +71,192 @@ impl Default for MainCamera {
             zoom_speed: 1.0,
             zoom_limit_near: 1.0,
             zoom_limit_far: 20.0,
+            home: Transform::IDENTITY,
+            center: Vec3::ZERO,
+            home_center: Vec3::ZERO,
+            field_radius: 0.0,
+        }
+    }
+}
+
+/// Transform the [MainCamera] is smoothly moving towards, if any.
+///
+/// Anything that moves the camera to a preset view should [CameraTarget::set] it here
+/// instead of moving the camera directly. Setting a new target while moving starts over
+/// from wherever the camera is, and rotating or zooming manually cancels the move.
+#[derive(Debug, Resource)]
+pub struct CameraTarget {
+    /// How long a move takes, in seconds
+    pub duration: f32,
+    /// Maps the fraction of [CameraTarget::duration] elapsed to the fraction of the
+    /// distance covered, both from 0.0 to 1.0
+    pub easing: fn(f32) -> f32,
+    target: Option<Transform>,
+    /// Where the current move started, once it has
+    start: Option<Transform>,
+    elapsed: f32,
+}
+impl Default for CameraTarget {
+    fn default() -> Self {
+        Self {
+            duration: 0.4,
+            easing: |t| t * t * (3.0 - 2.0 * t),
+            target: None,
+            start: None,
+            elapsed: 0.0,
         }
     }
 }
+impl CameraTarget {
+    /// Start moving the camera to `target`
+    pub fn set(&mut self, target: Transform) {
+        self.target = Some(target);
+        self.start = None;
+        self.elapsed = 0.0;
+    }
+    /// Stop moving the camera, leaving it wherever it is
+    pub fn cancel(&mut self) {
+        self.target = None;
+    }
+    /// Whether the camera is currently moving to a target
+    pub fn moving(&self) -> bool {
+        self.target.is_some()
+    }
+}
 
-#[derive(Event)]
+/// Rotation the [MainCamera] keeps after a drag with [GameSettings::camera_inertia]
+#[derive(Debug, Default, Resource)]
+struct CameraInertia {
+    /// Rotation per second, in the same units as [InputEvent::RotateCamera]
+    velocity: Vec2,
+}
+
+/// Seconds since the last input, for [GameSettings::idle_spin]
+#[derive(Debug, Default, Resource)]
+pub(super) struct IdleTime(f32);
+
+/// Camera move back out to the whole field, after focusing on a detonated mine
+#[derive(Debug, Default, Resource)]
+struct LossFocus {
+    /// Where the camera was sent to look at the mine, and how long it has been held there
+    focus: Option<(Transform, Timer)>,
+}
+
+/// Farthest distance along a cursor ray at which blocks can be hit: the far plane of the
+/// [MainCamera], since nothing past it is drawn
+#[derive(Debug, Resource)]
+pub(super) struct PickDistance(pub(super) f32);
+
+/// Mirrors the corresponding [InputEvent] variants, with screen positions converted to rays.
+#[derive(Event, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 pub enum RayEvent {
     ClearBlock(Ray3d),
     MarkBlock(Ray3d),
+    SweepBlock(Ray3d),
+    HoverBlock(Option<Ray3d>),
 }
 
-pub(super) fn spawn(mut commands: Commands) {
-    let zoom = 10.0;
-    let translation = Vec3::ONE.normalize() * zoom;
+pub(super) fn spawn(
+    mut commands: Commands,
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
+    mut camera_target: ResMut<CameraTarget>,
+    mut idle_time: ResMut<IdleTime>,
+) {
+    idle_time.0 = 0.0;
+    // Distance from the center of the field to its farthest corner
+    let scale = game_settings.world_scale;
+    let field_radius =
+        Vec3::from_array(field_settings.field_size.map(|n| n as f32)).length() / 2.0 * scale;
+    let zoom = (field_radius * 2.5).max(10.0 * scale);
+    let center = game_settings.up_axis.to_world(field_settings.centroid()) * scale
+        + game_settings.camera_target_offset;
+    let translation = center + game_settings.initial_view.direction() * zoom;
+    let transform = Transform::from_translation(translation).looking_at(center, Vec3::Y);
+    let default_camera = MainCamera::default();
+    let main_camera = MainCamera {
+        zoom_limit_near: default_camera.zoom_limit_near * scale,
+        zoom_limit_far: (field_radius * 4.0).max(default_camera.zoom_limit_far * scale),
+        home: transform,
+        center,
+        home_center: center,
+        field_radius,
+        ..default()
+    };
+    camera_target.cancel();
+    // Keep the whole field within the depth range at every zoom level
+    let projection = PerspectiveProjection {
+        near: game_settings.camera_near.unwrap_or(0.05 * scale),
+        far: game_settings
+            .camera_far
+            .unwrap_or(main_camera.zoom_limit_far + field_radius + scale),
+        ..default()
+    };
+    commands.insert_resource(PickDistance(projection.far));
 
     commands.spawn((
         Camera3dBundle {
-            transform: Transform::from_translation(translation).looking_at(Vec3::ZERO, Vec3::Y),
+            transform,
+            projection: projection.into(),
             ..Default::default()
         },
-        MainCamera::default(),
+        Fxaa {
+            enabled: game_settings.fxaa,
+            ..default()
+        },
+        main_camera,
         GamePiece,
     ));
 }
 
 pub(super) fn camera_controls(
     mut input_events: EventReader<InputEvent>,
-    mut camera_transform: Query<(&Camera, &MainCamera, &mut Transform)>,
+    mut camera_transform: Query<(&Camera, &mut MainCamera, &mut Transform)>,
     mut ray_events: EventWriter<RayEvent>,
+    mut camera_target: ResMut<CameraTarget>,
+    blocks: Query<(Entity, &Block, &InheritedVisibility)>,
+    pick_distance: Res<PickDistance>,
+    game_settings: Res<GameSettings>,
 ) {
-    let (camera, main_camera, mut transform) = camera_transform.single_mut();
+    let (camera, mut main_camera, mut transform) = camera_transform.single_mut();
     for input_event in input_events.read() {
         match input_event {
             InputEvent::RotateCamera { delta } => {
-                let delta_x = delta.x * TAU;
-                let delta_y = delta.y * PI;
-                // Rotate around local X axis and global Y axis
-                let camera_tilt = transform.up().dot(Vec3::Y);
-                debug!("Camera tilt: {camera_tilt}");
-                let x_rot = Quat::from_axis_angle(
-                    Vec3::Y,
-                    if camera_tilt > 0.0 { -delta_x } else { delta_x },
-                );
-                let y_rot = Quat::from_axis_angle(*transform.local_x(), -delta_y);
-                transform.rotate_around(Vec3::ZERO, x_rot);
-                transform.rotate_around(Vec3::ZERO, y_rot);
-                // Fix the odd tilt we get sometimes
-                let camera_tilt = transform.up().dot(Vec3::Y);
-                if camera_tilt > 0.01 {
-                    *transform = transform.looking_at(Vec3::ZERO, Vec3::Y);
-                }
-                if camera_tilt < -0.01 {
-                    *transform = transform.looking_at(Vec3::ZERO, Vec3::NEG_Y);
-                }
+                camera_target.cancel();
+                orbit(&mut transform, main_camera.center, *delta);
             }
-            InputEvent::ZoomCamera { delta } => {
-                let zoom = *delta * transform.translation * main_camera.zoom_speed * -0.1;
-                let new_translation = transform.translation + zoom;
-                let zoom_dist = new_translation.distance(Vec3::ZERO);
-                if zoom_dist > main_camera.zoom_limit_near && zoom_dist < main_camera.zoom_limit_far
+            InputEvent::ZoomCamera { delta, cursor } => {
+                camera_target.cancel();
+                // Factor the distance from the camera to the center is scaled by
+                let scale = 1.0 - *delta * main_camera.zoom_speed * 0.1;
+                let zoom_dist = transform.translation.distance(main_camera.center) * scale;
+                if zoom_dist <= main_camera.zoom_limit_near
+                    || zoom_dist >= main_camera.zoom_limit_far
                 {
-                    *transform = transform.with_translation(new_translation);
+                    continue;
                 }
+                let focus = cursor
+                    .filter(|_| game_settings.zoom_to_cursor && *delta > 0.0)
+                    .and_then(|pos| get_cursor_ray(camera, &transform, pos))
+                    .and_then(|ray| {
+                        zoom_focus(ray, &main_camera, &blocks, pick_distance.0, &game_settings)
+                    });
+                let center = match focus {
+                    // Scale the view around the focus, so it stays under the cursor
+                    Some(focus) => focus + (main_camera.center - focus) * scale,
+                    None if *delta < 0.0 => main_camera
+                        .center
+                        .lerp(main_camera.home_center, (scale - 1.0).min(1.0)),
+                    None => main_camera.center,
+                };
+                let offset = (transform.translation - main_camera.center) * scale;
+                transform.translation = center + offset;
+                main_camera.center = center;
+            }
+            InputEvent::ResetCamera => {
+                main_camera.center = main_camera.home_center;
+                camera_target.set(main_camera.home);
             }
             InputEvent::ClearBlock(cursor_pos) => {
                 if let Some(ray) = get_cursor_ray(camera, &transform, *cursor_pos) {
@@ -106,11 +270,221 @@ pub(super) fn camera_controls(
                     ray_events.send(RayEvent::MarkBlock(ray));
                 }
             }
+            InputEvent::HoverBlock(cursor_pos) => {
+                let ray = cursor_pos.and_then(|pos| get_cursor_ray(camera, &transform, pos));
+                ray_events.send(RayEvent::HoverBlock(ray));
+            }
+            InputEvent::SweepBlock(cursor_pos) => {
+                if let Some(ray) = get_cursor_ray(camera, &transform, *cursor_pos) {
+                    debug!("Send RayEvent::SweepBlock");
+                    ray_events.send(RayEvent::SweepBlock(ray));
+                }
+            }
             _ => {}
         }
     }
 }
 
+/// Point to zoom in towards along `ray` with [GameSettings::zoom_to_cursor]: the nearest
+/// block it hits, or else where it enters the sphere around the field, if it does
+fn zoom_focus(
+    ray: Ray3d,
+    main_camera: &MainCamera,
+    blocks: &Query<(Entity, &Block, &InheritedVisibility)>,
+    pick_distance: f32,
+    game_settings: &GameSettings,
+) -> Option<Vec3> {
+    if let Some((dist, ..)) = pick_block(ray, pick_distance, blocks, game_settings) {
+        return Some(ray.get_point(dist));
+    }
+    let sphere = BoundingSphere::new(main_camera.home_center, main_camera.field_radius);
+    RayCast3d::from_ray(ray, pick_distance)
+        .sphere_intersection_at(&sphere)
+        // From inside the sphere there's nothing to head towards
+        .filter(|dist| *dist > 0.0)
+        .map(|dist| ray.get_point(dist))
+}
+
+/// Rotate the camera around `center`, by `delta` relative to a full turn
+/// horizontally and a half turn vertically.
+fn orbit(transform: &mut Transform, center: Vec3, delta: Vec2) {
+    let delta_x = delta.x * TAU;
+    let delta_y = delta.y * PI;
+    // Rotate around local X axis and global Y axis
+    let camera_tilt = transform.up().dot(Vec3::Y);
+    debug!("Camera tilt: {camera_tilt}");
+    let x_rot = Quat::from_axis_angle(Vec3::Y, if camera_tilt > 0.0 { -delta_x } else { delta_x });
+    let y_rot = Quat::from_axis_angle(*transform.local_x(), -delta_y);
+    transform.rotate_around(center, x_rot);
+    transform.rotate_around(center, y_rot);
+    // Fix the odd tilt we get sometimes
+    let camera_tilt = transform.up().dot(Vec3::Y);
+    if camera_tilt > 0.01 {
+        *transform = transform.looking_at(center, Vec3::Y);
+    }
+    if camera_tilt < -0.01 {
+        *transform = transform.looking_at(center, Vec3::NEG_Y);
+    }
+}
+
+/// Track how fast the camera is being dragged, and keep it spinning with decaying
+/// speed once the rotate button is released, if [GameSettings::camera_inertia] is set.
+fn coast_camera(
+    mut input_events: EventReader<InputEvent>,
+    mut inertia: ResMut<CameraInertia>,
+    camera_target: Res<CameraTarget>,
+    mut camera: Query<(&MainCamera, &mut Transform)>,
+    game_settings: Res<GameSettings>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+) {
+    let mut dragged = Vec2::ZERO;
+    for event in input_events.read() {
+        match event {
+            InputEvent::RotateCamera { delta } => dragged += *delta,
+            InputEvent::ResetCamera => inertia.velocity = Vec2::ZERO,
+            _ => {}
+        }
+    }
+    let dt = time.delta_seconds();
+    if !game_settings.camera_inertia || camera_target.moving() || dt <= 0.0 {
+        inertia.velocity = Vec2::ZERO;
+        return;
+    }
+    if mouse_button.pressed(game_settings.rotate_button) {
+        // Holding still before letting go shouldn't leave any spin
+        inertia.velocity = dragged / dt;
+        return;
+    }
+    if inertia.velocity.length_squared() < MIN_COAST_SPEED * MIN_COAST_SPEED {
+        inertia.velocity = Vec2::ZERO;
+        return;
+    }
+    let (main_camera, mut transform) = camera.single_mut();
+    orbit(&mut transform, main_camera.center, inertia.velocity * dt);
+    inertia.velocity *= (-game_settings.camera_damping * dt).exp();
+}
+
+/// Orbit the camera slowly once there's been no input for [GameSettings::idle_spin_delay].
+/// Any input, or the camera being moved some other way, starts the wait over.
+fn idle_spin(
+    mut input_events: EventReader<InputEvent>,
+    mut idle_time: ResMut<IdleTime>,
+    camera_target: Res<CameraTarget>,
+    inertia: Res<CameraInertia>,
+    mut camera: Query<(&MainCamera, &mut Transform)>,
+    game_settings: Res<GameSettings>,
+    time: Res<Time>,
+) {
+    let any_input = input_events.read().count() > 0;
+    let camera_busy = camera_target.moving() || inertia.velocity != Vec2::ZERO;
+    if !game_settings.idle_spin || any_input || camera_busy {
+        idle_time.0 = 0.0;
+        return;
+    }
+    let dt = time.delta_seconds();
+    idle_time.0 += dt;
+    if idle_time.0 < game_settings.idle_spin_delay {
+        return;
+    }
+    let (main_camera, mut transform) = camera.single_mut();
+    orbit(
+        &mut transform,
+        main_camera.center,
+        Vec2::new(IDLE_SPIN_SPEED * dt, 0.0),
+    );
+}
+
+/// After a loss, move the camera to look at the detonated mine from outside the field,
+/// so it can be seen even if it was on the far side.
+fn focus_on_detonation(
+    mut camera_target: ResMut<CameraTarget>,
+    mut loss_focus: ResMut<LossFocus>,
+    blocks: Query<(&super::Block, &GlobalTransform)>,
+    camera: Query<&MainCamera>,
+    game_result: Res<super::GameResult>,
+    game_settings: Res<GameSettings>,
+) {
+    loss_focus.focus = None;
+    if *game_result != super::GameResult::Failure || !game_settings.animations_enabled {
+        return;
+    }
+    // Chording can detonate several mines at once, but one is enough to show what happened
+    let Some((_, mine)) = blocks
+        .iter()
+        .find(|(block, _)| block.revealed() == Some(super::Contains::Mine))
+    else {
+        return;
+    };
+    let center = camera.single().center;
+    let mine = mine.translation() - center;
+    // Stay on the line through the center of the field, so orbiting still works as usual
+    let direction = mine.try_normalize().unwrap_or(Vec3::Z);
+    let distance = mine.length() + LOSS_FOCUS_DISTANCE * game_settings.world_scale;
+    let focus =
+        Transform::from_translation(center + direction * distance).looking_at(center, Vec3::Y);
+    camera_target.set(focus);
+    loss_focus.focus = Some((focus, Timer::from_seconds(LOSS_FOCUS_HOLD, TimerMode::Once)));
+}
+
+/// Once the camera has arrived at a detonated mine and held there for a moment,
+/// zoom back out to show the whole field from the same side.
+/// Does nothing if the player moved the camera in the meantime.
+fn zoom_out_after_loss(
+    mut camera_target: ResMut<CameraTarget>,
+    mut loss_focus: ResMut<LossFocus>,
+    camera: Query<(&MainCamera, &Transform)>,
+    time: Res<Time>,
+) {
+    let Some((focus, timer)) = &mut loss_focus.focus else {
+        return;
+    };
+    let (main_camera, transform) = camera.single();
+    if camera_target.moving() {
+        return;
+    }
+    if transform.translation.distance(focus.translation) > 0.01 {
+        loss_focus.focus = None;
+        return;
+    }
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+    let center = main_camera.center;
+    let direction = (focus.translation - center).normalize_or_zero();
+    let distance = main_camera.home.translation.distance(center);
+    let overview =
+        Transform::from_translation(center + direction * distance).looking_at(center, Vec3::Y);
+    camera_target.set(overview);
+    loss_focus.focus = None;
+}
+
+/// Ease the [MainCamera] towards the [CameraTarget]
+fn move_to_target(
+    mut camera_target: ResMut<CameraTarget>,
+    mut camera_transform: Query<&mut Transform, With<MainCamera>>,
+    time: Res<Time>,
+) {
+    let Some(target) = camera_target.target else {
+        return;
+    };
+    let mut transform = camera_transform.single_mut();
+    let start = *camera_target.start.get_or_insert(*transform);
+    camera_target.elapsed += time.delta_seconds();
+    let progress = if camera_target.duration > 0.0 {
+        (camera_target.elapsed / camera_target.duration).min(1.0)
+    } else {
+        1.0
+    };
+    let t = (camera_target.easing)(progress);
+    transform.translation = start.translation.lerp(target.translation, t);
+    transform.rotation = start.rotation.slerp(target.rotation, t);
+    if progress >= 1.0 {
+        *transform = target;
+        camera_target.cancel();
+    }
+}
+
 fn get_cursor_ray(
     camera: &Camera,
     camera_trans: &Transform,
@@ -135,18 +509,131 @@ fn get_cursor_ray(
     Some(Ray3d::new(near, ray_direction))
 }
 
+/// Draw the cursor ray, ending at the block it would hit
 #[cfg(feature = "debug-draw")]
 fn cursor_ray_gizmo(
     mut gizmos: Gizmos,
     main_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     primary_window: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    blocks: Query<(Entity, &super::Block, &InheritedVisibility)>,
+    pick_distance: Res<PickDistance>,
+    game_settings: Res<GameSettings>,
 ) {
     let Some(cursor_pos) = primary_window.single().cursor_position() else {
         return;
     };
     let (camera, camera_trans) = main_camera.single();
-    let Some(ray) = get_cursor_ray(camera, camera_trans, cursor_pos) else {
+    let Some(ray) = get_cursor_ray(camera, &camera_trans.compute_transform(), cursor_pos.into())
+    else {
         return;
     };
-    gizmos.arrow(ray.origin, ray.get_point(20.0), Color::YELLOW);
+    let length = super::block::pick_block(ray, pick_distance.0, &blocks, &game_settings)
+        .map_or(20.0, |(dist, ..)| dist);
+    gizmos.arrow(ray.origin, ray.get_point(length), Color::YELLOW);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::super::block::{block_bounds, calculate_position};
+    use super::*;
+
+    #[test]
+    fn picks_blocks_from_the_far_zoom_limit_under_world_scale() {
+        let game_settings = GameSettings {
+            world_scale: 4.0,
+            ..default()
+        };
+        let field_settings = FieldSettings {
+            field_size: [10, 10, 10],
+            ..default()
+        };
+        let scale = game_settings.world_scale;
+        let cube_size = game_settings.cube_size * scale;
+        let mut blocks = vec![];
+        for i in 0..10 {
+            for j in 0..10 {
+                for k in 0..10 {
+                    let pos = calculate_position(
+                        [i, j, k],
+                        field_settings.field_size,
+                        scale,
+                        game_settings.up_axis,
+                    );
+                    blocks.push(Block::new(block_bounds(pos, cube_size), [i, j, k]));
+                }
+            }
+        }
+
+        let mut app = App::new();
+        app.insert_resource(game_settings)
+            .insert_resource(field_settings)
+            .init_resource::<CameraTarget>()
+            .init_resource::<IdleTime>()
+            .add_systems(Update, spawn);
+        app.update();
+        let main_camera = app.world.query::<&MainCamera>().single(&app.world);
+        let pick_distance = app.world.resource::<PickDistance>().0;
+        let game_settings = app.world.resource::<GameSettings>();
+        // Blocks on the far side of the field are within reach even zoomed all the way out
+        assert!(main_camera.zoom_limit_far + main_camera.field_radius < pick_distance);
+
+        let direction = (main_camera.home.translation - main_camera.home_center).normalize();
+        let origin = main_camera.home_center + direction * main_camera.zoom_limit_far;
+        let ray = Ray3d::new(origin, -direction);
+        let entities = (0..blocks.len() as u32).map(Entity::from_raw);
+        let visible = InheritedVisibility::VISIBLE;
+        let hit = pick_block(
+            ray,
+            pick_distance,
+            entities
+                .zip(&blocks)
+                .map(|(entity, block)| (entity, block, &visible)),
+            game_settings,
+        );
+        let (dist, _, index) = hit.expect("ray towards the field should hit a block");
+        // The hit is on the surface of the block that was picked
+        let center = calculate_position(index, [10, 10, 10], scale, game_settings.up_axis);
+        let offset = (ray.get_point(dist) - center).abs().max_element();
+        assert!((offset - cube_size / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn camera_reaches_the_target_within_the_duration() {
+        let mut app = App::new();
+        app.init_resource::<GameSettings>()
+            .init_resource::<FieldSettings>()
+            .insert_resource(CameraTarget {
+                duration: 0.5,
+                ..default()
+            })
+            .init_resource::<IdleTime>()
+            .init_resource::<Time>();
+        app.world.run_system_once(spawn);
+        app.add_systems(Update, move_to_target);
+        let target = Transform::from_xyz(3.0, 4.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y);
+        app.world.resource_mut::<CameraTarget>().set(target);
+        let camera = |app: &mut App| {
+            let world = &mut app.world;
+            *world
+                .query_filtered::<&Transform, With<MainCamera>>()
+                .single(world)
+        };
+        let step = |app: &mut App| {
+            app.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(0.25));
+            app.update();
+        };
+        step(&mut app);
+        // Halfway through the duration the camera is still on its way
+        assert_ne!(camera(&mut app), target);
+        assert!(app.world.resource::<CameraTarget>().moving());
+        step(&mut app);
+        assert_eq!(camera(&mut app), target);
+        assert!(!app.world.resource::<CameraTarget>().moving());
+    }
 }
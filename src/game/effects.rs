@@ -0,0 +1,425 @@
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use super::block::Block;
+use super::camera::MainCamera;
+use super::minefield::Contains;
+use super::{GamePiece, GameResult};
+use crate::{ExplosionSound, FieldSettings, GameAssets, GameSettings, GameState};
+
+/// How long a click ripple lasts, in seconds
+const RIPPLE_DURATION: f32 = 0.15;
+/// Radius of a click ripple when it finishes expanding
+const RIPPLE_RADIUS: f32 = 0.35;
+/// Gap between the bottom of the field and the ground plane
+const GROUND_GAP: f32 = 0.5;
+/// Number of cells the ground extends past the field on each side
+const GROUND_MARGIN: usize = 1;
+/// Illuminance at the center of the field from all of the [GameSettings::light_count]
+/// lights together, in lux
+const FIELD_LIGHT_ILLUMINANCE: f32 = 600.0;
+/// Seconds each piece of explosion debris lasts before it has shrunk away
+const DEBRIS_LIFETIME: f32 = 1.2;
+/// Size of a piece of explosion debris, relative to a cell
+const DEBRIS_SIZE: f32 = 0.08;
+/// Fastest a piece of explosion debris is thrown out, in cells per second
+const DEBRIS_SPEED: f32 = 6.0;
+/// Downwards acceleration of explosion debris, in cells per second squared
+const DEBRIS_GRAVITY: f32 = 9.8;
+/// Seconds the camera shakes for after a detonation
+const SHAKE_DURATION: f32 = 0.4;
+/// Farthest the camera is moved by shaking, relative to a cell
+const SHAKE_AMPLITUDE: f32 = 0.15;
+
+pub struct EffectsPlugin;
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (create_ripple_mesh, create_debris_assets));
+        app.init_resource::<CameraShake>();
+        app.add_systems(OnEnter(GameState::GameOver), explode);
+        app.add_systems(Update, animate_debris.run_if(GameState::in_game()));
+        // The offset is only applied while rendering, so nothing else sees the camera move
+        app.add_systems(First, unshake_camera);
+        app.add_systems(
+            PostUpdate,
+            shake_camera.before(bevy::transform::TransformSystem::TransformPropagate),
+        );
+        app.add_systems(
+            OnEnter(GameState::GameStart),
+            (spawn_ground, spawn_lights).after(super::cleanup),
+        );
+        app.add_systems(Update, animate_ripples.run_if(GameState::in_game()));
+    }
+}
+
+/// Thin ring mesh shared by all ripples, with a radius of 1.
+#[derive(Resource)]
+pub(super) struct RippleMesh(pub(super) Handle<Mesh>);
+
+/// Expanding ring shown where a click hit a block.
+/// Each ripple owns its material so it can fade out independently.
+#[derive(Component)]
+pub(super) struct Ripple {
+    timer: Timer,
+    /// Radius when it finishes expanding
+    radius: f32,
+}
+
+fn create_ripple_mesh(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(RippleMesh(meshes.add(Torus::new(0.9, 1.0))));
+}
+
+/// Show a ripple at `position`, typically where a click ray hit a block,
+/// sized for a field with the given [GameSettings::world_scale].
+pub(super) fn spawn_ripple(
+    commands: &mut Commands,
+    mesh: &RippleMesh,
+    materials: &mut Assets<StandardMaterial>,
+    position: Vec3,
+    scale: f32,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: mesh.0.clone(),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_translation(position).with_scale(Vec3::ZERO),
+            ..default()
+        },
+        Ripple {
+            timer: Timer::from_seconds(RIPPLE_DURATION, TimerMode::Once),
+            radius: RIPPLE_RADIUS * scale,
+        },
+        GamePiece,
+    ));
+}
+
+fn animate_ripples(
+    mut commands: Commands,
+    mut ripples: Query<(
+        Entity,
+        &mut Ripple,
+        &mut Transform,
+        &Handle<StandardMaterial>,
+    )>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for (entity, mut ripple, mut transform, material) in &mut ripples {
+        ripple.timer.tick(time.delta());
+        if ripple.timer.finished() {
+            materials.remove(material);
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let progress = ripple.timer.fraction();
+        // The torus lies in the XZ plane, so point its Y axis at the camera
+        let to_camera = (camera.translation() - transform.translation).normalize_or_zero();
+        transform.rotation = Quat::from_rotation_arc(Vec3::Y, to_camera);
+        transform.scale = Vec3::splat(ripple.radius * progress);
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color.set_a(1.0 - progress);
+        }
+    }
+}
+
+/// Mesh and material shared by all explosion debris
+#[derive(Resource)]
+struct DebrisAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Piece of debris thrown out by a detonated mine, falling and shrinking until it vanishes
+#[derive(Component)]
+struct Debris {
+    velocity: Vec3,
+    /// Downwards acceleration, scaled to the world
+    gravity: f32,
+    /// Size when first thrown out
+    size: f32,
+    timer: Timer,
+}
+
+/// Offset applied to the [MainCamera] after a detonation, decaying over [SHAKE_DURATION]
+#[derive(Debug, Default, Resource)]
+struct CameraShake {
+    /// Time left shaking, and the largest offset
+    shaking: Option<(Timer, f32)>,
+    /// Offset applied to the camera this frame, to undo before the next
+    applied: Vec3,
+}
+
+fn create_debris_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(DebrisAssets {
+        mesh: meshes.add(Cuboid::from_size(Vec3::ONE)),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1.0, 0.4, 0.1),
+            emissive: Color::rgb(1.0, 0.3, 0.0),
+            ..default()
+        }),
+    });
+}
+
+/// When the game is lost, throw debris out of the detonated mine, shake the camera and
+/// play [GameSettings::explosion_sound]
+fn explode(
+    blocks: Query<(&Block, &GlobalTransform)>,
+    game_result: Res<GameResult>,
+    game_settings: Res<GameSettings>,
+    game_assets: Res<GameAssets>,
+    debris_assets: Res<DebrisAssets>,
+    mut shake: ResMut<CameraShake>,
+    mut commands: Commands,
+) {
+    if *game_result != GameResult::Failure {
+        return;
+    }
+    let sound = match game_settings.explosion_sound {
+        ExplosionSound::None => None,
+        ExplosionSound::Pop => Some((game_assets.pop3.clone(), 1.0)),
+        ExplosionSound::Boom => Some((game_assets.pop1.clone(), 0.5)),
+    };
+    if let Some((source, speed)) = sound {
+        commands.spawn(AudioBundle {
+            source,
+            settings: PlaybackSettings {
+                mode: PlaybackMode::Despawn,
+                volume: Volume::new(game_settings.sfx_volume),
+                speed,
+                ..default()
+            },
+        });
+    }
+    if !game_settings.animations_enabled {
+        return;
+    }
+    // Chording can detonate several mines at once, but one explosion is enough
+    let Some((_, mine)) = blocks
+        .iter()
+        .find(|(block, _)| block.revealed() == Some(Contains::Mine))
+    else {
+        return;
+    };
+    let scale = game_settings.world_scale;
+    if game_settings.screen_shake {
+        shake.shaking = Some((
+            Timer::from_seconds(SHAKE_DURATION, TimerMode::Once),
+            SHAKE_AMPLITUDE * scale,
+        ));
+    }
+    let mut rng = rand::thread_rng();
+    let size = DEBRIS_SIZE * scale;
+    for _ in 0..game_settings.explosion_particles {
+        let direction = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-0.5..1.0),
+            rng.gen_range(-1.0..1.0),
+        )
+        .normalize_or_zero();
+        let velocity = direction * rng.gen_range(0.3..1.0) * DEBRIS_SPEED * scale;
+        commands.spawn((
+            PbrBundle {
+                mesh: debris_assets.mesh.clone(),
+                material: debris_assets.material.clone(),
+                transform: Transform::from_translation(mine.translation())
+                    .with_rotation(Quat::from_euler(
+                        EulerRot::XYZ,
+                        rng.gen_range(0.0..std::f32::consts::TAU),
+                        rng.gen_range(0.0..std::f32::consts::TAU),
+                        0.0,
+                    ))
+                    .with_scale(Vec3::splat(size)),
+                ..default()
+            },
+            Debris {
+                velocity,
+                gravity: DEBRIS_GRAVITY * scale,
+                size,
+                timer: Timer::from_seconds(
+                    DEBRIS_LIFETIME * rng.gen_range(0.6..1.0),
+                    TimerMode::Once,
+                ),
+            },
+            GamePiece,
+        ));
+    }
+}
+
+fn animate_debris(
+    mut commands: Commands,
+    mut debris: Query<(Entity, &mut Debris, &mut Transform)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut piece, mut transform) in &mut debris {
+        piece.timer.tick(time.delta());
+        if piece.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        piece.velocity.y -= piece.gravity * dt;
+        transform.translation += piece.velocity * dt;
+        transform.scale = Vec3::splat(piece.size * (1.0 - piece.timer.fraction()));
+    }
+}
+
+fn shake_camera(
+    mut shake: ResMut<CameraShake>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    time: Res<Time>,
+) {
+    let Some((timer, amplitude)) = &mut shake.shaking else {
+        return;
+    };
+    timer.tick(time.delta());
+    if timer.finished() {
+        shake.shaking = None;
+        return;
+    }
+    let Ok(mut transform) = camera.get_single_mut() else {
+        return;
+    };
+    let strength = *amplitude * (1.0 - timer.fraction());
+    let mut rng = rand::thread_rng();
+    let offset = Vec3::new(
+        rng.gen_range(-1.0..1.0),
+        rng.gen_range(-1.0..1.0),
+        rng.gen_range(-1.0..1.0),
+    ) * strength;
+    transform.translation += offset;
+    shake.applied = offset;
+}
+
+fn unshake_camera(
+    mut shake: ResMut<CameraShake>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    let offset = std::mem::take(&mut shake.applied);
+    if offset == Vec3::ZERO {
+        return;
+    }
+    if let Ok(mut transform) = camera.get_single_mut() {
+        transform.translation -= offset;
+    }
+}
+
+/// Spawn [GameSettings::light_count] point lights evenly spaced around the field,
+/// alternating above and below it, sharing [FIELD_LIGHT_ILLUMINANCE] between them.
+fn spawn_lights(
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
+    mut commands: Commands,
+) {
+    let count = game_settings.light_count;
+    if count == 0 {
+        return;
+    }
+    let field_radius = Vec3::from_array(field_settings.field_size.map(|n| n as f32)).length() / 2.0
+        * game_settings.world_scale;
+    let distance = field_radius * 1.5 + game_settings.world_scale;
+    // Inverse square law, for a light emitting equally in every direction
+    let intensity =
+        FIELD_LIGHT_ILLUMINANCE * 4.0 * std::f32::consts::PI * distance * distance / count as f32;
+    for n in 0..count {
+        let angle = n as f32 / count as f32 * std::f32::consts::TAU;
+        let height = if n % 2 == 0 { 0.5 } else { -0.5 };
+        let direction = Vec3::new(angle.cos(), height, angle.sin()).normalize();
+        commands.spawn((
+            PointLightBundle {
+                point_light: PointLight {
+                    intensity,
+                    range: distance * 2.0,
+                    ..default()
+                },
+                transform: Transform::from_translation(direction * distance),
+                ..default()
+            },
+            GamePiece,
+        ));
+    }
+}
+
+/// Spawn a ground plane below the field, with grid lines aligned to the blocks above it.
+/// It has no [super::block::Block], so it can't be picked.
+fn spawn_ground(
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    if !game_settings.show_ground {
+        return;
+    }
+    let size = Vec3::from_array(field_settings.field_size.map(|n| n as f32));
+    let [x, y, z] = game_settings.up_axis.to_world(size).abs().to_array();
+    let (cells_x, cells_z) = (
+        x as usize + 2 * GROUND_MARGIN,
+        z as usize + 2 * GROUND_MARGIN,
+    );
+    let (width, depth) = (cells_x as f32, cells_z as f32);
+    let height = -y / 2.0 - GROUND_GAP;
+    let scale = game_settings.world_scale;
+    let ground = commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(Plane3d::default().mesh().size(width, depth)),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.08, 0.08, 0.1),
+                    perceptual_roughness: 1.0,
+                    ..default()
+                }),
+                transform: Transform::from_xyz(0.0, height * scale, 0.0)
+                    .with_scale(Vec3::splat(scale)),
+                ..default()
+            },
+            GamePiece,
+        ))
+        .id();
+    // Grid lines along the boundaries between cells, slightly above the plane
+    let line_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(1.0, 1.0, 1.0, 0.15),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    let line_x = meshes.add(Cuboid::new(width, 0.001, 0.02));
+    let line_z = meshes.add(Cuboid::new(0.02, 0.001, depth));
+    let mut lines = vec![];
+    for i in 0..=cells_z {
+        lines.push((
+            line_x.clone(),
+            Vec3::new(0.0, 0.001, i as f32 - depth / 2.0),
+        ));
+    }
+    for i in 0..=cells_x {
+        lines.push((
+            line_z.clone(),
+            Vec3::new(i as f32 - width / 2.0, 0.001, 0.0),
+        ));
+    }
+    commands.entity(ground).with_children(|parent| {
+        for (mesh, translation) in lines {
+            parent.spawn(PbrBundle {
+                mesh,
+                material: line_material.clone(),
+                transform: Transform::from_translation(translation),
+                ..default()
+            });
+        }
+    });
+}
@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+use bevy::app::AppExit;
+use bevy::math::bounding::Aabb3d;
+use bevy::prelude::*;
+
+use super::block::{handle_block_events, Block, BlockEvent, BlockVisualChanged};
+use super::minefield::{handle_field_events, FieldEvent, FieldPlugin};
+use super::save::Autosave;
+use super::{
+    GameLostEvent, GamePiece, GameResult, GameState, GameStats, GameTimer, Inspecting, LastReveal,
+};
+use crate::{FieldSettings, GameSettings, InputEvent};
+
+/// Runs a scripted game with only the minefield logic, for benchmarking without a window.
+/// Add it alongside [MinimalPlugins] instead of the usual plugins.
+///
+/// Blocks keep their state as usual, but nothing is displayed.
+///
+/// The first block cleared is the center, which generates the field and floods outwards.
+/// Then every remaining block is cleared at once. The time taken by each step is printed
+/// to stdout, and the app exits.
+pub struct HeadlessPlugin {
+    /// Field to play on
+    pub field_settings: FieldSettings,
+}
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .insert_resource(GameSettings::default())
+            .insert_resource(self.field_settings.clone())
+            .init_resource::<GameResult>()
+            .init_resource::<GameStats>()
+            .init_resource::<Autosave>()
+            .init_resource::<Inspecting>()
+            .init_resource::<LastReveal>()
+            .init_resource::<GameTimer>()
+            .init_resource::<Script>()
+            // Sent and read by the minefield and blocks, but handled by plugins that aren't added
+            .add_event::<BlockEvent>()
+            .add_event::<BlockVisualChanged>()
+            .add_event::<GameLostEvent>()
+            .add_event::<InputEvent>()
+            .add_plugins(FieldPlugin)
+            .add_systems(Startup, start)
+            .add_systems(OnEnter(GameState::GameStart), spawn_blocks)
+            .add_systems(
+                Update,
+                (
+                    run_script.before(handle_field_events),
+                    time_step.after(handle_block_events),
+                )
+                    .run_if(GameState::in_game()),
+            )
+            .add_systems(
+                Update,
+                handle_block_events
+                    .after(handle_field_events)
+                    .run_if(GameState::in_game()),
+            );
+    }
+}
+
+/// Progress through the scripted moves
+#[derive(Debug, Default, Resource)]
+struct Script {
+    /// Number of steps started
+    step: usize,
+    /// Name and start time of the step in progress
+    started: Option<(&'static str, Instant)>,
+    /// Timings of the finished steps
+    timings: Vec<(&'static str, Duration)>,
+}
+
+fn start(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::GameStart);
+}
+
+/// Spawn a [Block] for every cell with nothing to display it
+pub(super) fn spawn_blocks(
+    field_settings: Res<FieldSettings>,
+    mut commands: Commands,
+    mut field_events: EventWriter<FieldEvent>,
+) {
+    let [x, y, z] = field_settings.field_size;
+    let bb = Aabb3d::new(Vec3::ZERO, Vec3::splat(0.5));
+    for i in 0..x {
+        for j in 0..y {
+            for k in 0..z {
+                if !field_settings
+                    .shape
+                    .contains([i, j, k], field_settings.field_size)
+                {
+                    continue;
+                }
+                let block = commands.spawn((Block::new(bb, [i, j, k]), GamePiece)).id();
+                field_events.send(FieldEvent::SpawnBlock(block, [i, j, k]));
+            }
+        }
+    }
+}
+
+fn run_script(
+    mut script: ResMut<Script>,
+    field_settings: Res<FieldSettings>,
+    blocks: Query<&Block>,
+    mut field_events: EventWriter<FieldEvent>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    let name = match script.step {
+        0 => {
+            let center = field_settings.field_size.map(|n| n / 2);
+            field_events.send(FieldEvent::ClearBlock(center));
+            "generate and clear the center"
+        }
+        1 => {
+            for block in &blocks {
+                field_events.send(FieldEvent::ClearBlock(block.index()));
+            }
+            "clear every block"
+        }
+        _ => {
+            let [x, y, z] = field_settings.field_size;
+            println!("Headless run on a {x}x{y}x{z} field:");
+            for (name, duration) in &script.timings {
+                println!("  {name}: {:.3} ms", duration.as_secs_f64() * 1000.0);
+            }
+            exit_events.send(AppExit);
+            return;
+        }
+    };
+    script.step += 1;
+    script.started = Some((name, Instant::now()));
+}
+
+fn time_step(mut script: ResMut<Script>) {
+    if let Some((name, started)) = script.started.take() {
+        script.timings.push((name, started.elapsed()));
+    }
+}
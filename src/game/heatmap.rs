@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::block::{apply_block_visuals, Block, BlockEvent, BlockMaterials};
+use super::board::CellView;
+use super::minefield::Contains;
+use super::GameStats;
+use crate::{Connectivity, FieldSettings, GameSettings, GameState, InputEvent};
+
+/// Number of distinct shades between safe and certain mine
+const HEATMAP_SHADES: usize = 11;
+
+pub struct HeatmapPlugin;
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heatmap>()
+            .add_systems(Startup, create_heatmap_materials)
+            .add_systems(
+                Update,
+                (toggle_heatmap, paint_heatmap)
+                    .chain()
+                    .after(apply_block_visuals)
+                    .run_if(GameState::in_game()),
+            )
+            .add_systems(OnExit(GameState::GamePlaying), hide_heatmap);
+    }
+}
+
+/// Overlay tinting each hidden block by how likely it is to contain a mine,
+/// judging only by what the player can see.
+#[derive(Debug, Default, Resource)]
+pub struct Heatmap {
+    /// Whether the overlay is shown
+    pub visible: bool,
+}
+
+/// Materials for each shade of the heatmap, from safe to certain mine
+#[derive(Resource)]
+struct HeatmapMaterials([Handle<StandardMaterial>; HEATMAP_SHADES]);
+impl HeatmapMaterials {
+    fn shade(&self, probability: f32) -> Handle<StandardMaterial> {
+        let shade = (probability.clamp(0.0, 1.0) * (HEATMAP_SHADES - 1) as f32).round();
+        self.0[shade as usize].clone()
+    }
+}
+
+fn create_heatmap_materials(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let shades = std::array::from_fn(|shade| {
+        let t = shade as f32 / (HEATMAP_SHADES - 1) as f32;
+        materials.add(Color::rgb(t, 1.0 - t, 0.1))
+    });
+    commands.insert_resource(HeatmapMaterials(shades));
+}
+
+fn toggle_heatmap(mut input_events: EventReader<InputEvent>, mut heatmap: ResMut<Heatmap>) {
+    for event in input_events.read() {
+        if matches!(event, InputEvent::ToggleHeatmap) {
+            heatmap.visible = !heatmap.visible;
+        }
+    }
+}
+
+/// Repaint hidden blocks whenever the overlay is toggled, or while it's shown and the board
+/// changes. Blocks highlighted by the chord preview keep their highlight.
+#[allow(clippy::too_many_arguments)]
+fn paint_heatmap(
+    heatmap: Res<Heatmap>,
+    mut block_events: EventReader<BlockEvent>,
+    blocks: Query<(Entity, &Block, Option<&Handle<StandardMaterial>>)>,
+    block_materials: Option<Res<BlockMaterials>>,
+    heatmap_materials: Res<HeatmapMaterials>,
+    game_settings: Res<GameSettings>,
+    field_settings: Res<FieldSettings>,
+    stats: Res<GameStats>,
+    mut commands: Commands,
+) {
+    let board_changed = block_events.read().count() > 0;
+    let Some(block_materials) = block_materials else {
+        return;
+    };
+    let toggled = heatmap.is_changed();
+    if !toggled && !heatmap.visible {
+        return;
+    }
+    if !toggled && !board_changed {
+        return;
+    }
+    let cells: HashMap<[usize; 3], CellView> = blocks
+        .iter()
+        .map(|(_, block, _)| (block.index(), block.into()))
+        .collect();
+    let probabilities = heatmap.visible.then(|| {
+        mine_probabilities(
+            &cells,
+            game_settings.connectivity_for(&field_settings),
+            stats.mines,
+        )
+    });
+    let preview = block_materials.preview();
+    for (entity, block, material) in &blocks {
+        if cells[&block.index()] != CellView::Hidden || material == Some(&preview) {
+            continue;
+        }
+        let material = match &probabilities {
+            Some(probabilities) => heatmap_materials.shade(probabilities[&block.index()]),
+            None => block_materials.hidden(),
+        };
+        commands.entity(entity).insert(material);
+    }
+}
+
+fn hide_heatmap(mut heatmap: ResMut<Heatmap>) {
+    heatmap.visible = false;
+}
+
+/// Estimate the chance of each hidden cell containing a mine.
+///
+/// Cells next to a number that already has all its mines marked are certainly safe,
+/// and cells next to a number that needs every one of its hidden neighbors are certainly
+/// mines. Otherwise, the estimate is the highest share of remaining mines among the
+/// hidden neighbors of any adjacent number, or the share of remaining mines over the
+/// whole board for cells next to no number.
+pub(super) fn mine_probabilities(
+    cells: &HashMap<[usize; 3], CellView>,
+    connectivity: Connectivity,
+    mines: usize,
+) -> HashMap<[usize; 3], f32> {
+    let neighbors = |[i, j, k]: [usize; 3]| {
+        let mut adjacent = vec![];
+        for i_off in -1..=1 {
+            for j_off in -1..=1 {
+                for k_off in -1..=1 {
+                    if !connectivity.includes([i_off, j_off, k_off]) {
+                        continue;
+                    }
+                    let index = [
+                        i.wrapping_add_signed(i_off),
+                        j.wrapping_add_signed(j_off),
+                        k.wrapping_add_signed(k_off),
+                    ];
+                    if let Some(view) = cells.get(&index) {
+                        adjacent.push((index, *view));
+                    }
+                }
+            }
+        }
+        adjacent
+    };
+    let hidden = cells
+        .values()
+        .filter(|view| **view == CellView::Hidden)
+        .count();
+    let flagged = cells
+        .values()
+        .filter(|view| **view == CellView::Flagged)
+        .count();
+    let background = mines.saturating_sub(flagged) as f32 / hidden.max(1) as f32;
+
+    let mut estimates: HashMap<[usize; 3], f32> = HashMap::new();
+    let mut safe = vec![];
+    let mut certain = vec![];
+    for (&index, view) in cells {
+        let CellView::Revealed(Contains::Empty { adjacent_mines }) = view else {
+            continue;
+        };
+        let adjacent = neighbors(index);
+        let marked = adjacent
+            .iter()
+            .filter(|(_, view)| *view == CellView::Flagged)
+            .count();
+        let unknown: Vec<_> = adjacent
+            .iter()
+            .filter(|(_, view)| *view == CellView::Hidden)
+            .map(|(index, _)| *index)
+            .collect();
+        if unknown.is_empty() {
+            continue;
+        }
+        let remaining = usize::from(*adjacent_mines).saturating_sub(marked);
+        if remaining == 0 {
+            safe.extend(unknown);
+        } else if remaining >= unknown.len() {
+            certain.extend(unknown);
+        } else {
+            let share = remaining as f32 / unknown.len() as f32;
+            for index in unknown {
+                estimates
+                    .entry(index)
+                    .and_modify(|estimate| *estimate = estimate.max(share))
+                    .or_insert(share);
+            }
+        }
+    }
+
+    let mut probabilities: HashMap<[usize; 3], f32> = cells
+        .iter()
+        .filter(|(_, view)| **view == CellView::Hidden)
+        .map(|(index, _)| (*index, estimates.get(index).copied().unwrap_or(background)))
+        .collect();
+    for index in certain {
+        probabilities.insert(index, 1.0);
+    }
+    // Safe takes priority, in case wrong marks make the numbers contradict each other
+    for index in safe {
+        probabilities.insert(index, 0.0);
+    }
+    probabilities
+}
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+
+use super::GameResult;
+use crate::{ExplosionSound, GameSettings, GameState};
+
+/// Seconds between the start of each note of a jingle
+const NOTE_SPACING: f32 = 0.14;
+/// How much longer than [NOTE_SPACING] the last note of a jingle rings
+const LAST_NOTE_LENGTH: f32 = 3.0;
+/// Seconds the defeat sting waits for the explosion sound, so the two don't overlap
+const EXPLOSION_GAP: f32 = 0.6;
+/// Frequencies of the victory jingle, in Hz: a rising major arpeggio
+const VICTORY_NOTES: [f32; 4] = [523.25, 659.25, 783.99, 1046.5];
+/// Frequencies of the defeat sting, in Hz: falling, and ending low
+const DEFEAT_NOTES: [f32; 3] = [392.0, 311.13, 196.0];
+
+pub struct JinglePlugin;
+impl Plugin for JinglePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<JingleQueue>()
+            .add_systems(Startup, create_jingles)
+            .add_systems(OnEnter(GameState::GameOver), queue_jingle)
+            .add_systems(OnEnter(GameState::GameStart), stop_jingle)
+            .add_systems(OnEnter(GameState::MenuMain), stop_jingle)
+            .add_systems(Update, play_jingle);
+    }
+}
+
+/// Notes of the jingles played when a game ends, at [GameSettings::music_volume]
+#[derive(Resource)]
+struct Jingles {
+    victory: Vec<Handle<Pitch>>,
+    defeat: Vec<Handle<Pitch>>,
+}
+
+/// Notes waiting to be played, by seconds after the jingle started
+#[derive(Debug, Default, Resource)]
+struct JingleQueue {
+    notes: VecDeque<(f32, Handle<Pitch>)>,
+    elapsed: f32,
+}
+
+fn create_jingles(mut commands: Commands, mut pitches: ResMut<Assets<Pitch>>) {
+    let mut notes = |frequencies: &[f32]| {
+        let last = frequencies.len() - 1;
+        frequencies
+            .iter()
+            .enumerate()
+            .map(|(n, &frequency)| {
+                let length = match n == last {
+                    true => NOTE_SPACING * LAST_NOTE_LENGTH,
+                    false => NOTE_SPACING,
+                };
+                pitches.add(Pitch::new(frequency, Duration::from_secs_f32(length)))
+            })
+            .collect()
+    };
+    commands.insert_resource(Jingles {
+        victory: notes(&VICTORY_NOTES),
+        defeat: notes(&DEFEAT_NOTES),
+    });
+}
+
+/// Start the jingle for how the game ended. After a detonation, the defeat sting waits
+/// for the explosion sound to finish.
+fn queue_jingle(
+    mut queue: ResMut<JingleQueue>,
+    jingles: Res<Jingles>,
+    game_result: Res<GameResult>,
+    game_settings: Res<GameSettings>,
+) {
+    let (notes, start) = match *game_result {
+        GameResult::Unfinished => return,
+        GameResult::Victory => (&jingles.victory, 0.0),
+        GameResult::Surrender => (&jingles.defeat, 0.0),
+        GameResult::Failure if game_settings.explosion_sound == ExplosionSound::None => {
+            (&jingles.defeat, 0.0)
+        }
+        GameResult::Failure => (&jingles.defeat, EXPLOSION_GAP),
+    };
+    queue.elapsed = 0.0;
+    queue.notes = notes
+        .iter()
+        .enumerate()
+        .map(|(n, note)| (start + n as f32 * NOTE_SPACING, note.clone()))
+        .collect();
+}
+
+fn stop_jingle(mut queue: ResMut<JingleQueue>) {
+    queue.notes.clear();
+}
+
+fn play_jingle(
+    mut queue: ResMut<JingleQueue>,
+    game_settings: Res<GameSettings>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    if queue.notes.is_empty() {
+        return;
+    }
+    queue.elapsed += time.delta_seconds();
+    while queue
+        .notes
+        .front()
+        .is_some_and(|(at, _)| *at <= queue.elapsed)
+    {
+        let Some((_, note)) = queue.notes.pop_front() else {
+            break;
+        };
+        if game_settings.music_volume <= 0.0 {
+            continue;
+        }
+        commands.spawn(PitchBundle {
+            source: note,
+            settings: PlaybackSettings {
+                mode: PlaybackMode::Despawn,
+                volume: Volume::new(game_settings.music_volume),
+                ..default()
+            },
+        });
+    }
+}
@@ -1,14 +1,33 @@
+use std::collections::VecDeque;
 use std::fmt::Display;
 
 use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
 use ndarray::prelude::*;
 use rand::prelude::*;
 
 use super::{
     block::{Block, BlockEvent},
-    GamePiece, GameResult, GameState,
+    board::CellView,
+    heatmap::mine_probabilities,
+    save::Autosave,
+    GamePiece, GameResult, GameState, GameStats,
 };
-use crate::{FieldSettings, GameSettings, Safety};
+use crate::{
+    Connectivity, FieldSettings, FieldShape, GameSettings, InputEvent, RevealOnLoss, Safety,
+    WinCondition,
+};
+
+/// Most passes over the board [Minefield::solve_obvious] makes before giving up
+const MAX_SOLVE_PASSES: usize = 100;
+/// Most fields generated looking for one with [GameSettings::no_guess] that can be solved
+/// without guessing. After that, the last one is played anyway.
+const MAX_GENERATION_ATTEMPTS: usize = 200;
+/// Fields with at least this many cells are generated in the background, so the window
+/// stays responsive. Fields with [GameSettings::no_guess] always are, for the retries.
+const BACKGROUND_GENERATION_CELLS: usize = 100_000;
+/// Seconds the notice that no flags are left stays up
+const FLAG_LIMIT_NOTICE_TIME: f32 = 1.5;
 
 pub struct FieldPlugin;
 impl Plugin for FieldPlugin {
@@ -21,28 +40,113 @@ impl Plugin for FieldPlugin {
                 .after(super::block::handle_ray_events)
                 .run_if(GameState::playable()),
         );
+        app.add_systems(
+            Update,
+            send_solve_obvious.before(handle_field_events).run_if(
+                GameState::playable()
+                    .and_then(|game_settings: Res<GameSettings>| game_settings.solve_obvious),
+            ),
+        );
+        app.add_systems(
+            Update,
+            send_free_move.before(handle_field_events).run_if(
+                in_state(GameState::GamePlaying)
+                    .and_then(|stats: Res<GameStats>| stats.free_moves > 0),
+            ),
+        );
+        app.add_systems(
+            Update,
+            poll_generation
+                .before(handle_field_events)
+                .run_if(in_state(GameState::GameGenerating)),
+        );
         app.add_systems(OnEnter(GameState::GameOver), reveal_all);
         app.add_event::<FieldEvent>();
+        app.init_resource::<GenerationReport>();
+        app.init_resource::<FlagLimitReached>();
+        app.init_resource::<BoardSeed>();
     }
 }
 
-#[derive(Event)]
+/// How the current field was generated with [GameSettings::no_guess], for judging how fair
+/// and how hard it is. Left at its default when no-guess generation wasn't used.
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct GenerationReport {
+    /// Number of fields generated before one could be solved without guessing
+    pub attempts: usize,
+    /// Rounds of deductions the solver needed after the first clear, or None if no
+    /// attempt could be solved without guessing
+    pub deduction_passes: Option<usize>,
+}
+
+/// Seed the mines of a field are placed from, so a board can be shared or played again.
+/// The same seed only gives the same board with the same field and game settings,
+/// and the same first clear.
+#[derive(Debug, Default, Resource)]
+pub struct BoardSeed {
+    /// Seed of the current game, or None if it was resumed from a save that didn't record it
+    pub current: Option<u64>,
+    /// Seed to use for the next game, instead of a random one
+    pub next: Option<u64>,
+}
+
+/// Field being generated on the background thread pool, and the events waiting for it
+#[derive(Resource)]
+struct PendingGeneration {
+    task: Task<(Minefield, Option<GenerationReport>)>,
+    /// The first clear the field is generated for, then any events sent along with it
+    events: Vec<FieldEvent>,
+}
+
+/// Seconds left to show that a mark was refused by [GameSettings::limit_flags], if one
+/// just was.
+#[derive(Debug, Default, Resource)]
+pub struct FlagLimitReached(pub Option<f32>);
+
+/// Moves and updates applied to the [Minefield].
+///
+/// Indices are the same as [Block::index]. Other plugins can send these to play the game
+/// programmatically, without going through mouse input.
+#[derive(Event, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 pub enum FieldEvent {
+    /// Register the block entity at an index. Sent when the field is spawned.
     SpawnBlock(Entity, [usize; 3]),
+    /// Clear a block, detonating it if it contains a mine.
+    /// Clearing the first block of a game determines where mines are placed.
     ClearBlock([usize; 3]),
+    /// Mark a block (or unmark if already marked) as containing a mine.
+    MarkBlock([usize; 3]),
+    /// Clear every unmarked neighbor of a revealed block, if as many neighbors are marked
+    /// as it has adjacent mines.
+    ChordBlock([usize; 3]),
+    /// Highlight the blocks that chording at this index would clear, replacing any
+    /// previous preview. `None` removes the preview.
+    PreviewChord(Option<[usize; 3]>),
+    /// Clear or mark every block whose contents follow directly from a revealed number,
+    /// repeating until there are none left.
+    SolveObvious,
+    /// Use up one of [GameSettings::free_safe_moves] to clear a hidden block the numbers
+    /// show is safe, or the least risky one if there is none.
+    FreeMove,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Cell {
     contains: Contains,
     revealed: bool,
+    marked: bool,
     block: Option<Entity>,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// What a cell of the [Minefield] contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Contains {
     Mine,
-    Empty { adjacent_mines: u8 },
+    /// No mine, but this many of the (up to 26) surrounding cells have one.
+    Empty {
+        adjacent_mines: u8,
+    },
 }
 impl Default for Contains {
     fn default() -> Self {
@@ -83,22 +187,88 @@ impl Display for FieldIndex {
     }
 }
 
-#[derive(Component)]
+/// Contents of every cell of the field, and the rules it's played by.
+#[derive(Component, Clone)]
 pub struct Minefield {
     cells: Array3<Cell>,
+    /// Which cells of [Minefield::cells] exist
+    shape: FieldShape,
     density: f64,
     safety: Safety,
+    win_condition: WinCondition,
+    /// Neighbors counted towards each cell's number of adjacent mines
+    connectivity: Connectivity,
+    /// Neighbors revealed when a cell with no adjacent mines is cleared
+    flood_connectivity: Connectivity,
+    /// Whether the flood reveal skips over marked cells
+    flood_respects_flags: bool,
+    /// Whether chording also clears marked neighbors that don't contain a mine
+    chord_corrects_flags: bool,
+    /// Most rings of cells the flood reveal spreads out, if limited
+    max_flood_depth: Option<usize>,
+    /// Remaining number of times a mine will be moved away instead of detonating
+    assist_moves: usize,
+    /// Remaining number of [FieldEvent::FreeMove]s
+    free_moves: usize,
+    /// Seed the mines are placed from, if known
+    seed: Option<u64>,
+    /// Whether mines have been placed yet
+    initialized: bool,
+    /// Index whose chord is being previewed
+    chord_preview_center: Option<FieldIndex>,
+    /// Blocks currently highlighted by the chord preview
+    chord_preview: Vec<Entity>,
 }
 impl Minefield {
-    /// Initialize the [Minefield], placing mines randomly according to [Minefield::density].
-    fn initialize(&mut self, blocks: &Query<(Entity, &Block)>, click_location: FieldIndex) {
-        // Save Block ids
+    /// Whether the cell at `index` is part of the field's shape
+    fn exists(&self, index: impl Into<FieldIndex>) -> bool {
+        let (i, j, k) = *index.into();
+        self.shape.contains([i, j, k], self.cells.dim().into())
+    }
+    /// Save the entity of each [Block] in its cell
+    fn register_blocks(&mut self, blocks: &Query<(Entity, &Block)>) {
         for (entity, block) in blocks {
             self.cells[block.index()].block = Some(entity)
         }
+    }
+    /// Initialize the [Minefield], placing mines randomly according to [Minefield::density].
+    /// With `no_guess`, fields are generated until one can be solved from `click_location`
+    /// without guessing, up to [MAX_GENERATION_ATTEMPTS] times, and how that went is returned.
+    ///
+    /// Touches nothing outside the field, so it can run on a background thread.
+    fn generate(&mut self, click_location: FieldIndex, no_guess: bool) -> Option<GenerationReport> {
+        self.initialized = true;
         info!("Creating minefield");
-        let mut rng = rand::thread_rng();
-        let num_blocks = self.cells.iter().count();
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        if !no_guess {
+            self.place_mines(click_location, &mut rng);
+            return None;
+        }
+        let mut report = GenerationReport::default();
+        while report.attempts < MAX_GENERATION_ATTEMPTS {
+            report.attempts += 1;
+            self.place_mines(click_location, &mut rng);
+            report.deduction_passes = self.deduction_passes_from((*click_location).into());
+            if report.deduction_passes.is_some() {
+                break;
+            }
+        }
+        Some(report)
+    }
+    /// Replace any mines with new ones placed randomly according to [Minefield::density]
+    /// and [Minefield::safety].
+    fn place_mines(&mut self, click_location: FieldIndex, rng: &mut StdRng) {
+        for cell in self.cells.iter_mut() {
+            cell.contains = Contains::default();
+        }
+        let num_blocks = self
+            .cells
+            .indexed_iter()
+            .filter(|(i, _)| self.exists(i))
+            .count();
         let num_mines = (num_blocks as f64 * self.density) as usize;
         debug!(
             "Density {} => num_mines = {}/{}",
@@ -110,7 +280,7 @@ impl Minefield {
             Safety::Safe => vec![click_location],
             Safety::Clear => {
                 let mut safe = vec![click_location];
-                self.foreach_adjacent(click_location, |adj_index| {
+                self.foreach_adjacent(click_location, self.connectivity, |adj_index| {
                     safe.push(adj_index);
                 });
                 safe
@@ -121,6 +291,7 @@ impl Minefield {
             .cells
             .indexed_iter()
             .map(|(i, _)| i.into())
+            .filter(|&i| self.exists(i))
             .filter(|i| {
                 let safe = safe_cells.contains(i);
                 if safe {
@@ -129,7 +300,7 @@ impl Minefield {
                 !safe
             })
             .collect();
-        random_cells.shuffle(&mut rng);
+        random_cells.shuffle(rng);
         // Place mines
         let mut mines_to_place = num_mines;
         let num_cells = random_cells.len();
@@ -147,57 +318,115 @@ impl Minefield {
                 mines_to_place -= 1;
             }
         }
-        // Determine adjacent value in each cell
+        self.compute_adjacency();
+    }
+    /// Determine the number of adjacent mines in each cell, from scratch.
+    fn compute_adjacency(&mut self) {
         let mines: Vec<_> = self
             .cells
-            .indexed_iter()
+            .indexed_iter_mut()
             .filter_map(|(i, c)| match c.contains {
                 Contains::Mine => Some(i),
-                _ => None,
+                Contains::Empty {
+                    ref mut adjacent_mines,
+                } => {
+                    *adjacent_mines = 0;
+                    None
+                }
             })
             .collect();
         for index in mines {
-            debug!("Placed mine at {index:?}");
-            let (i, j, k) = index;
-            let mut increment_adjacent = |i_off, j_off, k_off| {
-                let adj_index = (
-                    i.wrapping_add_signed(i_off),
-                    j.wrapping_add_signed(j_off),
-                    k.wrapping_add_signed(k_off),
-                );
-                if let Some(adj) = self.cells.get_mut(adj_index) {
-                    if let Contains::Empty {
-                        ref mut adjacent_mines,
-                    } = adj.contains
-                    {
-                        debug!("Increment adjacent at {adj_index:?}");
-                        *adjacent_mines += 1;
-                    }
+            debug!("Mine at {index:?}");
+            let mut adjacent = vec![];
+            self.foreach_adjacent(index, self.connectivity, |adj_index| {
+                adjacent.push(adj_index)
+            });
+            for adj_index in adjacent {
+                if let Contains::Empty {
+                    ref mut adjacent_mines,
+                } = self.cells[*adj_index].contains
+                {
+                    debug!("Increment adjacent at {adj_index}");
+                    *adjacent_mines += 1;
                 }
+            }
+        }
+    }
+    /// Number of mines next to `index`, as counted for empty cells by
+    /// [Minefield::compute_adjacency], whatever the cell itself contains
+    fn count_adjacent_mines(&self, index: FieldIndex) -> u8 {
+        let mut count = 0;
+        self.foreach_adjacent(index, self.connectivity, |adj_index| {
+            if self.cells[*adj_index].contains == Contains::Mine {
+                count += 1;
+            }
+        });
+        count
+    }
+    /// Move the mine at `index` to a random hidden, unmarked cell without one.
+    /// The cell is picked from [Minefield::seed] and `index`, so replaying a seeded board
+    /// moves its mines the same way.
+    /// Revealed blocks whose number changes as a result are updated.
+    /// Returns false if there was nowhere to move it.
+    fn relocate_mine(
+        &mut self,
+        index: FieldIndex,
+        block_events: &mut EventWriter<BlockEvent>,
+    ) -> bool {
+        let candidates: Vec<_> = self
+            .cells
+            .indexed_iter()
+            .filter(|(i, cell)| {
+                FieldIndex::from(i) != index
+                    && self.exists(i)
+                    && !cell.revealed
+                    && !cell.marked
+                    && matches!(cell.contains, Contains::Empty { .. })
+            })
+            .map(|(i, _)| FieldIndex::from(i))
+            .collect();
+        let mut rng = match self.seed {
+            Some(seed) => {
+                let (i, j, k) = *index;
+                let (_, size_j, size_k) = self.cells.dim();
+                let offset = ((i * size_j + j) * size_k + k) as u64 + 1;
+                StdRng::seed_from_u64(seed.wrapping_add(offset))
+            }
+            None => StdRng::from_entropy(),
+        };
+        let Some(&target) = candidates.choose(&mut rng) else {
+            return false;
+        };
+        info!("Assist: moving mine from {index} to {target}");
+        let before = self.cells.clone();
+        self.cells[*index].contains = Contains::default();
+        self.cells[*target].contains = Contains::Mine;
+        self.compute_adjacency();
+        for (cell, old) in self.cells.iter().zip(before.iter()) {
+            let (Some(block), true) = (cell.block, cell.revealed) else {
+                continue;
             };
-            for i_off in -1..=1 {
-                for j_off in -1..=1 {
-                    for k_off in -1..=1 {
-                        // The block at index is not adjacent to itself
-                        if i_off == 0 && j_off == 0 && k_off == 0 {
-                            continue;
-                        }
-                        increment_adjacent(i_off, j_off, k_off);
-                    }
-                }
+            if cell.contains != old.contains {
+                block_events.send(BlockEvent::Update(block, cell.contains));
             }
         }
+        true
     }
-    fn foreach_adjacent<F>(&self, index: impl Into<FieldIndex>, mut f: F)
-    where
+    /// Call `f` with the index of each cell neighboring `index` according to `connectivity`.
+    fn foreach_adjacent<F>(
+        &self,
+        index: impl Into<FieldIndex>,
+        connectivity: Connectivity,
+        mut f: F,
+    ) where
         F: FnMut(FieldIndex),
     {
         let (i, j, k) = *index.into();
         for i_off in -1..=1 {
             for j_off in -1..=1 {
                 for k_off in -1..=1 {
-                    // The block at index is not adjacent to itself
-                    if i_off == 0 && j_off == 0 && k_off == 0 {
+                    // Skip offsets that aren't neighbors, including the block at index itself
+                    if !connectivity.includes([i_off, j_off, k_off]) {
                         continue;
                     }
                     // Get a block adjacent to index
@@ -206,8 +435,8 @@ impl Minefield {
                         j.wrapping_add_signed(j_off),
                         k.wrapping_add_signed(k_off),
                     );
-                    // Make sure we have a valid adj_index
-                    if self.cells.get(adj_index).is_none() {
+                    // Make sure we have a valid adj_index, within the field's shape
+                    if self.cells.get(adj_index).is_none() || !self.exists(adj_index) {
                         continue;
                     };
                     f(adj_index.into());
@@ -215,90 +444,727 @@ impl Minefield {
             }
         }
     }
+    /// Reveal the cells neighboring `index` according to [Minefield::flood_connectivity],
+    /// continuing through any that have no adjacent mines, one ring at a time.
+    /// Stops after [Minefield::max_flood_depth] rings, if set.
     fn reveal_adjacent(
         &mut self,
         index: (usize, usize, usize),
         block_events: &mut EventWriter<BlockEvent>,
     ) {
-        let (i, j, k) = index;
-        for i_off in -1..=1 {
-            for j_off in -1..=1 {
-                for k_off in -1..=1 {
-                    // The block at index is not adjacent to itself
-                    if i_off == 0 && j_off == 0 && k_off == 0 {
-                        continue;
+        let mut queue = VecDeque::from([(FieldIndex::from(index), 0)]);
+        while let Some((index, depth)) = queue.pop_front() {
+            if self.max_flood_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            let mut adjacent = vec![];
+            self.foreach_adjacent(index, self.flood_connectivity, |adj_index| {
+                adjacent.push(adj_index)
+            });
+            for adj_index in adjacent {
+                let adj = &mut self.cells[*adj_index];
+                // If the adjacent block is already revealed, don't bother
+                if adj.revealed {
+                    continue;
+                }
+                // Leave the player's marks alone, if they asked us to
+                if adj.marked && self.flood_respects_flags {
+                    continue;
+                }
+                let contains = adj.contains;
+                // Only clearing a mine directly can detonate it, never a flood
+                let Contains::Empty { adjacent_mines } = contains else {
+                    let ((i, j, k), (adj_i, adj_j, adj_k)) = (*index, *adj_index);
+                    let offset = [
+                        adj_i as isize - i as isize,
+                        adj_j as isize - j as isize,
+                        adj_k as isize - k as isize,
+                    ];
+                    // Expected if the flood spreads to neighbors the numbers don't count,
+                    // but otherwise the number it spread from should have counted this mine
+                    if self.connectivity.includes(offset) {
+                        error!("Flood from {index} reached a mine at {adj_index} next to it");
+                    } else {
+                        debug!("Flood from {index} stopped at a mine at {adj_index}");
                     }
-                    // Get a block adjacent to index
-                    let adj_index = (
-                        i.wrapping_add_signed(i_off),
-                        j.wrapping_add_signed(j_off),
-                        k.wrapping_add_signed(k_off),
-                    );
-                    // Make sure we have a valid adj_index
-                    let Some(adj) = self.cells.get_mut(adj_index) else {
-                        continue;
-                    };
-                    // If the adjacent block is already revealed, don't bother
-                    // TODO: maybe not good idea?
-                    if adj.revealed {
-                        continue;
+                    continue;
+                };
+                // Get the entity to send with the message
+                let Some(adj_id) = adj.block else {
+                    continue;
+                };
+                adj.revealed = true;
+                adj.marked = false;
+                // Send a message to reveal this block
+                let event = BlockEvent::Clear(adj_id, contains);
+                debug!("Send {event:?}");
+                block_events.send(event);
+                // Continue only from blocks that were not adjacent to any mines
+                if adjacent_mines == 0 {
+                    queue.push_back((adj_index, depth + 1));
+                }
+            }
+        }
+    }
+    /// Reveal the cell at `index`, and flood outwards if it has no adjacent mines.
+    fn clear_cell(&mut self, index: FieldIndex, block_events: &mut EventWriter<BlockEvent>) {
+        let Some(cell) = self.cells.get(*index) else {
+            return;
+        };
+        if matches!(cell.contains, Contains::Mine)
+            && self.assist_moves > 0
+            && self.relocate_mine(index, block_events)
+        {
+            self.assist_moves -= 1;
+        }
+        let cell = &mut self.cells[*index];
+        // A revealed block can't also be marked
+        cell.revealed = true;
+        cell.marked = false;
+        let contains = cell.contains;
+        let Some(block) = cell.block else {
+            return;
+        };
+        let event = BlockEvent::Clear(block, contains);
+        debug!("Send {event:?}");
+        block_events.send(event);
+        if matches!(contains, Contains::Empty { adjacent_mines } if adjacent_mines == 0) {
+            self.reveal_adjacent(*index, block_events);
+        }
+    }
+    /// Cells that chording at `index` would clear. `None` if the cell isn't a revealed
+    /// number, or the number of marked neighbors doesn't match it.
+    fn chord_targets(&self, index: FieldIndex) -> Option<Vec<FieldIndex>> {
+        let cell = self.cells.get(*index)?;
+        let Contains::Empty { adjacent_mines } = cell.contains else {
+            return None;
+        };
+        if !cell.revealed || adjacent_mines == 0 {
+            return None;
+        }
+        let mut marked = 0;
+        let mut targets = vec![];
+        self.foreach_adjacent(index, self.connectivity, |adj_index| {
+            let adj = &self.cells[*adj_index];
+            if adj.marked {
+                marked += 1;
+            } else if !adj.revealed {
+                targets.push(adj_index);
+            }
+        });
+        (marked == usize::from(adjacent_mines)).then_some(targets)
+    }
+    /// Marked neighbors of `index` that don't actually contain a mine
+    fn misplaced_marks_around(&self, index: FieldIndex) -> Vec<FieldIndex> {
+        let mut misplaced = vec![];
+        self.foreach_adjacent(index, self.connectivity, |adj_index| {
+            let adj = &self.cells[*adj_index];
+            if adj.marked && !matches!(adj.contains, Contains::Mine) {
+                misplaced.push(adj_index);
+            }
+        });
+        misplaced
+    }
+    /// Repeatedly apply the two basic deductions to every revealed number, until nothing
+    /// changes or [MAX_SOLVE_PASSES] is reached:
+    /// if as many neighbors are marked as it has adjacent mines, clear the rest,
+    /// and if its hidden neighbors are all needed to make up the number, mark them.
+    /// No more marks are made once there are `flag_limit` of them, as for the player.
+    /// Returns the number of blocks cleared or marked, and whether any marks were held back.
+    fn solve_obvious(
+        &mut self,
+        block_events: &mut EventWriter<BlockEvent>,
+        flag_limit: Option<usize>,
+    ) -> (usize, bool) {
+        if !self.initialized {
+            return (0, false);
+        }
+        let mut limited = false;
+        let mut moves = 0;
+        for _ in 0..MAX_SOLVE_PASSES {
+            let mut to_clear = vec![];
+            let mut to_mark = vec![];
+            for (index, cell) in self.cells.indexed_iter() {
+                let Contains::Empty { adjacent_mines } = cell.contains else {
+                    continue;
+                };
+                if !cell.revealed || adjacent_mines == 0 {
+                    continue;
+                }
+                let (mut marked, mut hidden) = (0, vec![]);
+                self.foreach_adjacent(index, self.connectivity, |adj_index| {
+                    let adj = &self.cells[*adj_index];
+                    if adj.marked {
+                        marked += 1;
+                    } else if !adj.revealed {
+                        hidden.push(adj_index);
                     }
-                    let contains = adj.contains;
-                    // Don't reveal mines
-                    let Contains::Empty { adjacent_mines } = contains else {
-                        continue;
-                    };
-                    // Get the entity to send with the message
-                    let Some(adj_id) = adj.block else {
-                        continue;
-                    };
-                    adj.revealed = true;
-                    // Send a message to reveal this block
-                    let event = BlockEvent::Clear(adj_id, contains);
-                    debug!("Send {event:?}");
-                    block_events.send(event);
-                    // Recurse only if this block was not adjacent to any mines
-                    if adjacent_mines == 0 {
-                        self.reveal_adjacent(adj_index, block_events);
+                });
+                if hidden.is_empty() {
+                    continue;
+                }
+                if marked == usize::from(adjacent_mines) {
+                    to_clear.extend(hidden);
+                } else if marked + hidden.len() == usize::from(adjacent_mines) {
+                    to_mark.extend(hidden);
+                }
+            }
+            let mut changed = false;
+            for index in to_mark {
+                let cell = &self.cells[*index];
+                let Some(block) = cell.block.filter(|_| !cell.marked && !cell.revealed) else {
+                    continue;
+                };
+                if self.at_flag_limit(flag_limit) {
+                    limited = true;
+                    continue;
+                }
+                let cell = &mut self.cells[*index];
+                cell.marked = true;
+                block_events.send(BlockEvent::Mark(block));
+                moves += 1;
+                changed = true;
+            }
+            for index in to_clear {
+                let cell = &self.cells[*index];
+                if cell.revealed || cell.marked {
+                    continue;
+                }
+                self.clear_cell(index, block_events);
+                moves += 1;
+                changed = true;
+                // Only possible if some marks were wrong
+                if matches!(self.cells[*index].contains, Contains::Mine) {
+                    return (moves, limited);
+                }
+            }
+            if !changed || self.is_won() {
+                break;
+            }
+        }
+        (moves, limited)
+    }
+    /// Hidden cells that the revealed numbers alone prove safe, by the same two deductions
+    /// as [Minefield::solve_obvious], but deducing the mines instead of trusting the marks.
+    fn proven_safe(&self) -> Vec<FieldIndex> {
+        let mut mines = Array3::from_elem(self.cells.dim(), false);
+        let mut safe = Array3::from_elem(self.cells.dim(), false);
+        for _ in 0..MAX_SOLVE_PASSES {
+            let mut changed = false;
+            for (index, cell) in self.cells.indexed_iter() {
+                let Contains::Empty { adjacent_mines } = cell.contains else {
+                    continue;
+                };
+                if !cell.revealed {
+                    continue;
+                }
+                let (mut known, mut unknown) = (0, vec![]);
+                self.foreach_adjacent(index, self.connectivity, |adj_index| {
+                    if mines[*adj_index] {
+                        known += 1;
+                    } else if !self.cells[*adj_index].revealed && !safe[*adj_index] {
+                        unknown.push(adj_index);
                     }
+                });
+                let deduced = if unknown.is_empty() {
+                    continue;
+                } else if known == usize::from(adjacent_mines) {
+                    &mut safe
+                } else if known + unknown.len() == usize::from(adjacent_mines) {
+                    &mut mines
+                } else {
+                    continue;
+                };
+                for adj_index in unknown {
+                    deduced[*adj_index] = true;
                 }
+                changed = true;
+            }
+            if !changed {
+                break;
             }
         }
+        safe.indexed_iter()
+            .filter(|(_, safe)| **safe)
+            .map(|(index, _)| FieldIndex::from(index))
+            .collect()
+    }
+    /// Clear a hidden, unmarked cell if any free moves are left, going only by what the
+    /// player can see: one the revealed numbers prove safe if there is one, or else the
+    /// one least likely to contain a mine as the heatmap estimates it, which may be a mine.
+    /// Ties go to the lowest index, so the pick never depends on where the mines are.
+    fn free_move(&mut self, block_events: &mut EventWriter<BlockEvent>) -> Option<FieldIndex> {
+        if !self.initialized || self.free_moves == 0 {
+            return None;
+        }
+        let proven_safe = self
+            .proven_safe()
+            .into_iter()
+            .find(|index| !self.cells[**index].marked);
+        let target = match proven_safe {
+            Some(target) => target,
+            None => FieldIndex::from(self.least_likely_mine()?),
+        };
+        self.free_moves -= 1;
+        self.clear_cell(target, block_events);
+        Some(target)
+    }
+    /// Hidden, unmarked cell least likely to contain a mine as the heatmap estimates it
+    fn least_likely_mine(&self) -> Option<[usize; 3]> {
+        let views = self
+            .cells
+            .indexed_iter()
+            .filter(|(index, _)| self.exists(index))
+            .map(|((i, j, k), cell)| {
+                let view = match (cell.revealed, cell.marked) {
+                    (true, _) => CellView::Revealed(cell.contains),
+                    (false, true) => CellView::Flagged,
+                    (false, false) => CellView::Hidden,
+                };
+                ([i, j, k], view)
+            })
+            .collect();
+        let probabilities = mine_probabilities(&views, self.connectivity, self.num_mines());
+        probabilities
+            .into_iter()
+            .min_by(|(a, p_a), (b, p_b)| p_a.total_cmp(p_b).then_with(|| a.cmp(b)))
+            .map(|(index, _)| index)
+    }
+    /// Whether every safe cell can be cleared from a fresh start by clearing `first`, then
+    /// only applying the same deductions as [Minefield::solve_obvious], without guessing.
+    /// Ignores what is currently revealed or marked, and never triggers assist moves.
+    pub fn is_solvable_from(&self, first: [usize; 3]) -> bool {
+        self.deduction_passes_from(first).is_some()
+    }
+    /// Rounds of deductions needed to solve the field after clearing `first`, as in
+    /// [Minefield::is_solvable_from], or None if it can't be solved without guessing.
+    /// More rounds mean longer chains of reasoning, and usually a harder field.
+    pub fn deduction_passes_from(&self, first: [usize; 3]) -> Option<usize> {
+        if !self.initialized || !self.exists(first) {
+            return None;
+        }
+        // Just the revealed and marked state, replayed from scratch
+        let mut cells = self.cells.map(|cell| Cell {
+            contains: cell.contains,
+            ..default()
+        });
+        let mut to_clear = vec![FieldIndex::from(first)];
+        let mut passes = 0;
+        loop {
+            // Clear everything deduced, flooding through cells with no adjacent mines
+            while let Some(index) = to_clear.pop() {
+                let cell = &mut cells[*index];
+                if cell.revealed {
+                    continue;
+                }
+                let Contains::Empty { adjacent_mines } = cell.contains else {
+                    return None;
+                };
+                cell.revealed = true;
+                if adjacent_mines == 0 {
+                    // Like the real flood, stepping around mines it can reach
+                    self.foreach_adjacent(index, self.flood_connectivity, |adj_index| {
+                        if !matches!(cells[*adj_index].contains, Contains::Mine) {
+                            to_clear.push(adj_index)
+                        }
+                    });
+                }
+            }
+            let mut to_mark = vec![];
+            for (index, cell) in cells.indexed_iter() {
+                let Contains::Empty { adjacent_mines } = cell.contains else {
+                    continue;
+                };
+                if !cell.revealed || adjacent_mines == 0 {
+                    continue;
+                }
+                let (mut marked, mut hidden) = (0, vec![]);
+                self.foreach_adjacent(index, self.connectivity, |adj_index| {
+                    let adj = &cells[*adj_index];
+                    if adj.marked {
+                        marked += 1;
+                    } else if !adj.revealed {
+                        hidden.push(adj_index);
+                    }
+                });
+                if marked == usize::from(adjacent_mines) {
+                    to_clear.extend(hidden);
+                } else if marked + hidden.len() == usize::from(adjacent_mines) {
+                    to_mark.extend(hidden);
+                }
+            }
+            if to_clear.is_empty() && to_mark.is_empty() {
+                break;
+            }
+            passes += 1;
+            for index in to_mark {
+                cells[*index].marked = true;
+            }
+        }
+        let solved = cells.indexed_iter().all(|(index, cell)| {
+            !self.exists(index) || cell.revealed || matches!(cell.contains, Contains::Mine)
+        });
+        solved.then_some(passes)
+    }
+    /// Update the highlighted chord preview to match the current field.
+    fn refresh_chord_preview(&mut self, block_events: &mut EventWriter<BlockEvent>) {
+        let preview: Vec<Entity> = self
+            .chord_preview_center
+            .and_then(|center| self.chord_targets(center))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|index| self.cells[*index].block)
+            .collect();
+        for &block in &self.chord_preview {
+            if !preview.contains(&block) {
+                block_events.send(BlockEvent::Preview(block, false));
+            }
+        }
+        for &block in &preview {
+            if !self.chord_preview.contains(&block) {
+                block_events.send(BlockEvent::Preview(block, true));
+            }
+        }
+        self.chord_preview = preview;
+    }
+    /// Least number of clears that solve the field, the 3D equivalent of 3BV: one for each
+    /// region of cells with no adjacent mines that flood together (revealing the numbers
+    /// around it too), and one for each other safe cell that no such region reaches.
+    /// Ignores [Minefield::max_flood_depth].
+    pub fn three_bv(&self) -> usize {
+        let is_zero = |cell: &Cell| cell.contains == Contains::default();
+        let mut reached = Array3::from_elem(self.cells.dim(), false);
+        let mut regions = 0;
+        for (index, cell) in self.cells.indexed_iter() {
+            if reached[index] || !self.exists(index) || !is_zero(cell) {
+                continue;
+            }
+            regions += 1;
+            reached[index] = true;
+            let mut queue = VecDeque::from([FieldIndex::from(index)]);
+            while let Some(zero) = queue.pop_front() {
+                self.foreach_adjacent(zero, self.flood_connectivity, |adj_index| {
+                    let adj = &self.cells[*adj_index];
+                    if reached[*adj_index] || matches!(adj.contains, Contains::Mine) {
+                        return;
+                    }
+                    reached[*adj_index] = true;
+                    if is_zero(adj) {
+                        queue.push_back(adj_index);
+                    }
+                });
+            }
+        }
+        let unreached = self
+            .cells
+            .indexed_iter()
+            .filter(|(index, cell)| {
+                self.exists(index) && !reached[*index] && !matches!(cell.contains, Contains::Mine)
+            })
+            .count();
+        regions + unreached
+    }
+    /// Number of cells containing a mine
+    fn num_mines(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| matches!(cell.contains, Contains::Mine))
+            .count()
+    }
+    /// Entity of the [Block] for the cell at `index`, once it's spawned
+    pub(super) fn block_at(&self, [i, j, k]: [usize; 3]) -> Option<Entity> {
+        self.cells.get((i, j, k))?.block
+    }
+    /// Whether the game is in progress, so there is something worth saving
+    pub(super) fn in_progress(&self) -> bool {
+        self.initialized && !self.is_won()
+    }
+    /// Field settings this field was generated with
+    pub(super) fn field_settings(&self) -> FieldSettings {
+        FieldSettings {
+            field_size: self.cells.dim().into(),
+            mine_density: self.density as f32,
+            shape: self.shape,
+            connectivity: Some(self.connectivity),
+        }
+    }
+    /// Serialize the state of the field, to be restored by [Minefield::from_save].
+    pub(super) fn to_save(&self) -> String {
+        let [x, y, z] = self.cells.dim().into();
+        let cells: String = self
+            .cells
+            .iter()
+            .map(|cell| {
+                let bits = u32::from(matches!(cell.contains, Contains::Mine))
+                    | u32::from(cell.revealed) << 1
+                    | u32::from(cell.marked) << 2;
+                char::from_digit(bits, 8).unwrap()
+            })
+            .collect();
+        format!(
+            "size {x} {y} {z}\nshape {:?}\ndensity {}\nwin_condition {:?}\nconnectivity {:?}\n\
+            flood_connectivity {:?}\nflood_respects_flags {}\nchord_corrects_flags {}\n\
+            max_flood_depth {}\nassist_moves {}\nfree_moves {}\nseed {}\ncells {cells}\n",
+            self.shape,
+            self.density,
+            self.win_condition,
+            self.connectivity,
+            self.flood_connectivity,
+            self.flood_respects_flags,
+            self.chord_corrects_flags,
+            self.max_flood_depth
+                .map_or_else(|| "none".to_string(), |max| max.to_string()),
+            self.assist_moves,
+            self.free_moves,
+            self.seed
+                .map_or_else(|| "none".to_string(), |seed| seed.to_string()),
+        )
+    }
+    /// Restore a field serialized by [Minefield::to_save].
+    pub fn from_save(save: &str, safety: Safety) -> Result<Self, String> {
+        let value = |key: &str| {
+            save.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(' '))
+                .ok_or_else(|| format!("missing {key}"))
+        };
+        let size: Vec<usize> = value("size")?
+            .split_whitespace()
+            .map(|n| n.parse().map_err(|err| format!("invalid size: {err}")))
+            .collect::<Result<_, _>>()?;
+        let [x, y, z] = size[..] else {
+            return Err(format!("invalid size: {size:?}"));
+        };
+        // Saves from before shapes were added are always boxes
+        let shape = match value("shape").unwrap_or("Box") {
+            "Box" => FieldShape::Box,
+            "HollowShell" => FieldShape::HollowShell,
+            other => return Err(format!("invalid shape: {other}")),
+        };
+        let density = value("density")?
+            .parse()
+            .map_err(|err| format!("invalid density: {err}"))?;
+        let win_condition = match value("win_condition")? {
+            "RevealAll" => WinCondition::RevealAll,
+            "MarkAll" => WinCondition::MarkAll,
+            other => return Err(format!("invalid win_condition: {other}")),
+        };
+        let connectivity = |key| match value(key)? {
+            "Faces" => Ok(Connectivity::Faces),
+            "Edges" => Ok(Connectivity::Edges),
+            "Vertices" => Ok(Connectivity::Vertices),
+            other => Err(format!("invalid {key}: {other}")),
+        };
+        // Older saves always flooded through marks
+        let flood_respects_flags = match value("flood_respects_flags") {
+            Ok(flag) => flag
+                .parse()
+                .map_err(|err| format!("invalid flood_respects_flags: {err}"))?,
+            Err(_) => false,
+        };
+        // Older saves never cleared marks by chording
+        let chord_corrects_flags = match value("chord_corrects_flags") {
+            Ok(flag) => flag
+                .parse()
+                .map_err(|err| format!("invalid chord_corrects_flags: {err}"))?,
+            Err(_) => false,
+        };
+        // Older saves always flooded without limit
+        let max_flood_depth = match value("max_flood_depth").unwrap_or("none") {
+            "none" => None,
+            max => Some(
+                max.parse()
+                    .map_err(|err| format!("invalid max_flood_depth: {err}"))?,
+            ),
+        };
+        let assist_moves = value("assist_moves")?
+            .parse()
+            .map_err(|err| format!("invalid assist_moves: {err}"))?;
+        // Older saves had no free moves
+        let free_moves = match value("free_moves") {
+            Ok(moves) => moves
+                .parse()
+                .map_err(|err| format!("invalid free_moves: {err}"))?,
+            Err(_) => 0,
+        };
+        // Older saves didn't record the seed
+        let seed = match value("seed").unwrap_or("none") {
+            "none" => None,
+            seed => Some(seed.parse().map_err(|err| format!("invalid seed: {err}"))?),
+        };
+        let cells: Vec<Cell> = value("cells")?
+            .chars()
+            .map(|c| {
+                let bits = c.to_digit(8).ok_or_else(|| format!("invalid cell: {c}"))?;
+                Ok(Cell {
+                    contains: match bits & 1 {
+                        1 => Contains::Mine,
+                        _ => Contains::default(),
+                    },
+                    revealed: bits & 2 != 0,
+                    marked: bits & 4 != 0,
+                    block: None,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        let cells = Array3::from_shape_vec((x, y, z), cells)
+            .map_err(|err| format!("invalid cells: {err}"))?;
+        let mut field = Self {
+            cells,
+            shape,
+            density,
+            safety,
+            win_condition,
+            connectivity: connectivity("connectivity")?,
+            flood_connectivity: connectivity("flood_connectivity")?,
+            flood_respects_flags,
+            chord_corrects_flags,
+            max_flood_depth,
+            assist_moves,
+            free_moves,
+            seed,
+            initialized: true,
+            chord_preview_center: None,
+            chord_preview: Vec::new(),
+        };
+        field.compute_adjacency();
+        Ok(field)
     }
-    /// Return true iff the Minefield has been fully revealed (victory condition)
+    /// Return true iff the Minefield has been fully revealed
     fn fully_revealed(&self) -> bool {
-        for cell in &self.cells {
-            if !cell.revealed && !matches!(cell.contains, Contains::Mine) {
+        for (index, cell) in self.cells.indexed_iter() {
+            if self.exists(index) && !cell.revealed && !matches!(cell.contains, Contains::Mine) {
                 return false;
             }
         }
         true
     }
+    /// Number of marked cells
+    fn num_marked(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.marked).count()
+    }
+    /// Whether no more cells can be marked with `flag_limit` marks allowed, if limited
+    fn at_flag_limit(&self, flag_limit: Option<usize>) -> bool {
+        flag_limit.is_some_and(|limit| self.num_marked() >= limit)
+    }
+    /// Number of marked cells that really contain a mine
+    fn correct_flags(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| cell.marked && matches!(cell.contains, Contains::Mine))
+            .count()
+    }
+    /// Return true iff every mine is marked, and nothing else is
+    fn correctly_marked(&self) -> bool {
+        self.cells
+            .iter()
+            .all(|cell| cell.marked == matches!(cell.contains, Contains::Mine))
+    }
+    /// Return true iff the victory condition has been met
+    fn is_won(&self) -> bool {
+        if !self.initialized {
+            return false;
+        }
+        match self.win_condition {
+            WinCondition::RevealAll => self.fully_revealed(),
+            WinCondition::MarkAll => self.correctly_marked(),
+        }
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn(
     game_settings: Res<GameSettings>,
     field_settings: Res<FieldSettings>,
+    mut autosave: ResMut<Autosave>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut stats: ResMut<GameStats>,
+    mut generation_report: ResMut<GenerationReport>,
+    mut board_seed: ResMut<BoardSeed>,
     mut commands: Commands,
 ) {
+    *generation_report = GenerationReport::default();
+    // Dropping the task of a game that was left mid-generation cancels it
+    commands.remove_resource::<PendingGeneration>();
+    if let Some(field) = autosave.take_resume() {
+        info!("Resuming saved game");
+        board_seed.current = field.seed;
+        stats.mines = field.num_mines();
+        stats.correct_flags = field.correct_flags();
+        stats.free_moves = field.free_moves;
+        stats.three_bv = field.three_bv();
+        next_state.set(GameState::GamePlaying);
+        commands.spawn((field, GamePiece));
+        return;
+    }
+    // With no mines to mark, only clearing everything can win a zen game
+    let (density, win_condition) = if game_settings.zen {
+        (0.0, WinCondition::RevealAll)
+    } else {
+        (
+            field_settings.mine_density.into(),
+            game_settings.win_condition,
+        )
+    };
+    let seed = board_seed.next.take().unwrap_or_else(random);
+    info!("Board seed {seed}");
+    board_seed.current = Some(seed);
     let field = Minefield {
         cells: Array3::default(field_settings.field_size),
-        density: field_settings.mine_density.into(),
+        shape: field_settings.shape,
+        density,
         safety: game_settings.safety,
+        win_condition,
+        connectivity: game_settings.connectivity_for(&field_settings),
+        flood_connectivity: game_settings.flood_connectivity_for(&field_settings),
+        flood_respects_flags: game_settings.flood_respects_flags,
+        chord_corrects_flags: game_settings.chord_corrects_flags,
+        max_flood_depth: game_settings.max_flood_depth,
+        assist_moves: game_settings.assist_moves,
+        free_moves: game_settings.free_safe_moves,
+        seed: Some(seed),
+        initialized: false,
+        chord_preview_center: None,
+        chord_preview: Vec::new(),
+    };
+    stats.mines = if game_settings.zen {
+        0
+    } else {
+        field_settings.num_mines()
     };
+    stats.free_moves = game_settings.free_safe_moves;
     commands.spawn((field, GamePiece));
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn handle_field_events(
-    game_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
     mut game_result: ResMut<GameResult>,
+    mut stats: ResMut<GameStats>,
     blocks: Query<(Entity, &Block)>,
     mut field: Query<&mut Minefield>,
     mut field_events: EventReader<FieldEvent>,
     mut block_events: EventWriter<BlockEvent>,
+    mut generation_report: ResMut<GenerationReport>,
+    mut flag_limit: ResMut<FlagLimitReached>,
+    game_settings: Res<GameSettings>,
+    mut commands: Commands,
 ) {
+    let mut won = false;
+    let mut pending: Option<PendingGeneration> = None;
     for event in field_events.read() {
+        let assist_moves = field.get_single().map_or(0, |field| field.assist_moves);
+        // Nothing can change the board once it's won, e.g. the rest of a sweep that
+        // finished it, so the final stats are exactly what won
+        if won {
+            debug!("Ignoring field event after victory");
+            continue;
+        }
+        // Nor before it exists, so the rest wait until it does
+        if let Some(pending) = &mut pending {
+            debug!("Holding field event while the field is generated");
+            pending.events.push(*event);
+            continue;
+        }
         match event {
             FieldEvent::SpawnBlock(entity, index) => {
                 let mut field = field.single_mut();
@@ -306,43 +1172,774 @@ pub(super) fn handle_field_events(
                     continue;
                 };
                 cell.block = Some(*entity);
+                // Restore the display of blocks in a resumed game
+                if cell.revealed {
+                    block_events.send(BlockEvent::Clear(*entity, cell.contains));
+                } else if cell.marked {
+                    block_events.send(BlockEvent::Mark(*entity));
+                }
             }
             FieldEvent::ClearBlock(index) => {
                 let mut field = field.single_mut();
-                let Some(cell) = field.cells.get_mut(*index) else {
+                if field.cells.get(*index).is_none() {
                     continue;
-                };
-                cell.revealed = true;
-                if matches!(game_state.get(), GameState::GameStart) {
+                }
+                if !field.initialized {
+                    let no_guess = game_settings.no_guess && !game_settings.zen;
+                    field.register_blocks(&blocks);
+                    if no_guess || field.cells.len() >= BACKGROUND_GENERATION_CELLS {
+                        debug!("Transition to GameState::GameGenerating");
+                        next_state.set(GameState::GameGenerating);
+                        let mut generated = field.clone();
+                        let click = FieldIndex::from(index);
+                        let task = AsyncComputeTaskPool::get().spawn(async move {
+                            let report = generated.generate(click, no_guess);
+                            (generated, report)
+                        });
+                        // The clear and any events after it are sent again by poll_generation
+                        // once the field exists
+                        pending = Some(PendingGeneration {
+                            task,
+                            events: vec![*event],
+                        });
+                        continue;
+                    }
                     debug!("Transition to GameState::Playing");
                     next_state.set(GameState::GamePlaying);
-                    field.initialize(&blocks, index.into());
+                    let report = field.generate(index.into(), no_guess);
+                    record_generation(&field, report, &mut stats, &mut generation_report);
+                }
+                let cell = &field.cells[*index];
+                if !cell.revealed && !cell.marked {
+                    stats.clicks += 1;
+                }
+                field.clear_cell(index.into(), &mut block_events);
+            }
+            FieldEvent::ChordBlock(index) => {
+                let mut field = field.single_mut();
+                let Some(mut targets) = field.chord_targets(index.into()) else {
+                    continue;
+                };
+                // Not part of chord_targets, so the preview doesn't give away wrong marks
+                if field.chord_corrects_flags {
+                    targets.extend(field.misplaced_marks_around(index.into()));
+                }
+                stats.clicks += 1;
+                debug!("Chord {targets:?}");
+                for target in targets {
+                    field.clear_cell(target, &mut block_events);
+                }
+            }
+            FieldEvent::SolveObvious => {
+                let (moves, limited) = field
+                    .single_mut()
+                    .solve_obvious(&mut block_events, flag_limit_for(&game_settings, &stats));
+                info!("Solved {moves} obvious moves");
+                if limited {
+                    debug!("Held back marks, every mine is already marked");
+                    flag_limit.0 = Some(FLAG_LIMIT_NOTICE_TIME);
+                }
+            }
+            FieldEvent::FreeMove => {
+                let mut field = field.single_mut();
+                if let Some(target) = field.free_move(&mut block_events) {
+                    info!("Free move cleared {target}, {} left", field.free_moves);
                 }
-                // Get the updated field
+                stats.free_moves = field.free_moves;
+            }
+            FieldEvent::PreviewChord(center) => {
+                field.single_mut().chord_preview_center = center.map(FieldIndex::from);
+            }
+            FieldEvent::MarkBlock(index) => {
+                let mut field = field.single_mut();
                 let Some(cell) = field.cells.get_mut(*index) else {
                     continue;
                 };
-                let contains = cell.contains;
-                let event = BlockEvent::Clear(cell.block.unwrap(), contains);
-                debug!("Send {event:?}");
-                block_events.send(event);
-                if matches!(contains, Contains::Empty { adjacent_mines } if adjacent_mines == 0) {
-                    field.reveal_adjacent((index[0], index[1], index[2]), &mut block_events);
+                let Some(block) = cell.block else {
+                    continue;
+                };
+                if cell.revealed {
+                    debug!("Ignoring mark on revealed block {block:?}");
+                    continue;
                 }
-                if field.fully_revealed() {
-                    info!("Victory!");
-                    debug!("Transition to GameState::Ended");
-                    *game_result = GameResult::Victory;
-                    next_state.set(GameState::GameOver);
+                let adding = !cell.marked;
+                if adding && field.at_flag_limit(flag_limit_for(&game_settings, &stats)) {
+                    debug!("Ignoring mark on {block:?}, every mine is already marked");
+                    flag_limit.0 = Some(FLAG_LIMIT_NOTICE_TIME);
+                    continue;
                 }
+                stats.clicks += 1;
+                let cell = &mut field.cells[*index];
+                cell.marked = adding;
+                let event = BlockEvent::Mark(block);
+                debug!("Send {event:?}");
+                block_events.send(event);
             }
         }
+        if matches!(event, FieldEvent::SpawnBlock(..)) {
+            continue;
+        }
+        field.single_mut().refresh_chord_preview(&mut block_events);
+        // Marks can change in many ways (clears unmark, mines move), so just count again
+        stats.correct_flags = field.single().correct_flags();
+        // Moving a mine changes the numbers around both cells, and so the clears needed
+        if field.single().assist_moves != assist_moves {
+            stats.three_bv = field.single().three_bv();
+        }
+        // A board with no mines next to the first clear can be won by it, in which case
+        // this replaces the transition to GameState::GamePlaying and the game goes straight
+        // from GameState::GameStart to GameState::GameOver
+        if field.single().is_won() {
+            info!("Victory!");
+            debug!("Transition to GameState::Ended");
+            *game_result = GameResult::Victory;
+            next_state.set(GameState::GameOver);
+            won = true;
+        }
+    }
+    if let Some(pending) = pending {
+        commands.insert_resource(pending);
+    }
+}
+
+/// Most marks allowed on the field, if [GameSettings::limit_flags] limits them
+fn flag_limit_for(game_settings: &GameSettings, stats: &GameStats) -> Option<usize> {
+    game_settings.limit_flags.then_some(stats.mines)
+}
+
+/// Update the stats and [GenerationReport] for a newly generated field
+fn record_generation(
+    field: &Minefield,
+    report: Option<GenerationReport>,
+    stats: &mut GameStats,
+    generation_report: &mut GenerationReport,
+) {
+    if let Some(report) = report {
+        match report.deduction_passes {
+            Some(passes) => info!(
+                "Generated a no-guess field in {} attempts, needing {passes} \
+                rounds of deductions",
+                report.attempts
+            ),
+            None => warn!(
+                "No field could be solved without guessing in {} attempts",
+                report.attempts
+            ),
+        }
+        *generation_report = report;
     }
+    stats.mines = field.num_mines();
+    stats.three_bv = field.three_bv();
 }
 
-fn reveal_all(mut field: Query<&mut Minefield>, mut block_events: EventWriter<BlockEvent>) {
-    for cell in field.single_mut().cells.iter_mut() {
+/// Check on the field being generated in the background, and once it's done, start
+/// playing with the first clear it was generated for
+fn poll_generation(
+    pending: Option<ResMut<PendingGeneration>>,
+    mut field: Query<&mut Minefield>,
+    mut field_events: EventWriter<FieldEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut stats: ResMut<GameStats>,
+    mut generation_report: ResMut<GenerationReport>,
+    mut commands: Commands,
+) {
+    let Some(mut pending) = pending else {
+        return;
+    };
+    let Some((generated, report)) = block_on(poll_once(&mut pending.task)) else {
+        return;
+    };
+    commands.remove_resource::<PendingGeneration>();
+    let mut field = field.single_mut();
+    *field = generated;
+    record_generation(&field, report, &mut stats, &mut generation_report);
+    debug!("Transition to GameState::Playing");
+    next_state.set(GameState::GamePlaying);
+    field_events.send_batch(pending.events.drain(..));
+}
+
+/// Show the contents of the field once the game is over.
+/// After a loss, how much is shown depends on [GameSettings::reveal_on_loss].
+fn reveal_all(
+    mut field: Query<&mut Minefield>,
+    mut block_events: EventWriter<BlockEvent>,
+    game_result: Res<GameResult>,
+    game_settings: Res<GameSettings>,
+) {
+    let reveal = match *game_result {
+        GameResult::Failure => game_settings.reveal_on_loss,
+        _ => RevealOnLoss::Everything,
+    };
+    let mut field = field.single_mut();
+    let mut annotations = vec![];
+    for (index, cell) in field.cells.indexed_iter_mut() {
+        let is_mine = matches!(cell.contains, Contains::Mine);
+        let shown = match reveal {
+            // Still highlight the mine that was detonated
+            RevealOnLoss::HitOnly => is_mine && cell.revealed,
+            RevealOnLoss::AllMines => is_mine,
+            RevealOnLoss::Everything => true,
+        };
+        // Revealed empty blocks already show their contents
+        if !shown || (cell.revealed && !is_mine) {
+            continue;
+        }
         cell.revealed = true;
-        block_events.send(BlockEvent::EndReveal(cell.block.unwrap(), cell.contains));
+        if let Some(block) = cell.block {
+            block_events.send(BlockEvent::EndReveal(block, cell.contains));
+            if is_mine && game_settings.annotate_mines {
+                annotations.push((block, index));
+            }
+        }
+    }
+    for (block, index) in annotations {
+        let adjacent_mines = field.count_adjacent_mines(index.into());
+        block_events.send(BlockEvent::AnnotateMine(block, adjacent_mines));
+    }
+}
+
+fn send_free_move(
+    mut input_events: EventReader<InputEvent>,
+    mut field_events: EventWriter<FieldEvent>,
+    inspecting: Res<super::Inspecting>,
+) {
+    for event in input_events.read() {
+        if matches!(event, InputEvent::FreeMove) && !inspecting.0 {
+            field_events.send(FieldEvent::FreeMove);
+        }
+    }
+}
+
+fn send_solve_obvious(
+    mut input_events: EventReader<InputEvent>,
+    mut field_events: EventWriter<FieldEvent>,
+    inspecting: Res<super::Inspecting>,
+) {
+    for event in input_events.read() {
+        if matches!(event, InputEvent::SolveObvious) && !inspecting.0 {
+            field_events.send(FieldEvent::SolveObvious);
+        }
+    }
+}
+
+/// Known boards, and apps to play them in, for tests of the game logic
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use crate::game::block::{handle_block_events, BlockVisualChanged};
+    use crate::game::headless::spawn_blocks;
+    use crate::game::{GameLostEvent, GameTimer, Inspecting, LastReveal};
+    use crate::InputEvent;
+
+    /// Field of `size` with mines at `mines`, restored like a save that records nothing
+    /// else, so every other option has the value older saves get
+    pub(crate) fn board(size: [usize; 3], mines: &[[usize; 3]]) -> Minefield {
+        let [x, y, z] = size;
+        let cells: String = (0..x)
+            .flat_map(|i| (0..y).flat_map(move |j| (0..z).map(move |k| [i, j, k])))
+            .map(|index| if mines.contains(&index) { '1' } else { '0' })
+            .collect();
+        let save = format!(
+            "size {x} {y} {z}\ndensity 0\nwin_condition RevealAll\nconnectivity Vertices\n\
+            flood_connectivity Vertices\nassist_moves 0\ncells {cells}\n"
+        );
+        Minefield::from_save(&save, Safety::Random).unwrap()
+    }
+
+    /// App running the minefield and block logic without a window or any assets, like
+    /// [crate::HeadlessPlugin], with a new field of `field_settings` in [GameState::GameStart]
+    pub(crate) fn field_app(game_settings: GameSettings, field_settings: FieldSettings) -> App {
+        let mut app = App::new();
+        app.init_state::<GameState>()
+            .insert_resource(game_settings)
+            .insert_resource(field_settings)
+            .init_resource::<GameResult>()
+            .init_resource::<GameStats>()
+            .init_resource::<Autosave>()
+            .init_resource::<Inspecting>()
+            .init_resource::<LastReveal>()
+            .init_resource::<GameTimer>()
+            .add_event::<BlockEvent>()
+            .add_event::<BlockVisualChanged>()
+            .add_event::<GameLostEvent>()
+            .add_event::<InputEvent>()
+            .add_plugins(FieldPlugin)
+            .add_systems(OnEnter(GameState::GameStart), spawn_blocks)
+            .add_systems(
+                Update,
+                handle_block_events
+                    .after(handle_field_events)
+                    .run_if(GameState::in_game()),
+            );
+        app.world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GameStart);
+        app.update();
+        app
+    }
+
+    /// A [field_app] playing `board` instead, as if it was resumed
+    pub(crate) fn board_app(game_settings: GameSettings, mut board: Minefield) -> App {
+        let mut app = field_app(game_settings, board.field_settings());
+        let world = &mut app.world;
+        let blocks: Vec<_> = world
+            .query::<(Entity, &Block)>()
+            .iter(world)
+            .map(|(entity, block)| (entity, block.index()))
+            .collect();
+        for (entity, [i, j, k]) in blocks {
+            board.cells[(i, j, k)].block = Some(entity);
+        }
+        let mut stats = world.resource_mut::<GameStats>();
+        stats.mines = board.num_mines();
+        stats.three_bv = board.three_bv();
+        *world.query::<&mut Minefield>().single_mut(world) = board;
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GamePlaying);
+        app.update();
+        app
+    }
+
+    /// Send `events` and run a frame to handle them
+    pub(crate) fn play(app: &mut App, events: impl IntoIterator<Item = FieldEvent>) {
+        app.world
+            .resource_mut::<Events<FieldEvent>>()
+            .send_batch(events);
+        app.update();
+    }
+
+    /// The block entity at `index`
+    pub(crate) fn entity_at(app: &mut App, index: [usize; 3]) -> Entity {
+        let world = &mut app.world;
+        world
+            .query::<(Entity, &Block)>()
+            .iter(world)
+            .find(|(_, block)| block.index() == index)
+            .map(|(entity, _)| entity)
+            .expect("every cell of the board has a block")
+    }
+
+    /// Where the mines of the field are, once it has been generated
+    pub(crate) fn mines(app: &mut App) -> Vec<[usize; 3]> {
+        let world = &mut app.world;
+        let field = world.query::<&Minefield>().single(world);
+        field
+            .cells
+            .indexed_iter()
+            .filter(|(_, cell)| cell.contains == Contains::Mine)
+            .map(|((i, j, k), _)| [i, j, k])
+            .collect()
+    }
+
+    /// What the block at `index` was revealed to contain, if it was, and whether it's marked
+    pub(crate) fn block_at(app: &mut App, index: [usize; 3]) -> (Option<Contains>, bool) {
+        let entity = entity_at(app, index);
+        let block = app.world.get::<Block>(entity).unwrap();
+        (block.revealed(), block.marked())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::*;
+    use super::*;
+    use crate::game::GameLostEvent;
+
+    fn result(app: &App) -> GameResult {
+        *app.world.resource::<GameResult>()
+    }
+
+    #[test]
+    fn reveal_all_wins_once_every_safe_cell_is_cleared() {
+        let mut app = board_app(default(), board([3, 1, 1], &[[0, 0, 0]]));
+        play(&mut app, [FieldEvent::ClearBlock([1, 0, 0])]);
+        assert_eq!(result(&app), GameResult::Unfinished);
+        // Marking the mine doesn't matter
+        play(&mut app, [FieldEvent::MarkBlock([0, 0, 0])]);
+        assert_eq!(result(&app), GameResult::Unfinished);
+        play(&mut app, [FieldEvent::ClearBlock([2, 0, 0])]);
+        assert_eq!(result(&app), GameResult::Victory);
+    }
+
+    #[test]
+    fn mark_all_wins_only_without_wrong_marks() {
+        let mut field = board([3, 1, 1], &[[0, 0, 0]]);
+        field.win_condition = WinCondition::MarkAll;
+        let mut app = board_app(default(), field);
+        play(
+            &mut app,
+            [
+                FieldEvent::MarkBlock([2, 0, 0]),
+                FieldEvent::MarkBlock([0, 0, 0]),
+            ],
+        );
+        assert_eq!(block_at(&mut app, [0, 0, 0]), (None, true));
+        assert_eq!(result(&app), GameResult::Unfinished);
+        play(&mut app, [FieldEvent::MarkBlock([2, 0, 0])]);
+        assert_eq!(result(&app), GameResult::Victory);
+    }
+
+    /// Number of revealed blocks
+    fn revealed(app: &mut App) -> usize {
+        let world = &mut app.world;
+        world
+            .query::<&Block>()
+            .iter(world)
+            .filter(|block| block.revealed().is_some())
+            .count()
+    }
+
+    #[test]
+    fn flood_connectivity_is_independent_of_adjacency() {
+        use Connectivity::{Faces, Vertices};
+        // Mines in two opposite corners of a 3x3 square, so the zeros in the other corners
+        // and the middle only touch diagonally
+        let mines = [[2, 0, 0], [0, 2, 0]];
+        // For each pair, how many blocks the flood reveals, and what the middle shows
+        for (connectivity, flood_connectivity, flooded, middle) in [
+            (Faces, Faces, 3, None),
+            (Faces, Vertices, 7, Some(0)),
+            (Vertices, Faces, 3, None),
+            (Vertices, Vertices, 4, Some(2)),
+        ] {
+            let mut field = board([3, 3, 1], &mines);
+            field.connectivity = connectivity;
+            field.flood_connectivity = flood_connectivity;
+            field.compute_adjacency();
+            let mut app = board_app(default(), field);
+            play(&mut app, [FieldEvent::ClearBlock([0, 0, 0])]);
+            let pair = (connectivity, flood_connectivity);
+            assert_eq!(revealed(&mut app), flooded, "{pair:?}");
+            // The number only depends on the adjacency
+            let middle = middle.map(|adjacent_mines| Contains::Empty { adjacent_mines });
+            assert_eq!(block_at(&mut app, [1, 1, 0]).0, middle, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn losing_reveals_as_much_as_asked() {
+        let mine = Some(Contains::Mine);
+        let one = Some(Contains::Empty { adjacent_mines: 1 });
+        // What the detonated mine, the other mine and the untouched empty block show
+        for (reveal_on_loss, shown) in [
+            (RevealOnLoss::HitOnly, [mine, None, None]),
+            (RevealOnLoss::AllMines, [mine, mine, None]),
+            (RevealOnLoss::Everything, [mine, mine, one]),
+        ] {
+            let game_settings = GameSettings {
+                reveal_on_loss,
+                ..default()
+            };
+            let mut app = board_app(game_settings, board([4, 1, 1], &[[0, 0, 0], [3, 0, 0]]));
+            play(&mut app, [FieldEvent::ClearBlock([1, 0, 0])]);
+            play(&mut app, [FieldEvent::ClearBlock([0, 0, 0])]);
+            app.update();
+            assert_eq!(result(&app), GameResult::Failure);
+            let blocks = [[0, 0, 0], [3, 0, 0], [2, 0, 0]].map(|index| block_at(&mut app, index).0);
+            assert_eq!(blocks, shown, "{reveal_on_loss:?}");
+            assert_eq!(block_at(&mut app, [1, 0, 0]).0, one);
+        }
+    }
+
+    #[test]
+    fn flood_keeps_flags_next_to_zeros_by_default() {
+        let zero = Some(Contains::Empty { adjacent_mines: 0 });
+        for (respects_flags, flagged) in [
+            (GameSettings::default().flood_respects_flags, (None, true)),
+            (false, (zero, false)),
+        ] {
+            // One mine at the end, and a wrong flag on the zero next to the one cleared
+            let mut field = board([4, 1, 1], &[[0, 0, 0]]);
+            field.flood_respects_flags = respects_flags;
+            let mut app = board_app(default(), field);
+            play(
+                &mut app,
+                [
+                    FieldEvent::MarkBlock([2, 0, 0]),
+                    FieldEvent::ClearBlock([3, 0, 0]),
+                ],
+            );
+            assert_eq!(block_at(&mut app, [2, 0, 0]), flagged, "{respects_flags}");
+        }
+    }
+
+    #[test]
+    fn solvable_board_needs_no_guess_but_a_split_one_does() {
+        // Clearing the far end floods up to the 1, which gives away the mine
+        let solvable = board([4, 1, 1], &[[0, 0, 0]]);
+        assert!(solvable.is_solvable_from([3, 0, 0]));
+        assert_eq!(solvable.deduction_passes_from([3, 0, 0]), Some(1));
+        // The mine in the middle cuts off the far end, which nothing can tell apart
+        let forced_guess = board([3, 1, 1], &[[1, 0, 0]]);
+        assert!(!forced_guess.is_solvable_from([0, 0, 0]));
+        assert_eq!(forced_guess.deduction_passes_from([0, 0, 0]), None);
+        // Clearing a mine first leaves nothing to solve from
+        assert!(!solvable.is_solvable_from([0, 0, 0]));
+    }
+
+    #[test]
+    fn correct_flags_follow_every_toggle() {
+        let mut app = board_app(default(), board([4, 1, 1], &[[0, 0, 0], [3, 0, 0]]));
+        for (toggle, correct) in [
+            ([0, 0, 0], 1),
+            // A wrong flag doesn't count
+            ([1, 0, 0], 1),
+            ([3, 0, 0], 2),
+            // Removing a correct flag takes it away again
+            ([0, 0, 0], 1),
+            ([1, 0, 0], 1),
+            ([0, 0, 0], 2),
+        ] {
+            play(&mut app, [FieldEvent::MarkBlock(toggle)]);
+            let stats = app.world.resource::<GameStats>();
+            assert_eq!(stats.correct_flags, correct, "after toggling {toggle:?}");
+        }
+    }
+
+    #[test]
+    fn flood_stops_after_max_depth_rings() {
+        // Empty but for one mine in the far corner
+        for (max_flood_depth, flooded) in [(Some(1), 4), (Some(2), 9), (Some(5), 36), (None, 80)] {
+            let mut field = board([9, 9, 1], &[[8, 8, 0]]);
+            field.max_flood_depth = max_flood_depth;
+            let mut app = board_app(default(), field);
+            play(&mut app, [FieldEvent::ClearBlock([0, 0, 0])]);
+            assert_eq!(revealed(&mut app), flooded, "{max_flood_depth:?}");
+        }
+    }
+
+    #[test]
+    fn flood_never_exposes_a_mine() {
+        use Connectivity::{Edges, Faces, Vertices};
+        let mines = [[1, 1, 1], [3, 0, 2], [0, 3, 3], [2, 2, 3]];
+        for connectivity in [Faces, Edges, Vertices] {
+            for flood_connectivity in [Faces, Edges, Vertices] {
+                let mut field = board([4, 4, 4], &mines);
+                field.connectivity = connectivity;
+                field.flood_connectivity = flood_connectivity;
+                field.compute_adjacency();
+                let zeros: Vec<_> = field
+                    .cells
+                    .indexed_iter()
+                    .filter(|(_, cell)| cell.contains == Contains::Empty { adjacent_mines: 0 })
+                    .map(|((i, j, k), _)| FieldEvent::ClearBlock([i, j, k]))
+                    .collect();
+                let mut app = board_app(default(), field);
+                // Flood from every cell that can start one
+                play(&mut app, zeros);
+                let pair = (connectivity, flood_connectivity);
+                assert_ne!(result(&app), GameResult::Failure, "{pair:?}");
+                for mine in mines {
+                    assert_eq!(block_at(&mut app, mine), (None, false), "{pair:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flag_limit_rejects_one_flag_more_than_the_mines() {
+        let game_settings = GameSettings {
+            limit_flags: true,
+            ..default()
+        };
+        // Two mines at the ends, and two wrong flags on zeros that hold back the flood
+        let mut field = board([7, 1, 1], &[[0, 0, 0], [6, 0, 0]]);
+        field.flood_respects_flags = true;
+        let mut app = board_app(game_settings, field);
+        play(
+            &mut app,
+            [
+                FieldEvent::MarkBlock([3, 0, 0]),
+                FieldEvent::MarkBlock([4, 0, 0]),
+                FieldEvent::ClearBlock([2, 0, 0]),
+            ],
+        );
+        let notice = |app: &mut App| app.world.resource_mut::<FlagLimitReached>().0.take();
+        assert_eq!(notice(&mut app), None);
+        // Neither the player nor the solver marks the obvious mine next to the 1
+        for (by, event) in [
+            ("player", FieldEvent::MarkBlock([0, 0, 0])),
+            ("solver", FieldEvent::SolveObvious),
+        ] {
+            play(&mut app, [event]);
+            assert_eq!(block_at(&mut app, [0, 0, 0]), (None, false), "{by}");
+            assert!(notice(&mut app).is_some(), "{by}");
+        }
+        // Unmarking always works, and frees a flag
+        play(&mut app, [FieldEvent::MarkBlock([4, 0, 0])]);
+        assert_eq!(block_at(&mut app, [4, 0, 0]), (None, false));
+        play(&mut app, [FieldEvent::SolveObvious]);
+        assert_eq!(block_at(&mut app, [0, 0, 0]), (None, true));
+        assert_eq!(notice(&mut app), None);
+    }
+
+    #[test]
+    fn chording_corrects_a_misplaced_flag_only_if_asked() {
+        let zero = Some(Contains::Empty { adjacent_mines: 0 });
+        for (chord_corrects_flags, misplaced) in [(false, (None, true)), (true, (zero, false))] {
+            // The 1 in the middle is satisfied by the wrong flag, so chording it also hits
+            // the mine either way
+            let mut field = board([3, 1, 1], &[[0, 0, 0]]);
+            field.chord_corrects_flags = chord_corrects_flags;
+            let mut app = board_app(default(), field);
+            play(
+                &mut app,
+                [
+                    FieldEvent::ClearBlock([1, 0, 0]),
+                    FieldEvent::MarkBlock([2, 0, 0]),
+                    FieldEvent::ChordBlock([1, 0, 0]),
+                ],
+            );
+            assert_eq!(result(&app), GameResult::Failure);
+            let flag = block_at(&mut app, [2, 0, 0]);
+            assert_eq!(flag, misplaced, "{chord_corrects_flags}");
+        }
+    }
+
+    #[test]
+    fn three_bv_of_known_boards() {
+        for (size, mines, three_bv) in [
+            // One zero region that reaches the only number
+            ([3, 1, 1], &[[0, 0, 0]][..], 1),
+            // Two zero regions, one each side of the mine
+            ([5, 1, 1], &[[2, 0, 0]], 2),
+            // Only numbers, each clicked on its own
+            ([3, 1, 1], &[[1, 0, 0]], 2),
+            ([4, 1, 1], &[[0, 0, 0], [3, 0, 0]], 2),
+            // Zeros in opposite corners, kept apart by the 2 between them
+            ([3, 3, 1], &[[2, 0, 0], [0, 2, 0]], 2),
+            // A zero region, and two numbers past the mine that it doesn't reach
+            ([6, 1, 1], &[[2, 0, 0], [5, 0, 0]], 3),
+        ] {
+            assert_eq!(
+                board(size, mines).three_bv(),
+                three_bv,
+                "{size:?} {mines:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn one_click_wins_an_empty_zen_board() {
+        let game_settings = GameSettings {
+            zen: true,
+            ..default()
+        };
+        let field_settings = FieldSettings {
+            field_size: [3, 3, 3],
+            ..FieldSettings::small()
+        };
+        let mut app = field_app(game_settings, field_settings);
+        play(&mut app, [FieldEvent::ClearBlock([0, 0, 0])]);
+        assert_eq!(result(&app), GameResult::Victory);
+        assert_eq!(revealed(&mut app), 27);
+        let stats = app.world.resource::<GameStats>();
+        assert_eq!((stats.mines, stats.revealed, stats.clicks), (0, 27, 1));
+        assert_eq!(stats.three_bv, 1);
+        app.update();
+        let state = app.world.resource::<State<GameState>>();
+        assert_eq!(state.get(), &GameState::GameOver);
+    }
+
+    #[test]
+    fn relocating_a_mine_keeps_the_mine_count() {
+        let mut field = board([4, 1, 1], &[[1, 0, 0]]);
+        field.assist_moves = 1;
+        let mut app = board_app(default(), field);
+        // Reveal the 1 next to the mine first, so the only cell left to move it to is the end
+        play(&mut app, [FieldEvent::ClearBlock([3, 0, 0])]);
+        play(&mut app, [FieldEvent::ClearBlock([1, 0, 0])]);
+        // Which leaves nothing else to clear
+        assert_eq!(result(&app), GameResult::Victory);
+        let world = &mut app.world;
+        let field = world.query::<&Minefield>().single(world);
+        assert_eq!(field.num_mines(), 1);
+        assert_eq!(field.assist_moves, 0);
+        assert_eq!(field.cells[(0, 0, 0)].contains, Contains::Mine);
+        for (index, cell) in field.cells.indexed_iter() {
+            if let Contains::Empty { adjacent_mines } = cell.contains {
+                let counted = field.count_adjacent_mines(index.into());
+                assert_eq!(adjacent_mines, counted, "{index:?}");
+            }
+        }
+        // The cleared cell shows its new number, and the number next to it was updated
+        let one = Some(Contains::Empty { adjacent_mines: 1 });
+        let zero = Some(Contains::Empty { adjacent_mines: 0 });
+        assert_eq!(block_at(&mut app, [1, 0, 0]), (one, false));
+        assert_eq!(block_at(&mut app, [2, 0, 0]), (zero, false));
+    }
+
+    #[test]
+    fn free_moves_only_clear_safe_blocks_until_used_up() {
+        // Without a flood running to the far end, each free move clears two more blocks
+        let mut field = board([9, 1, 1], &[[0, 0, 0]]);
+        field.max_flood_depth = Some(1);
+        field.free_moves = 2;
+        assert!(field.is_solvable_from([8, 0, 0]));
+        let game_settings = GameSettings {
+            free_safe_moves: 2,
+            ..default()
+        };
+        let mut app = board_app(game_settings, field);
+        play(&mut app, [FieldEvent::ClearBlock([8, 0, 0])]);
+        assert_eq!(revealed(&mut app), 2);
+        for (free_moves, cleared) in [(1, 4), (0, 6), (0, 6)] {
+            play(&mut app, [FieldEvent::FreeMove]);
+            assert!(app.world.resource::<Events<GameLostEvent>>().is_empty());
+            assert_eq!(app.world.resource::<GameStats>().free_moves, free_moves);
+            assert_eq!(
+                revealed(&mut app),
+                cleared,
+                "with {free_moves} free moves left"
+            );
+        }
+        assert_eq!(result(&app), GameResult::Unfinished);
+    }
+
+    #[test]
+    fn generating_in_the_background_replays_the_held_events() {
+        AsyncComputeTaskPool::get_or_init(bevy::tasks::TaskPool::new);
+        let game_settings = GameSettings {
+            no_guess: true,
+            // Keep the flood from reaching the far corner
+            max_flood_depth: Some(1),
+            ..default()
+        };
+        let field_settings = FieldSettings {
+            field_size: [5, 5, 1],
+            mine_density: 0.2,
+            ..default()
+        };
+        let mut app = field_app(game_settings, field_settings);
+        play(
+            &mut app,
+            [
+                FieldEvent::ClearBlock([0, 0, 0]),
+                FieldEvent::MarkBlock([4, 4, 0]),
+            ],
+        );
+        // Nothing happens until the field exists
+        assert_eq!(block_at(&mut app, [0, 0, 0]), (None, false));
+        assert_eq!(block_at(&mut app, [4, 4, 0]), (None, false));
+        for _ in 0..1000 {
+            if *app.world.resource::<State<GameState>>() == GameState::GamePlaying {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            app.update();
+        }
+        assert_eq!(
+            *app.world.resource::<State<GameState>>(),
+            GameState::GamePlaying
+        );
+        app.update();
+        assert_eq!(mines(&mut app).len(), 5);
+        assert_eq!(app.world.resource::<GameStats>().mines, 5);
+        assert!(matches!(
+            block_at(&mut app, [0, 0, 0]).0,
+            Some(Contains::Empty { .. })
+        ));
+        assert!(block_at(&mut app, [4, 4, 0]).1);
     }
 }
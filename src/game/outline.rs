@@ -0,0 +1,127 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::render_resource::Face;
+
+use super::block::Block;
+use crate::{GameSettings, GameState, InputEvent};
+
+/// Size of the shell drawn behind each hidden block with [GameSettings::high_contrast],
+/// relative to the block. The part sticking out past the block is its outline.
+const OUTLINE_SCALE: f32 = 1.12;
+/// Opacity of outlines while peeking inside the field, matching the blocks
+const PEEK_ALPHA: f32 = 0.1;
+
+pub struct OutlinePlugin;
+impl Plugin for OutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, create_outline_assets)
+            .add_systems(Update, (peek, sync_outlines).run_if(GameState::in_game()))
+            .add_systems(OnExit(GameState::GamePlaying), stop_peeking);
+    }
+}
+
+/// Dark shell around a hidden block, as a child of the block
+#[derive(Component)]
+struct Outline;
+
+/// On a block, the [Outline] spawned around it
+#[derive(Component)]
+struct Outlined(Entity);
+
+#[derive(Resource)]
+struct OutlineAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn create_outline_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(OutlineAssets {
+        mesh: meshes.add(Cuboid::from_size(Vec3::ONE)),
+        // Only the inside of the shell is drawn, so it shows around the block but
+        // never over it
+        material: materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            unlit: true,
+            cull_mode: Some(Face::Front),
+            ..default()
+        }),
+    });
+}
+
+/// Give every hidden block an [Outline] with [GameSettings::high_contrast], and take them
+/// away from blocks once they're revealed or the setting is turned off.
+/// Outlines lost along with the other children of a block are put back.
+fn sync_outlines(
+    blocks: Query<(Entity, &Block, Option<&Outlined>)>,
+    outlines: Query<(), With<Outline>>,
+    assets: Res<OutlineAssets>,
+    game_settings: Res<GameSettings>,
+    mut commands: Commands,
+) {
+    for (entity, block, outlined) in &blocks {
+        let wanted = game_settings.high_contrast && block.revealed().is_none();
+        let outline = outlined
+            .map(|outlined| outlined.0)
+            .filter(|outline| outlines.contains(*outline));
+        match (wanted, outline) {
+            (true, None) => {
+                let outline = commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: assets.mesh.clone(),
+                            material: assets.material.clone(),
+                            transform: Transform::from_scale(Vec3::splat(OUTLINE_SCALE)),
+                            ..default()
+                        },
+                        NotShadowCaster,
+                        Outline,
+                    ))
+                    .id();
+                commands
+                    .entity(entity)
+                    .add_child(outline)
+                    .insert(Outlined(outline));
+            }
+            (false, Some(outline)) => {
+                commands.entity(outline).despawn_recursive();
+                commands.entity(entity).remove::<Outlined>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fade outlines along with the hidden blocks on [InputEvent::Peek], so they don't
+/// block the view inside
+fn peek(
+    mut input_events: EventReader<InputEvent>,
+    assets: Res<OutlineAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in input_events.read() {
+        if let InputEvent::Peek(peeking) = event {
+            set_peeking(*peeking, &assets, &mut materials);
+        }
+    }
+}
+
+fn stop_peeking(assets: Res<OutlineAssets>, mut materials: ResMut<Assets<StandardMaterial>>) {
+    set_peeking(false, &assets, &mut materials);
+}
+
+fn set_peeking(peeking: bool, assets: &OutlineAssets, materials: &mut Assets<StandardMaterial>) {
+    let Some(material) = materials.get_mut(&assets.material) else {
+        return;
+    };
+    if peeking {
+        material.base_color.set_a(PEEK_ALPHA);
+        material.alpha_mode = AlphaMode::Blend;
+    } else {
+        material.base_color.set_a(1.0);
+        material.alpha_mode = AlphaMode::Opaque;
+    }
+}
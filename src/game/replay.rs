@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::block::{handle_block_events, Block, BlockDisplay, BlockEvent, BlockVisuals};
+use super::board::CellView;
+use super::minefield::Contains;
+use super::{GameState, Paused};
+use crate::InputEvent;
+
+/// Seconds between steps while a replay plays back
+const REPLAY_STEP_TIME: f32 = 0.5;
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>()
+            .add_systems(OnEnter(GameState::GameStart), clear_replay)
+            .add_systems(
+                Update,
+                record
+                    .after(handle_block_events)
+                    .run_if(GameState::playable()),
+            )
+            .add_systems(
+                Update,
+                scrub
+                    .run_if(in_state(GameState::GameOver).and_then(resource_equals(Paused(false)))),
+            );
+    }
+}
+
+/// A change to a single block, as recorded by a [Replay]
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    /// The block was revealed, showing this
+    Reveal(Contains),
+    /// The block was marked, or unmarked if it already was
+    ToggleMark,
+}
+
+/// A recording of the current game, made of every change the player saw, grouped into
+/// steps by the frame they happened in (so a flood reveal is one step, like the click
+/// that caused it).
+///
+/// Once the game is over, it can be scrubbed through with [InputEvent::ReplayStep] and
+/// played back with [InputEvent::ReplayPlay]. The board is rebuilt from the recorded
+/// changes, so nothing needs to be generated again.
+#[derive(Debug, Default, Resource)]
+pub struct Replay {
+    steps: Vec<Vec<(Entity, Change)>>,
+    /// Number of steps currently shown, if scrubbing through the replay
+    position: Option<usize>,
+    /// Time until the next step, while playing back
+    playing: Option<Timer>,
+}
+impl Replay {
+    /// Number of recorded steps
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+    /// Whether nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+    /// Number of steps currently shown, if scrubbing through the replay
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+    /// Whether the replay is playing back
+    pub fn playing(&self) -> bool {
+        self.playing.is_some()
+    }
+    /// What each block that changed looks like after the first `steps` steps
+    fn views_at(&self, steps: usize) -> HashMap<Entity, CellView> {
+        let mut views = HashMap::new();
+        for &(block, change) in self.steps[..steps].iter().flatten() {
+            let view = views.entry(block).or_insert(CellView::Hidden);
+            *view = match (change, *view) {
+                (Change::Reveal(contains), _) => CellView::Revealed(contains),
+                (Change::ToggleMark, CellView::Hidden) => CellView::Flagged,
+                (Change::ToggleMark, CellView::Flagged) => CellView::Hidden,
+                (Change::ToggleMark, revealed) => revealed,
+            };
+        }
+        views
+    }
+}
+
+fn clear_replay(mut replay: ResMut<Replay>) {
+    *replay = Replay::default();
+}
+
+/// Add this frame's changes to the [Replay] as a single step
+fn record(mut replay: ResMut<Replay>, mut block_events: EventReader<BlockEvent>) {
+    let step: Vec<_> = block_events
+        .read()
+        .filter_map(|event| match event {
+            BlockEvent::Clear(block, contains) | BlockEvent::Update(block, contains) => {
+                Some((*block, Change::Reveal(*contains)))
+            }
+            BlockEvent::Mark(block) => Some((*block, Change::ToggleMark)),
+            BlockEvent::EndReveal(..) | BlockEvent::AnnotateMine(..) | BlockEvent::Preview(..) => {
+                None
+            }
+        })
+        .collect();
+    if !step.is_empty() {
+        replay.steps.push(step);
+    }
+}
+
+/// Move through the [Replay], and show the board as it was at the current step
+fn scrub(
+    mut replay: ResMut<Replay>,
+    mut input_events: EventReader<InputEvent>,
+    blocks: Query<Entity, With<Block>>,
+    visuals: BlockVisuals,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let len = replay.len();
+    let mut position = replay.position;
+    for event in input_events.read() {
+        match event {
+            InputEvent::ReplayStep { delta } => {
+                replay.playing = None;
+                let from = position.unwrap_or(len);
+                position = Some(from.saturating_add_signed(*delta).min(len));
+            }
+            InputEvent::ReplayPlay if replay.playing.is_some() => replay.playing = None,
+            InputEvent::ReplayPlay => {
+                // Start over if already at the end
+                if position.is_none_or(|position| position >= len) {
+                    position = Some(0);
+                }
+                replay.playing = Some(Timer::from_seconds(REPLAY_STEP_TIME, TimerMode::Repeating));
+            }
+            _ => {}
+        }
+    }
+    if let Some(timer) = &mut replay.playing {
+        if timer.tick(time.delta()).just_finished() {
+            position = position.map(|position| (position + 1).min(len));
+        }
+        if position.is_some_and(|position| position >= len) {
+            replay.playing = None;
+        }
+    }
+    if position == replay.position {
+        return;
+    }
+    replay.position = position;
+    let Some(position) = position else {
+        return;
+    };
+    let views = replay.views_at(position);
+    for block in &blocks {
+        let view = views.get(&block).copied().unwrap_or(CellView::Hidden);
+        BlockDisplay::show_view(view, &visuals, block, &mut commands);
+    }
+}
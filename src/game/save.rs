@@ -0,0 +1,153 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+use super::minefield::Minefield;
+use crate::{storage, FieldSettings, GameSettings, GameState};
+
+/// Autosaves alternate between these files, so a failed write never loses the previous save
+const AUTOSAVE_FILES: [&str; 2] = ["autosave-1.txt", "autosave-2.txt"];
+/// First line of every autosave, identifying the format
+const AUTOSAVE_HEADER: &str = "sweeper-3d autosave 1";
+
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Autosave>()
+            .add_systems(Startup, load_autosave)
+            .add_systems(
+                OnEnter(GameState::GameStart),
+                prepare_resume.before(super::limit_field_size),
+            )
+            .add_systems(Update, autosave.run_if(in_state(GameState::GamePlaying)))
+            .add_systems(OnEnter(GameState::GameOver), discard_autosave);
+    }
+}
+
+/// Periodic saves of the game in progress, see [GameSettings::autosave_interval].
+#[derive(Default, Resource)]
+pub struct Autosave {
+    /// Contents of the most recent autosave, if any
+    saved: Option<String>,
+    /// Whether the next game should resume from the autosave
+    resume: bool,
+    /// Field restored from the autosave, waiting to be spawned
+    pending: Option<Minefield>,
+    /// Seconds since the game was last saved
+    since_save: f32,
+    /// Index into [AUTOSAVE_FILES] of the file to write next
+    next_slot: usize,
+}
+impl Autosave {
+    /// Whether there's a saved game that can be resumed
+    pub fn available(&self) -> bool {
+        self.saved.is_some()
+    }
+    /// Resume the saved game when [GameState::GameStart] is next entered
+    pub fn resume(&mut self) {
+        self.resume = self.available();
+    }
+    /// Take the field to resume, if one was requested
+    pub(super) fn take_resume(&mut self) -> Option<Minefield> {
+        self.pending.take()
+    }
+}
+
+fn load_autosave(mut autosave: ResMut<Autosave>) {
+    let saved_at = |contents: &str| -> Option<u64> {
+        let mut lines = contents.lines();
+        if lines.next()? != AUTOSAVE_HEADER {
+            return None;
+        }
+        lines.next()?.strip_prefix("saved_at ")?.parse().ok()
+    };
+    let latest = AUTOSAVE_FILES
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, file)| {
+            let contents = storage::read(file)?;
+            let time = saved_at(&contents).or_else(|| {
+                error!("Ignoring invalid autosave {file}");
+                None
+            })?;
+            Some((time, slot, contents))
+        })
+        .max_by_key(|(time, _, _)| *time);
+    if let Some((_, slot, contents)) = latest {
+        info!("Found autosave {}", AUTOSAVE_FILES[slot]);
+        autosave.saved = Some(contents);
+        autosave.next_slot = (slot + 1) % AUTOSAVE_FILES.len();
+    }
+}
+
+/// Restore the saved field before anything else is spawned, if resuming
+fn prepare_resume(
+    mut autosave: ResMut<Autosave>,
+    game_settings: Res<GameSettings>,
+    mut field_settings: ResMut<FieldSettings>,
+) {
+    autosave.since_save = 0.0;
+    if !std::mem::take(&mut autosave.resume) {
+        return;
+    }
+    let Some(saved) = &autosave.saved else {
+        return;
+    };
+    match Minefield::from_save(saved, game_settings.safety) {
+        Ok(field) => {
+            *field_settings = field.field_settings();
+            autosave.pending = Some(field);
+        }
+        Err(err) => error!("Unable to resume autosave: {err}"),
+    }
+}
+
+fn autosave(
+    mut autosave: ResMut<Autosave>,
+    game_settings: Res<GameSettings>,
+    field: Query<&Minefield>,
+    time: Res<Time>,
+) {
+    let interval = game_settings.autosave_interval;
+    if interval <= 0.0 {
+        return;
+    }
+    autosave.since_save += time.delta_seconds();
+    if autosave.since_save < interval {
+        return;
+    }
+    autosave.since_save = 0.0;
+    let Ok(field) = field.get_single() else {
+        return;
+    };
+    if !field.in_progress() {
+        return;
+    }
+    let saved_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default();
+    let contents = format!(
+        "{AUTOSAVE_HEADER}\nsaved_at {saved_at}\n{}",
+        field.to_save()
+    );
+    let file = AUTOSAVE_FILES[autosave.next_slot];
+    match storage::write(file, &contents) {
+        Ok(()) => {
+            debug!("Autosaved to {file}");
+            autosave.saved = Some(contents);
+            autosave.next_slot = (autosave.next_slot + 1) % AUTOSAVE_FILES.len();
+        }
+        Err(err) => error!("Unable to autosave: {err}"),
+    }
+}
+
+/// A finished game can't be resumed
+fn discard_autosave(mut autosave: ResMut<Autosave>) {
+    autosave.saved = None;
+    for file in AUTOSAVE_FILES {
+        if let Err(err) = storage::remove(file) {
+            error!("Unable to remove {file}: {err}");
+        }
+    }
+}
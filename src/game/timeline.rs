@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use super::block::Block;
+use super::{GameState, Replay};
+use crate::InputEvent;
+
+/// Number of distinct shades between the first and last block revealed
+const TIMELINE_SHADES: usize = 11;
+/// Size of the cube shown in each revealed block, relative to the block
+const TIMELINE_MARKER_SIZE: f32 = 0.5;
+
+pub struct TimelinePlugin;
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RevealTimeline>()
+            .add_systems(Startup, create_timeline_assets)
+            .add_systems(OnEnter(GameState::GameStart), hide_timeline)
+            .add_systems(
+                Update,
+                (toggle_timeline, hide_on_replay, paint_timeline)
+                    .chain()
+                    .run_if(in_state(GameState::GameOver)),
+            );
+    }
+}
+
+/// Overlay shown after a game, putting a cube in each block the player revealed,
+/// shaded from blue for the first revealed to yellow for the last, by
+/// [Block::revealed_at].
+#[derive(Debug, Default, Resource)]
+pub struct RevealTimeline {
+    /// Whether the overlay is shown
+    pub visible: bool,
+}
+
+/// Cube shown in a revealed block by the [RevealTimeline]
+#[derive(Component)]
+struct TimelineMarker;
+
+/// Mesh and materials for each shade of the timeline, from first to last revealed
+#[derive(Resource)]
+struct TimelineAssets {
+    mesh: Handle<Mesh>,
+    shades: [Handle<StandardMaterial>; TIMELINE_SHADES],
+}
+
+fn create_timeline_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let shades = std::array::from_fn(|shade| {
+        let t = shade as f32 / (TIMELINE_SHADES - 1) as f32;
+        materials.add(StandardMaterial {
+            base_color: Color::rgb(t, 0.3 + 0.6 * t, 1.0 - t),
+            unlit: true,
+            ..default()
+        })
+    });
+    commands.insert_resource(TimelineAssets {
+        mesh: meshes.add(Cuboid::from_size(Vec3::splat(TIMELINE_MARKER_SIZE))),
+        shades,
+    });
+}
+
+fn hide_timeline(mut timeline: ResMut<RevealTimeline>) {
+    timeline.visible = false;
+}
+
+fn toggle_timeline(
+    mut input_events: EventReader<InputEvent>,
+    mut timeline: ResMut<RevealTimeline>,
+) {
+    for event in input_events.read() {
+        if matches!(event, InputEvent::ToggleTimeline) {
+            timeline.visible = !timeline.visible;
+        }
+    }
+}
+
+/// Scrubbing through the [Replay] rebuilds how blocks look, so hide the timeline rather
+/// than leave it half drawn
+fn hide_on_replay(
+    mut timeline: ResMut<RevealTimeline>,
+    replay: Res<Replay>,
+    mut last_position: Local<Option<usize>>,
+) {
+    let scrubbed = replay.position() != *last_position;
+    *last_position = replay.position();
+    if scrubbed && timeline.visible {
+        timeline.visible = false;
+    }
+}
+
+/// Show or remove the markers whenever the overlay is toggled
+fn paint_timeline(
+    timeline: Res<RevealTimeline>,
+    blocks: Query<(Entity, &Block)>,
+    markers: Query<Entity, With<TimelineMarker>>,
+    assets: Res<TimelineAssets>,
+    mut commands: Commands,
+) {
+    if !timeline.is_changed() {
+        return;
+    }
+    for marker in &markers {
+        commands.entity(marker).despawn_recursive();
+    }
+    if !timeline.visible {
+        return;
+    }
+    let times: Vec<_> = blocks
+        .iter()
+        .filter_map(|(entity, block)| Some((entity, block.revealed_at()?)))
+        .collect();
+    let first = times.iter().map(|(_, t)| *t).fold(f32::INFINITY, f32::min);
+    let last = times
+        .iter()
+        .map(|(_, t)| *t)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let span = (last - first).max(f32::EPSILON);
+    for (entity, time) in times {
+        let shade = ((time - first) / span * (TIMELINE_SHADES - 1) as f32).round() as usize;
+        let marker = commands
+            .spawn((
+                PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: assets.shades[shade.min(TIMELINE_SHADES - 1)].clone(),
+                    ..default()
+                },
+                TimelineMarker,
+            ))
+            .id();
+        commands.entity(entity).add_child(marker);
+    }
+}
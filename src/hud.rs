@@ -0,0 +1,566 @@
+use bevy::diagnostic::{
+    DiagnosticPath, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use bevy::window::{PrimaryWindow, WindowResized};
+use bevy_egui::EguiSettings;
+
+use crate::{
+    input::{reticle_position, ui_position, BoxSelection},
+    BoardSeed, Contains, FlagLimitReached, GameSettings, GameState, GameStats, GameTimer,
+    GenerationReport, InputEvent, Inspecting, KeyBindings, LastReveal, Replay, Screenshot,
+    SurrenderPrompt, WinCondition,
+};
+
+/// Seconds between refreshes of the stats overlay text
+const STATS_REFRESH_INTERVAL: f32 = 0.25;
+/// Length of each arm of the reticle crosshair, in logical pixels
+const RETICLE_SIZE: f32 = 8.0;
+/// Window size at which the UI is drawn at [GameSettings::ui_scale], in logical pixels
+const REFERENCE_WINDOW_SIZE: Vec2 = Vec2::new(1024.0, 768.0);
+/// Least and most the UI is scaled to fit the window, with [GameSettings::scale_ui_with_window]
+const WINDOW_UI_SCALE_RANGE: (f32, f32) = (0.75, 1.5);
+/// Width of the UI below which the corner panels move down, out of the mine counter's way
+const NARROW_UI_WIDTH: f32 = 900.0;
+/// Length of each arrow of the axis indicator, in points
+const AXES_SIZE: f32 = 32.0;
+/// Gap between the HUD and the edges of the window
+const HUD_MARGIN: f32 = 8.0;
+/// Space left above the corner panels in a narrow window, for the mine counter
+const NARROW_TOP_MARGIN: f32 = 56.0;
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+            app.add_plugins(EntityCountDiagnosticsPlugin);
+        }
+        app.init_resource::<StatsOverlay>()
+            .init_resource::<HudLayout>()
+            .add_systems(Update, fit_ui_to_window)
+            .init_resource::<LayerSummary>()
+            .init_resource::<LastRevealPanel>()
+            .add_systems(
+                Update,
+                (
+                    toggle_overlays,
+                    copy_seed.run_if(GameState::in_game()),
+                    (
+                        (update_stats, display_stats)
+                            .chain()
+                            .after(toggle_overlays)
+                            .run_if(|overlay: Res<StatsOverlay>| overlay.visible),
+                        display_layers.after(toggle_overlays).run_if(
+                            GameState::in_game()
+                                .and_then(|summary: Res<LayerSummary>| summary.visible),
+                        ),
+                        display_mine_counter.run_if(GameState::in_game()),
+                        display_box_selection.run_if(GameState::playable()),
+                        display_generating.run_if(in_state(GameState::GameGenerating)),
+                        display_replay.run_if(in_state(GameState::GameOver)),
+                        display_inspecting.run_if(
+                            GameState::playable().and_then(resource_equals(Inspecting(true))),
+                        ),
+                        display_surrender_prompt.run_if(
+                            in_state(GameState::GamePlaying)
+                                .and_then(|prompt: Res<SurrenderPrompt>| prompt.0.is_some()),
+                        ),
+                        display_axes.run_if(
+                            GameState::in_game().and_then(|game_settings: Res<GameSettings>| {
+                                game_settings.show_axes
+                            }),
+                        ),
+                        display_reticle.run_if(GameState::playable().and_then(
+                            |game_settings: Res<GameSettings>| game_settings.reticle_aim,
+                        )),
+                        display_last_reveal.after(toggle_overlays).run_if(
+                            GameState::in_game()
+                                .and_then(|panel: Res<LastRevealPanel>| panel.visible),
+                        ),
+                    )
+                        .run_if(
+                            |screenshot: Res<Screenshot>, game_settings: Res<GameSettings>| {
+                                !screenshot.hides_hud(&game_settings)
+                            },
+                        ),
+                ),
+            );
+    }
+}
+
+/// Placement of the HUD panels, following the size of the window
+#[derive(Debug, Resource)]
+struct HudLayout {
+    /// Distance from the top of the window to the panels in its top corners
+    corner_top: f32,
+}
+impl Default for HudLayout {
+    fn default() -> Self {
+        Self {
+            corner_top: HUD_MARGIN,
+        }
+    }
+}
+
+/// Scale the UI to the window and [GameSettings::ui_scale], and move the HUD panels
+/// so they don't overlap when the window is narrow
+fn fit_ui_to_window(
+    mut resized_events: EventReader<WindowResized>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    game_settings: Res<GameSettings>,
+    mut egui_settings: ResMut<EguiSettings>,
+    mut layout: ResMut<HudLayout>,
+) {
+    let resized = resized_events.read().last().is_some();
+    if !resized && !game_settings.is_changed() {
+        return;
+    }
+    let Ok(window) = primary_window.get_single() else {
+        return;
+    };
+    let size = Vec2::new(window.width(), window.height());
+    let fit = if game_settings.scale_ui_with_window {
+        let (min, max) = WINDOW_UI_SCALE_RANGE;
+        (size / REFERENCE_WINDOW_SIZE).min_element().clamp(min, max)
+    } else {
+        1.0
+    };
+    let scale = fit * game_settings.ui_scale;
+    if egui_settings.scale_factor != scale {
+        egui_settings.scale_factor = scale;
+    }
+    let narrow = size.x / scale < NARROW_UI_WIDTH;
+    layout.corner_top = if narrow {
+        NARROW_TOP_MARGIN
+    } else {
+        HUD_MARGIN
+    };
+}
+
+/// Overlay showing performance diagnostics and [GameStats].
+#[derive(Resource)]
+pub struct StatsOverlay {
+    /// Whether the overlay is shown
+    pub visible: bool,
+    /// Throttles how often the text is refreshed
+    refresh: Timer,
+    /// Lines of text to display
+    text: Vec<String>,
+}
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            refresh: Timer::from_seconds(STATS_REFRESH_INTERVAL, TimerMode::Repeating),
+            text: Vec::new(),
+        }
+    }
+}
+
+/// Per-layer counts of revealed, marked, and remaining blocks.
+#[derive(Debug, Default, Resource)]
+pub struct LayerSummary {
+    /// Whether the summary is shown
+    pub visible: bool,
+}
+
+/// Details of the most recently cleared block, from [LastReveal].
+#[derive(Debug, Default, Resource)]
+pub struct LastRevealPanel {
+    /// Whether the panel is shown
+    pub visible: bool,
+}
+
+fn toggle_overlays(
+    mut input_events: EventReader<InputEvent>,
+    mut overlay: ResMut<StatsOverlay>,
+    mut layers: ResMut<LayerSummary>,
+    mut last_reveal: ResMut<LastRevealPanel>,
+) {
+    for event in input_events.read() {
+        match event {
+            InputEvent::ToggleStats => {
+                overlay.visible = !overlay.visible;
+                // Refresh immediately when shown
+                let duration = overlay.refresh.duration();
+                overlay.refresh.set_elapsed(duration);
+            }
+            InputEvent::ToggleLayers => layers.visible = !layers.visible,
+            InputEvent::ToggleLastReveal => last_reveal.visible = !last_reveal.visible,
+            _ => {}
+        }
+    }
+}
+
+/// Put the seed on the clipboard on [InputEvent::CopySeed].
+/// If the clipboard can't be reached, bevy_egui logs why and the seed is left uncopied.
+fn copy_seed(
+    mut contexts: EguiContexts,
+    mut input_events: EventReader<InputEvent>,
+    board_seed: Res<BoardSeed>,
+) {
+    for event in input_events.read() {
+        if !matches!(event, InputEvent::CopySeed) {
+            continue;
+        }
+        let Some(seed) = board_seed.current else {
+            warn!("This game's seed isn't known, so it can't be copied");
+            continue;
+        };
+        info!("Copying seed {seed}");
+        contexts
+            .ctx_mut()
+            .output_mut(|output| output.copied_text = seed.to_string());
+    }
+}
+
+fn update_stats(
+    mut overlay: ResMut<StatsOverlay>,
+    diagnostics: Res<DiagnosticsStore>,
+    stats: Res<GameStats>,
+    generation_report: Res<GenerationReport>,
+    board_seed: Res<BoardSeed>,
+    time: Res<Time>,
+) {
+    overlay.refresh.tick(time.delta());
+    if !overlay.refresh.finished() && !overlay.text.is_empty() {
+        return;
+    }
+    let smoothed = |path: DiagnosticPath| {
+        diagnostics
+            .get(&path)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or_default()
+    };
+    let fps = smoothed(FrameTimeDiagnosticsPlugin::FPS);
+    let frame_time = smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME);
+    let entities = smoothed(EntityCountDiagnosticsPlugin::ENTITY_COUNT);
+    overlay.text = vec![
+        format!("FPS: {fps:.0} ({frame_time:.1} ms)"),
+        format!("Entities: {entities:.0}"),
+        format!("Blocks: {}", stats.blocks),
+        format!("Revealed: {}", stats.revealed),
+        format!("Marked: {}", stats.marked),
+        format!("Mines: {}", stats.mines),
+        format!("3BV: {}", stats.three_bv),
+        format!("Clicks: {}", stats.clicks),
+    ];
+    if let Some(efficiency) = stats.efficiency() {
+        overlay.text.push(format!("Efficiency: {efficiency:.0}%"));
+    }
+    if let Some(seed) = board_seed.current {
+        overlay.text.push(format!("Seed: {seed}"));
+    }
+    if generation_report.attempts > 0 {
+        let passes = generation_report
+            .deduction_passes
+            .map_or("unsolvable".to_string(), |passes| {
+                format!("{passes} passes")
+            });
+        overlay.text.push(format!(
+            "No-guess: {} attempts, {passes}",
+            generation_report.attempts
+        ));
+    }
+}
+
+fn display_stats(mut contexts: EguiContexts, overlay: Res<StatsOverlay>, layout: Res<HudLayout>) {
+    egui::Area::new("stats_overlay")
+        .anchor(egui::Align2::LEFT_TOP, [HUD_MARGIN, layout.corner_top])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for line in &overlay.text {
+                ui.label(
+                    egui::RichText::new(line)
+                        .monospace()
+                        .color(egui::Color32::WHITE),
+                );
+            }
+        });
+}
+
+fn display_layers(mut contexts: EguiContexts, stats: Res<GameStats>, layout: Res<HudLayout>) {
+    egui::Area::new("layer_summary")
+        .anchor(egui::Align2::RIGHT_TOP, [-HUD_MARGIN, layout.corner_top])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("layer_summary_grid").show(ui, |ui| {
+                for heading in ["Layer", "Revealed", "Flagged", "Remaining"] {
+                    ui.label(egui::RichText::new(heading).small().strong());
+                }
+                ui.end_row();
+                for (layer, counts) in stats.layers.iter().enumerate() {
+                    ui.label(egui::RichText::new(layer.to_string()).small());
+                    ui.label(egui::RichText::new(counts.revealed.to_string()).small());
+                    ui.label(egui::RichText::new(counts.marked.to_string()).small());
+                    ui.label(egui::RichText::new(counts.remaining().to_string()).small());
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+fn display_last_reveal(mut contexts: EguiContexts, last_reveal: Res<LastReveal>) {
+    egui::Area::new("last_reveal")
+        .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let Some(info) = &last_reveal.0 else {
+                ui.label(egui::RichText::new("No block cleared yet").small());
+                return;
+            };
+            let contents = match info.contains {
+                Contains::Mine => "Mine".to_string(),
+                Contains::Empty { adjacent_mines } => format!("{adjacent_mines} adjacent mines"),
+            };
+            let [i, j, k] = info.index;
+            for line in [
+                format!("Last cleared: ({i}, {j}, {k})"),
+                format!("Contains: {contents}"),
+                format!("Flagged neighbors: {}", info.flagged_neighbors),
+            ] {
+                ui.label(egui::RichText::new(line).small());
+            }
+        });
+}
+
+/// Show how many mines are left to mark, in red if too many blocks are marked,
+/// and the time played (or left, with a time limit).
+/// With [WinCondition::MarkAll], also show how many marks are correct.
+fn display_mine_counter(
+    mut contexts: EguiContexts,
+    stats: Res<GameStats>,
+    timer: Res<GameTimer>,
+    game_settings: Res<GameSettings>,
+    mut flag_limit: ResMut<FlagLimitReached>,
+    time: Res<Time>,
+) {
+    if let Some(remaining) = &mut flag_limit.0 {
+        *remaining -= time.delta_seconds();
+        if *remaining <= 0.0 {
+            flag_limit.0 = None;
+        }
+    }
+    egui::Area::new("mine_counter")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 8.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let clock = |seconds: f32| {
+                let seconds = seconds as u32;
+                format!("{}:{:02}", seconds / 60, seconds % 60)
+            };
+            match timer.remaining(&game_settings) {
+                Some(remaining) => {
+                    let text =
+                        egui::RichText::new(format!("Time Left: {}", clock(remaining.ceil())));
+                    // Warn in the last ten seconds
+                    if remaining <= 10.0 {
+                        ui.label(text.color(egui::Color32::LIGHT_RED));
+                    } else {
+                        ui.label(text);
+                    }
+                }
+                None => {
+                    ui.label(format!("Time: {}", clock(timer.elapsed)));
+                }
+            }
+            let text = egui::RichText::new(format!("Mines: {}", stats.mines_remaining())).strong();
+            if stats.over_marked() {
+                ui.label(text.color(egui::Color32::LIGHT_RED));
+            } else {
+                ui.label(text);
+            }
+            if game_settings.win_condition == WinCondition::MarkAll {
+                ui.label(format!(
+                    "Correct: {} / {}",
+                    stats.correct_flags, stats.mines
+                ));
+            }
+            if stats.free_moves > 0 {
+                ui.label(egui::RichText::new(format!("Free moves: {}", stats.free_moves)).small());
+            }
+            if flag_limit.0.is_some() {
+                ui.label(
+                    egui::RichText::new("No flags left")
+                        .small()
+                        .color(egui::Color32::LIGHT_RED),
+                );
+            }
+            if !stats.over_marked() {
+                return;
+            }
+            let warning = match game_settings.win_condition {
+                WinCondition::MarkAll => {
+                    "More blocks are marked than there are mines. \
+                    Unmark the wrong ones to win."
+                }
+                WinCondition::RevealAll => "More blocks are marked than there are mines.",
+            };
+            ui.label(
+                egui::RichText::new(warning)
+                    .small()
+                    .color(egui::Color32::LIGHT_RED),
+            );
+        });
+}
+
+fn display_box_selection(
+    mut contexts: EguiContexts,
+    box_selection: Res<BoxSelection>,
+    egui_settings: Res<EguiSettings>,
+) {
+    let Some(rect) = box_selection.rect() else {
+        return;
+    };
+    let rect = egui::Rect::from_min_max(
+        ui_position(rect.min, &egui_settings),
+        ui_position(rect.max, &egui_settings),
+    );
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+    painter.rect(
+        rect,
+        0.0,
+        egui::Color32::from_rgba_unmultiplied(255, 80, 80, 24),
+        egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 80, 80)),
+    );
+}
+
+/// Show which step of the [Replay] is shown, or how to start scrubbing through it
+fn display_replay(mut contexts: EguiContexts, replay: Res<Replay>, key_bindings: Res<KeyBindings>) {
+    if replay.is_empty() {
+        return;
+    }
+    let text = match replay.position() {
+        Some(position) => format!(
+            "Replay: step {position}/{}{}",
+            replay.len(),
+            if replay.playing() { "" } else { " (paused)" }
+        ),
+        None => format!(
+            "Press {:?} or {:?} to step through a replay, or {:?} to play it",
+            key_bindings.replay_back, key_bindings.replay_forward, key_bindings.replay_play
+        ),
+    };
+    egui::Area::new("replay")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 64.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(egui::RichText::new(text).small());
+        });
+}
+
+fn display_inspecting(mut contexts: EguiContexts, key_bindings: Res<KeyBindings>) {
+    egui::Area::new("inspecting")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 64.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new("INSPECT")
+                    .strong()
+                    .color(egui::Color32::LIGHT_BLUE),
+            )
+            .on_hover_text(format!(
+                "Clearing is disabled. Press {:?} to stop inspecting.",
+                key_bindings.toggle_inspect
+            ));
+        });
+}
+
+fn display_generating(mut contexts: EguiContexts) {
+    egui::Area::new("generating")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(egui::RichText::new("Generating...").strong());
+            });
+        });
+}
+
+fn display_surrender_prompt(mut contexts: EguiContexts, key_bindings: Res<KeyBindings>) {
+    egui::Area::new("surrender_prompt")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new(format!(
+                    "Press {:?} again to surrender and reveal the solution",
+                    key_bindings.surrender
+                ))
+                .strong()
+                .color(egui::Color32::LIGHT_RED),
+            );
+        });
+}
+
+/// Draw the field's axes as seen from the camera in the bottom right corner,
+/// in red, green and blue for the first, second and third index of a block.
+fn display_axes(
+    mut contexts: EguiContexts,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    game_settings: Res<GameSettings>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let screen = ctx.screen_rect();
+    let margin = HUD_MARGIN + AXES_SIZE;
+    let origin = egui::pos2(screen.max.x - margin, screen.max.y - margin);
+    let to_camera = camera.compute_transform().rotation.inverse();
+    let mut axes: Vec<_> = [
+        ("X", Vec3::X, egui::Color32::from_rgb(230, 60, 60)),
+        ("Y", Vec3::Y, egui::Color32::from_rgb(60, 200, 60)),
+        ("Z", Vec3::Z, egui::Color32::from_rgb(70, 110, 240)),
+    ]
+    .into_iter()
+    .map(|(name, axis, color)| {
+        let direction = to_camera * game_settings.up_axis.to_world(axis);
+        (name, direction, color)
+    })
+    .collect();
+    // Draw the axes pointing away from the camera first, so nearer ones are on top
+    axes.sort_by(|(_, a, _), (_, b, _)| a.z.total_cmp(&b.z));
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    for (name, direction, color) in axes {
+        let tip = origin + egui::vec2(direction.x, -direction.y) * AXES_SIZE;
+        painter.line_segment([origin, tip], egui::Stroke::new(2.0, color));
+        painter.text(
+            tip,
+            egui::Align2::CENTER_CENTER,
+            name,
+            egui::FontId::monospace(12.0),
+            color,
+        );
+    }
+}
+
+fn display_reticle(
+    mut contexts: EguiContexts,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    egui_settings: Res<EguiSettings>,
+) {
+    let center = ui_position(reticle_position(primary_window.single()), &egui_settings);
+    let stroke = egui::Stroke::new(2.0, egui::Color32::from_white_alpha(200));
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+    painter.hline(
+        (center.x - RETICLE_SIZE)..=(center.x + RETICLE_SIZE),
+        center.y,
+        stroke,
+    );
+    painter.vline(
+        center.x,
+        (center.y - RETICLE_SIZE)..=(center.y + RETICLE_SIZE),
+        stroke,
+    );
+}
@@ -1,18 +1,144 @@
 use bevy::input::{
     keyboard::KeyboardInput,
     mouse::{MouseButtonInput, MouseMotion, MouseWheel},
+    InputSystem,
 };
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+
+use crate::GameSettings;
 
 pub struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<InputEvent>()
-            .add_systems(PreUpdate, (mouse_input, keyboard_input));
+            .init_resource::<KeyBindings>()
+            .init_resource::<BoxSelection>()
+            .add_systems(PreUpdate, (mouse_input, keyboard_input.after(InputSystem)));
+    }
+}
+
+/// Keys bound to each keyboard action.
+#[derive(Debug, Resource)]
+pub struct KeyBindings {
+    /// Send [InputEvent::Pause]
+    pub pause: KeyCode,
+    /// Send [InputEvent::ToggleStats]
+    pub toggle_stats: KeyCode,
+    /// Send [InputEvent::ToggleLayers]
+    pub toggle_layers: KeyCode,
+    /// Send [InputEvent::ToggleLastReveal]
+    pub toggle_last_reveal: KeyCode,
+    /// Send [InputEvent::ToggleHeatmap]
+    pub toggle_heatmap: KeyCode,
+    /// Send [InputEvent::ToggleTimeline]
+    pub toggle_timeline: KeyCode,
+    /// Send [InputEvent::ToggleInspect]
+    pub toggle_inspect: KeyCode,
+    /// Send [InputEvent::Peek] while held
+    pub peek: KeyCode,
+    /// Hold while dragging with [GameSettings::flag_button] to send [InputEvent::BoxMark]
+    pub box_select: KeyCode,
+    /// Send [InputEvent::ZoomCamera] to zoom in by one step, like one line of scrolling
+    pub zoom_in: KeyCode,
+    /// Send [InputEvent::ZoomCamera] to zoom out by one step, like one line of scrolling
+    pub zoom_out: KeyCode,
+    /// Second key for [KeyBindings::zoom_in]
+    pub zoom_in_alt: KeyCode,
+    /// Second key for [KeyBindings::zoom_out]
+    pub zoom_out_alt: KeyCode,
+    /// Send [InputEvent::ClearBlock] at the reticle, if [GameSettings::reticle_aim] is enabled
+    pub reticle_clear: KeyCode,
+    /// Send [InputEvent::MarkBlock] at the reticle, if [GameSettings::reticle_aim] is enabled
+    pub reticle_mark: KeyCode,
+    /// Send [InputEvent::ResetCamera]
+    pub reset_camera: KeyCode,
+    /// Send [InputEvent::SolveObvious]
+    pub solve_obvious: KeyCode,
+    /// Send [InputEvent::Surrender]
+    pub surrender: KeyCode,
+    /// Send [InputEvent::Screenshot]
+    pub screenshot: KeyCode,
+    /// Send [InputEvent::TutorialNext]
+    pub tutorial_next: KeyCode,
+    /// Send [InputEvent::CopySeed]
+    pub copy_seed: KeyCode,
+    /// Send [InputEvent::FreeMove]
+    pub free_move: KeyCode,
+    /// Send [InputEvent::RetryBoard]
+    pub retry_board: KeyCode,
+    /// Send [InputEvent::NewBoard]
+    pub new_board: KeyCode,
+    /// Send [InputEvent::ReplayStep] one step back
+    pub replay_back: KeyCode,
+    /// Send [InputEvent::ReplayStep] one step forward
+    pub replay_forward: KeyCode,
+    /// Send [InputEvent::ReplayPlay]
+    pub replay_play: KeyCode,
+}
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            pause: KeyCode::Escape,
+            toggle_stats: KeyCode::F3,
+            toggle_layers: KeyCode::F4,
+            toggle_last_reveal: KeyCode::F5,
+            toggle_heatmap: KeyCode::F6,
+            toggle_timeline: KeyCode::F7,
+            toggle_inspect: KeyCode::KeyI,
+            peek: KeyCode::Tab,
+            box_select: KeyCode::ShiftLeft,
+            zoom_in: KeyCode::Equal,
+            zoom_out: KeyCode::Minus,
+            zoom_in_alt: KeyCode::NumpadAdd,
+            zoom_out_alt: KeyCode::NumpadSubtract,
+            reticle_clear: KeyCode::Space,
+            reticle_mark: KeyCode::KeyF,
+            reset_camera: KeyCode::Home,
+            solve_obvious: KeyCode::Enter,
+            surrender: KeyCode::F9,
+            screenshot: KeyCode::F12,
+            tutorial_next: KeyCode::KeyN,
+            copy_seed: KeyCode::KeyC,
+            free_move: KeyCode::KeyG,
+            retry_board: KeyCode::KeyR,
+            new_board: KeyCode::KeyB,
+            replay_back: KeyCode::BracketLeft,
+            replay_forward: KeyCode::BracketRight,
+            replay_play: KeyCode::Backslash,
+        }
     }
 }
 
+/// Selection rectangle being dragged out for [InputEvent::BoxMark].
+#[derive(Debug, Default, Resource)]
+pub struct BoxSelection {
+    /// Corner where the drag started, if a selection is in progress
+    pub start: Option<Vec2>,
+    /// Current cursor position, the opposite corner
+    pub end: Vec2,
+}
+impl BoxSelection {
+    /// The selected rectangle, if a selection is in progress
+    pub fn rect(&self) -> Option<Rect> {
+        self.start.map(|start| Rect::from_corners(start, self.end))
+    }
+}
+
+/// Position of the reticle used to pick blocks if [GameSettings::reticle_aim] is enabled.
+/// Always the center of the window.
+pub fn reticle_position(window: &Window) -> Vec2 {
+    Vec2::new(window.width(), window.height()) / 2.0
+}
+
+/// Convert a position in logical pixels of the window to egui points, which differ
+/// when the UI is scaled.
+pub fn ui_position(position: Vec2, egui_settings: &EguiSettings) -> egui::Pos2 {
+    let position = position / egui_settings.scale_factor;
+    egui::pos2(position.x, position.y)
+}
+
 /// Relative screen position, normalized at (0.0, 0.0) in the top-left,
 /// with the each unit corresponding to a logical pixel.
 #[derive(Debug, Deref, Clone, Copy)]
@@ -32,22 +158,74 @@ impl From<Vec2> for ScreenPosition {
 
 #[derive(Debug, Event)]
 pub enum InputEvent {
-    /// Clear a block at a screen position (default: left click).
+    /// Clear a block at a screen position (default: left click, see [GameSettings::reveal_button]).
     ClearBlock(ScreenPosition),
-    /// Mark a block at a screen location (default: Right click).
+    /// Mark a block at a screen location (default: Right click, see [GameSettings::flag_button]).
     MarkBlock(ScreenPosition),
+    /// Clear a block the cursor passed over while dragging with [GameSettings::reveal_button] held.
+    /// Only sent if [GameSettings::sweep_reveal] is enabled.
+    SweepBlock(ScreenPosition),
+    /// The cursor moved to a position over the field, or away from it (`None`).
+    HoverBlock(Option<ScreenPosition>),
     /// Select a button or object at a position on the screen (default: left click).
     Select(ScreenPosition),
     /// Rotate the camera. `delta.x` is horizontal rotation, and `delta.y` is vertical
-    /// (default: Middle mouse button and movement).
+    /// (default: Middle mouse button and movement, see [GameSettings::rotate_button]).
     /// Relative to window size.
     RotateCamera { delta: Vec2 },
-    /// Zoom the camera (default mouse wheel up/down).
+    /// Zoom the camera (default mouse wheel up/down, or the +/- keys).
     /// `delta` indicates zoom direction and magnitude: positive zooms in, and negative zooms out.
-    ZoomCamera { delta: f32 },
+    /// With [GameSettings::zoom_to_cursor], zooming in heads towards what's under `cursor`,
+    /// if it's set.
+    ZoomCamera {
+        delta: f32,
+        cursor: Option<ScreenPosition>,
+    },
+    /// Move the camera back to where it started the game (default Home).
+    ResetCamera,
+    /// Make every move the numbers on the board decide, if [GameSettings::solve_obvious]
+    /// is enabled (default Enter).
+    SolveObvious,
+    /// Give up and reveal the solution. Has to be sent twice in quick succession,
+    /// so a single stray press doesn't end the game (default F9).
+    Surrender,
+    /// Save the next frame to a file, see [GameSettings::screenshot_dir] (default F12).
+    Screenshot,
+    /// Show the next panel of the tutorial, if it's being shown (default N).
+    TutorialNext,
+    /// Copy the current game's [BoardSeed](crate::BoardSeed) to the clipboard (default C).
+    CopySeed,
+    /// Use up one of [GameSettings::free_safe_moves] to clear a safe block (default G).
+    FreeMove,
+    /// Once a game is over, play again on the same board (default R).
+    RetryBoard,
+    /// Once a game is over, play again on a fresh board (default B).
+    NewBoard,
+    /// Move through the replay of a finished game by `delta` steps
+    /// (default `[` and `]` for one step back and forward).
+    ReplayStep { delta: isize },
+    /// Start or stop playing back the replay of a finished game (default `\`).
+    ReplayPlay,
     /// Pause the game is a specific key is pressed (default ESC) or if the window
     /// (or app) loses focus.
     Pause,
+    /// Show or hide the performance and statistics overlay (default F3).
+    ToggleStats,
+    /// Show or hide the per-layer progress summary (default F4).
+    ToggleLayers,
+    /// Show or hide details of the most recently cleared block (default F5).
+    ToggleLastReveal,
+    /// Show or hide the estimated chance of each hidden block containing a mine (default F6).
+    ToggleHeatmap,
+    /// Show or hide the order blocks were revealed in, once the game is over (default F7).
+    ToggleTimeline,
+    /// Start or stop inspect mode, where clicks can't clear blocks (default I).
+    ToggleInspect,
+    /// Start (`true`) or stop (`false`) seeing through hidden blocks (default: hold Tab).
+    Peek(bool),
+    /// Mark every hidden block whose center is inside a rectangle on the screen
+    /// (default: drag with Left Shift and [GameSettings::flag_button] held).
+    BoxMark(Rect),
 }
 
 /// Conversion factor between scroll by pixels and scroll by lines, for consistent
@@ -56,18 +234,25 @@ const SCROLL_PIXELS_PER_LINE: f32 = 8.0;
 
 /// Handle mouse input. All available events are consumed and accumulated into possibly fewer
 /// InputEvents for efficiency.
+#[allow(clippy::too_many_arguments)]
 fn mouse_input(
     mut mouse_wheel_events: EventReader<MouseWheel>,
     mut mouse_button_events: EventReader<MouseButtonInput>,
     mut mouse_motion_events: EventReader<MouseMotion>,
     mouse_button: Res<ButtonInput<MouseButton>>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettings>,
     mut input_events: EventWriter<InputEvent>,
+    mut last_cursor_pos: Local<Option<Vec2>>,
+    mut box_selection: ResMut<BoxSelection>,
+    mut contexts: EguiContexts,
 ) {
     // Get the singular primary window. Multiple windows is not handled.
     let window = primary_window.single();
     // Handle mouse motion events only if the rotate button (default MMB) is pressed
-    if mouse_button.pressed(MouseButton::Middle) {
+    if mouse_button.pressed(game_settings.rotate_button) {
         // Collect all motion events into a single delta
         let mut delta = Vec2::ZERO;
         for motion_event in mouse_motion_events.read() {
@@ -101,48 +286,253 @@ fn mouse_input(
             }
         };
     }
+    // We don't care about mouse clicks if the mouse is not in the primary window,
+    // or if they're meant for a menu. When aiming with the reticle, clicks pick at
+    // the reticle instead of the cursor.
+    let cursor_pos = window
+        .cursor_position()
+        .filter(|_| !contexts.ctx_mut().is_pointer_over_area())
+        .map(|pos| {
+            if game_settings.reticle_aim {
+                reticle_position(window)
+            } else {
+                pos
+            }
+        });
     // Only send an event if the delta is nonzero
     if scroll_delta.abs() > 0.0 {
         debug!("Send InputEvent::ZoomCamera");
         input_events.send(InputEvent::ZoomCamera {
             delta: scroll_delta,
+            cursor: cursor_pos.map(ScreenPosition::from),
         });
     }
-    // We don't care about mouse clicks if the mouse is not in the primary window
-    let Some(cursor_pos) = window.cursor_position() else {
+    let cursor_moved = *last_cursor_pos != cursor_pos;
+    *last_cursor_pos = cursor_pos;
+    if cursor_moved {
+        input_events.send(InputEvent::HoverBlock(cursor_pos.map(ScreenPosition::from)));
+    }
+    let Some(cursor_pos) = cursor_pos else {
+        mouse_button_events.clear();
+        // A selection can't be finished outside the field, so drop it
+        if !mouse_button.pressed(game_settings.flag_button) {
+            box_selection.start = None;
+        }
         return;
     };
+    box_selection.end = cursor_pos;
+    // Handle dragging with the reveal button held
+    if game_settings.sweep_reveal
+        && cursor_moved
+        && mouse_button.pressed(game_settings.reveal_button)
+        && !mouse_button.just_pressed(game_settings.reveal_button)
+    {
+        debug!("Send InputEvent::SweepBlock");
+        input_events.send(InputEvent::SweepBlock(cursor_pos.into()));
+    }
     // Handle mouse click events (default LMB or RMB)
     for mouse_button_event in mouse_button_events.read() {
-        if mouse_button_event.state.is_pressed() {
-            debug!("Click at {cursor_pos:?}");
-            match mouse_button_event.button {
-                MouseButton::Left => {
-                    debug!("Send InputEvent::ClearBlock");
-                    input_events.send(InputEvent::ClearBlock(cursor_pos.into()));
-                }
-                MouseButton::Right => {
-                    debug!("Send InputEvent::MarkBlock");
-                    input_events.send(InputEvent::MarkBlock(cursor_pos.into()));
+        let button = mouse_button_event.button;
+        if !mouse_button_event.state.is_pressed() {
+            if button == game_settings.flag_button {
+                if let Some(rect) = box_selection.rect() {
+                    box_selection.start = None;
+                    debug!("Send InputEvent::BoxMark");
+                    input_events.send(InputEvent::BoxMark(rect));
                 }
-                _ => {}
-            };
+            }
+        } else {
+            debug!("Click at {cursor_pos:?}");
+            if button == game_settings.reveal_button {
+                debug!("Send InputEvent::ClearBlock");
+                input_events.send(InputEvent::ClearBlock(cursor_pos.into()));
+            } else if button == game_settings.flag_button && keys.pressed(key_bindings.box_select) {
+                debug!("Start box selection at {cursor_pos:?}");
+                box_selection.start = Some(cursor_pos);
+            } else if button == game_settings.flag_button {
+                debug!("Send InputEvent::MarkBlock");
+                input_events.send(InputEvent::MarkBlock(cursor_pos.into()));
+            }
         }
     }
 }
 
 fn keyboard_input(
     mut key_events: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettings>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
     mut input_events: EventWriter<InputEvent>,
 ) {
+    let reticle = ScreenPosition::from(reticle_position(primary_window.single()));
     for key_event in key_events.read() {
         match key_event {
             KeyboardInput {
                 key_code, state, ..
-            } if matches!(key_code, KeyCode::Escape) && state.is_pressed() => {
+            } if *key_code == key_bindings.pause && state.is_pressed() => {
                 debug!("Send InputEvent::Pause");
                 input_events.send(InputEvent::Pause);
             }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.toggle_stats && state.is_pressed() => {
+                debug!("Send InputEvent::ToggleStats");
+                input_events.send(InputEvent::ToggleStats);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.toggle_layers && state.is_pressed() => {
+                debug!("Send InputEvent::ToggleLayers");
+                input_events.send(InputEvent::ToggleLayers);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.toggle_last_reveal && state.is_pressed() => {
+                debug!("Send InputEvent::ToggleLastReveal");
+                input_events.send(InputEvent::ToggleLastReveal);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.toggle_heatmap && state.is_pressed() => {
+                debug!("Send InputEvent::ToggleHeatmap");
+                input_events.send(InputEvent::ToggleHeatmap);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.toggle_timeline && state.is_pressed() => {
+                debug!("Send InputEvent::ToggleTimeline");
+                input_events.send(InputEvent::ToggleTimeline);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.toggle_inspect && state.is_pressed() => {
+                debug!("Send InputEvent::ToggleInspect");
+                input_events.send(InputEvent::ToggleInspect);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.peek => {
+                debug!("Send InputEvent::Peek");
+                input_events.send(InputEvent::Peek(state.is_pressed()));
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.reset_camera && state.is_pressed() => {
+                debug!("Send InputEvent::ResetCamera");
+                input_events.send(InputEvent::ResetCamera);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.solve_obvious && state.is_pressed() => {
+                debug!("Send InputEvent::SolveObvious");
+                input_events.send(InputEvent::SolveObvious);
+            }
+            // Holding the key down repeats it, which mustn't count as confirming, so only a
+            // fresh press does
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.surrender
+                && state.is_pressed()
+                && keys.just_pressed(*key_code) =>
+            {
+                debug!("Send InputEvent::Surrender");
+                input_events.send(InputEvent::Surrender);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.screenshot && state.is_pressed() => {
+                debug!("Send InputEvent::Screenshot");
+                input_events.send(InputEvent::Screenshot);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.tutorial_next && state.is_pressed() => {
+                debug!("Send InputEvent::TutorialNext");
+                input_events.send(InputEvent::TutorialNext);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.copy_seed && state.is_pressed() => {
+                debug!("Send InputEvent::CopySeed");
+                input_events.send(InputEvent::CopySeed);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.free_move && state.is_pressed() => {
+                debug!("Send InputEvent::FreeMove");
+                input_events.send(InputEvent::FreeMove);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.retry_board && state.is_pressed() => {
+                debug!("Send InputEvent::RetryBoard");
+                input_events.send(InputEvent::RetryBoard);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.new_board && state.is_pressed() => {
+                debug!("Send InputEvent::NewBoard");
+                input_events.send(InputEvent::NewBoard);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.replay_back && state.is_pressed() => {
+                debug!("Send InputEvent::ReplayStep");
+                input_events.send(InputEvent::ReplayStep { delta: -1 });
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.replay_forward && state.is_pressed() => {
+                debug!("Send InputEvent::ReplayStep");
+                input_events.send(InputEvent::ReplayStep { delta: 1 });
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.replay_play && state.is_pressed() => {
+                debug!("Send InputEvent::ReplayPlay");
+                input_events.send(InputEvent::ReplayPlay);
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if (*key_code == key_bindings.zoom_in || *key_code == key_bindings.zoom_in_alt)
+                && state.is_pressed() =>
+            {
+                debug!("Send InputEvent::ZoomCamera");
+                input_events.send(InputEvent::ZoomCamera {
+                    delta: 1.0,
+                    cursor: None,
+                });
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if (*key_code == key_bindings.zoom_out || *key_code == key_bindings.zoom_out_alt)
+                && state.is_pressed() =>
+            {
+                debug!("Send InputEvent::ZoomCamera");
+                input_events.send(InputEvent::ZoomCamera {
+                    delta: -1.0,
+                    cursor: None,
+                });
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.reticle_clear
+                && state.is_pressed()
+                && game_settings.reticle_aim =>
+            {
+                debug!("Send InputEvent::ClearBlock");
+                input_events.send(InputEvent::ClearBlock(reticle));
+            }
+            KeyboardInput {
+                key_code, state, ..
+            } if *key_code == key_bindings.reticle_mark
+                && state.is_pressed()
+                && game_settings.reticle_aim =>
+            {
+                debug!("Send InputEvent::MarkBlock");
+                input_events.send(InputEvent::MarkBlock(reticle));
+            }
             _ => {}
         }
     }
@@ -1,27 +1,52 @@
 use bevy::prelude::*;
 
+mod analytics;
+mod campaign;
 mod game;
+mod hud;
 mod input;
 mod loader;
 mod menu;
+mod screenshot;
 mod settings;
+mod storage;
+mod tutorial;
 
-pub use input::InputEvent;
+pub use analytics::{Analytics, GameRecord, LossCause};
+pub use campaign::{Campaign, CAMPAIGN_LEVELS};
+pub use game::{
+    Autosave, Block, BlockEvent, BlockMeta, BoardSeed, BoardView, CameraTarget, CellView,
+    ClickMissedEvent, Contains, FieldEvent, FlagLimitReached, GameLostEvent, GameResult, GameStats,
+    GameTimer, GenerationReport, Heatmap, Inspecting, LastReveal, LayerStats, Minefield, Replay,
+    RevealInfo, RevealTimeline, SurrenderPrompt,
+};
+pub use input::{InputEvent, KeyBindings};
 pub use loader::GameAssets;
-pub use settings::{FieldSettings, GameSettings, Safety};
+pub use screenshot::Screenshot;
+pub use settings::{
+    BlockStyle, ColorScheme, Connectivity, EmptyMarker, ExplosionSound, FieldSettings, FieldShape,
+    GameSettings, InitialView, RevealAnim, RevealOnLoss, RevealStyle, Safety, SpawnOrder, UpAxis,
+    WinCondition, DEFAULT_MAX_CELLS,
+};
+pub use tutorial::Tutorial;
 
-pub use game::GamePlugin;
+pub use analytics::AnalyticsPlugin;
+pub use campaign::CampaignPlugin;
+pub use game::{GamePlugin, HeadlessPlugin};
+pub use hud::HudPlugin;
 pub use input::InputPlugin;
 pub use loader::LoaderPlugin;
 pub use menu::MenuPlugin;
+pub use screenshot::ScreenshotPlugin;
 pub use settings::SettingsPlugin;
+pub use tutorial::TutorialPlugin;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, States)]
 pub enum GameState {
     /// Loading
     #[default]
     Loading,
-    /// Main menu
+    /// Main menu, where difficulty is chosen. Nothing from a previous game remains spawned.
     MenuMain,
     /// Custom game menu
     MenuCustom,
@@ -29,6 +54,9 @@ pub enum GameState {
     MenuSettings,
     /// Game has started, but no cell has been clicked yet.
     GameStart,
+    /// The first cell has been clicked, and the field is being generated in the background.
+    /// Only entered for fields slow to generate: huge ones, or any with [`GameSettings::no_guess`].
+    GameGenerating,
     /// Game transitions to this state once the first cell is clicked.
     /// This is when the field actually initializes and determines mine placement.
     /// At this point the position of all mines is known.
@@ -37,9 +65,11 @@ pub enum GameState {
     GameOver,
 }
 impl GameState {
-    /// Any in-game state. [`GameState::GameStart`] || [`GameState::GamePlaying`] || [`GameState::GameOver`].
+    /// Any in-game state. [`GameState::GameStart`] || [`GameState::GameGenerating`] ||
+    /// [`GameState::GamePlaying`] || [`GameState::GameOver`].
     pub fn in_game() -> impl Condition<()> {
         in_state(Self::GameStart)
+            .or_else(in_state(Self::GameGenerating))
             .or_else(in_state(Self::GamePlaying))
             .or_else(in_state(Self::GameOver))
     }
@@ -1,10 +1,32 @@
 // Disable console window in Windows
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use bevy::{log::LogPlugin, prelude::*, window::WindowResolution};
-use sweeper_3d::{GamePlugin, GameState, InputPlugin, LoaderPlugin, MenuPlugin, SettingsPlugin};
+use bevy::{
+    log::LogPlugin,
+    prelude::*,
+    window::{WindowResizeConstraints, WindowResolution},
+};
+use sweeper_3d::{
+    CampaignPlugin, FieldSettings, GamePlugin, GameState, HeadlessPlugin, HudPlugin, InputPlugin,
+    LoaderPlugin, MenuPlugin, ScreenshotPlugin, SettingsPlugin, TutorialPlugin,
+};
+
+/// Smallest the window can be resized to, in logical pixels, so the HUD still fits
+const MIN_WINDOW_SIZE: Vec2 = Vec2::new(640.0, 480.0);
 
 fn main() {
+    // Benchmark the game logic without opening a window
+    if std::env::args().any(|arg| arg == "--headless") {
+        App::new()
+            .add_plugins((
+                MinimalPlugins,
+                HeadlessPlugin {
+                    field_settings: FieldSettings::large(),
+                },
+            ))
+            .run();
+        return;
+    }
     App::new()
         .init_state::<GameState>()
         .add_plugins(
@@ -15,6 +37,11 @@ fn main() {
                         // Show the window only once internal startup has finished and we're running systems
                         visible: false,
                         resolution: WindowResolution::new(1024.0, 768.0),
+                        resize_constraints: WindowResizeConstraints {
+                            min_width: MIN_WINDOW_SIZE.x,
+                            min_height: MIN_WINDOW_SIZE.y,
+                            ..default()
+                        },
                         title: "3D Sweeper".to_string(),
                         ..default()
                     }),
@@ -33,6 +60,10 @@ fn main() {
             GamePlugin,
             InputPlugin,
             LoaderPlugin,
+            CampaignPlugin,
+            HudPlugin,
+            ScreenshotPlugin,
+            TutorialPlugin,
         ))
         .run();
 }
@@ -1,7 +1,13 @@
 use bevy::{app::AppExit, prelude::*};
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 
-use crate::{game::GameResult, FieldSettings, GameSettings, GameState, Safety};
+use crate::{
+    campaign::CAMPAIGN_LEVELS,
+    game::{Autosave, BoardSeed, GameResult, GameStats, Paused},
+    Campaign, ColorScheme, Connectivity, ExplosionSound, FieldSettings, FieldShape, GameSettings,
+    GameState, InitialView, InputEvent, KeyBindings, RevealAnim, RevealOnLoss, RevealStyle, Safety,
+    SpawnOrder, Tutorial, UpAxis, WinCondition,
+};
 
 pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
@@ -13,6 +19,11 @@ impl Plugin for MenuPlugin {
                 display_custom_menu.run_if(in_state(GameState::MenuCustom)),
                 display_settings_menu.run_if(in_state(GameState::MenuSettings)),
                 display_game_over.run_if(in_state(GameState::GameOver)),
+                restart_keys.run_if(in_state(GameState::GameOver)),
+                toggle_pause.run_if(GameState::in_game()),
+                display_pause_menu
+                    .after(toggle_pause)
+                    .run_if(GameState::in_game().and_then(resource_equals(Paused(true)))),
             ),
         );
     }
@@ -34,8 +45,6 @@ fn global_settings(ctx: &mut egui::Context) {
         );
         style.spacing.item_spacing = egui::Vec2::new(5.0, 5.0);
     });
-    let mut visuals = egui::Visuals::dark();
-    visuals.window_shadow = egui::epaint::Shadow::NONE;
 }
 
 fn create_menu_window<'a>(title: impl Into<egui::WidgetText>) -> egui::Window<'a> {
@@ -46,35 +55,93 @@ fn create_menu_window<'a>(title: impl Into<egui::WidgetText>) -> egui::Window<'a
         .resizable(false)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn display_main_menu(
     mut contexts: EguiContexts,
     mut field_settings: ResMut<FieldSettings>,
+    mut campaign: ResMut<Campaign>,
+    mut autosave: ResMut<Autosave>,
+    mut tutorial: ResMut<Tutorial>,
+    mut board_seed: ResMut<BoardSeed>,
+    mut seed_text: Local<String>,
     mut next_state: ResMut<NextState<GameState>>,
     mut exit_events: EventWriter<AppExit>,
 ) {
+    let seed = seed_text.trim();
+    let parsed_seed = (!seed.is_empty()).then(|| seed.parse::<u64>());
+    board_seed.next = parsed_seed.clone().and_then(Result::ok);
     let ctx = contexts.ctx_mut();
     global_settings(ctx);
     create_menu_window("Sweeper 3D").show(ctx, |ui| {
         ui.allocate_ui(egui::Vec2::new(0.0, 0.0), |ui| {
             ui.vertical_centered(|ui| {
+                if autosave.available() && ui.add(egui::Button::new("Resume")).clicked() {
+                    autosave.resume();
+                    campaign.active = false;
+                    next_state.set(GameState::GameStart);
+                }
+                ui.horizontal_centered(|ui| {
+                    let level = if campaign.completed() {
+                        "Campaign (Complete)".to_string()
+                    } else {
+                        format!(
+                            "Campaign ({}/{})",
+                            campaign.level + 1,
+                            CAMPAIGN_LEVELS.len()
+                        )
+                    };
+                    if ui.add(egui::Button::new(level)).clicked() {
+                        if campaign.completed() {
+                            campaign.level = 0;
+                        }
+                        field_settings.set_if_neq(campaign.field_settings());
+                        campaign.active = true;
+                        next_state.set(GameState::GameStart);
+                    }
+                });
                 ui.horizontal_centered(|ui| {
                     if ui.add(egui::Button::new("Small")).clicked() {
                         field_settings.set_if_neq(FieldSettings::small());
+                        campaign.active = false;
                         next_state.set(GameState::GameStart);
                     }
                     if ui.add(egui::Button::new("Medium")).clicked() {
                         field_settings.set_if_neq(FieldSettings::medium());
+                        campaign.active = false;
                         next_state.set(GameState::GameStart);
                     }
                     if ui.add(egui::Button::new("Large")).clicked() {
                         field_settings.set_if_neq(FieldSettings::large());
+                        campaign.active = false;
                         next_state.set(GameState::GameStart);
                     }
                     if ui.add(egui::Button::new("Custom")).clicked() {
                         field_settings.set_if_neq(FieldSettings::default());
+                        campaign.active = false;
                         next_state.set(GameState::MenuCustom);
                     }
                 });
+                ui.horizontal_centered(|ui| {
+                    ui.add(egui::Label::new("Seed:"));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut *seed_text)
+                            .hint_text("Random")
+                            .desired_width(240.0),
+                    )
+                    .on_hover_text(concat!(
+                        "Paste a seed to play a shared board. It's only the same board ",
+                        "with the same field, settings, and first clear.",
+                    ));
+                });
+                if let Some(Err(_)) = parsed_seed {
+                    ui.colored_label(egui::Color32::LIGHT_RED, "Seeds are whole numbers");
+                }
+                if ui.add(egui::Button::new("Tutorial")).clicked() {
+                    field_settings.set_if_neq(FieldSettings::small());
+                    campaign.active = false;
+                    tutorial.start();
+                    next_state.set(GameState::GameStart);
+                }
                 if ui.add(egui::Button::new("Settings")).clicked() {
                     next_state.set(GameState::MenuSettings);
                 }
@@ -91,7 +158,8 @@ fn display_custom_menu(
     mut field_settings: ResMut<FieldSettings>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    let (field_size, mine_density) = field_settings.fields_mut();
+    let validation = field_settings.validate();
+    let (field_size, mine_density, shape) = field_settings.fields_mut();
     let ctx = contexts.ctx_mut();
     global_settings(ctx);
     create_menu_window("Custom Game").show(ctx, |ui| {
@@ -112,7 +180,18 @@ fn display_custom_menu(
                     );
                 });
                 ui.horizontal_centered(|ui| {
-                    if ui.add(egui::Button::new("Start")).clicked() {
+                    ui.add(egui::Label::new("Shape:"));
+                    ui.radio_value(shape, FieldShape::Box, "Box");
+                    ui.radio_value(shape, FieldShape::HollowShell, "Hollow Shell");
+                });
+                if let Err(reason) = &validation {
+                    ui.colored_label(egui::Color32::LIGHT_RED, reason);
+                }
+                ui.horizontal_centered(|ui| {
+                    if ui
+                        .add_enabled(validation.is_ok(), egui::Button::new("Start"))
+                        .clicked()
+                    {
                         next_state.set(GameState::GameStart);
                     }
                     if ui.add(egui::Button::new("Back")).clicked() {
@@ -127,32 +206,525 @@ fn display_custom_menu(
 fn display_settings_menu(
     mut contexts: EguiContexts,
     mut game_settings: ResMut<GameSettings>,
+    mut color_scheme: ResMut<ColorScheme>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    let safety = &mut game_settings.safety;
     let ctx = contexts.ctx_mut();
     global_settings(ctx);
     create_menu_window("Settings").show(ctx, |ui| {
         ui.allocate_ui(egui::Vec2::new(0.0, 0.0), |ui| {
             ui.vertical_centered(|ui| {
+                settings_controls(ui, &mut game_settings, &mut color_scheme);
                 ui.horizontal_centered(|ui| {
-                    ui.label("First Block Safety:");
-                    ui.radio_value(safety, Safety::Clear, "Clear")
-                        .on_hover_text(
-                            "The first block cleared is guaranteed to reveal more than one space.",
-                        );
-                    ui.radio_value(safety, Safety::Safe, "Safe")
-                        .on_hover_text(concat!(
-                            "The first block cleared is guaranteed to be safe, ",
-                            "but may only reveal one space."
-                        ));
-                    ui.radio_value(safety, Safety::Random, "Random")
-                        .on_hover_text(
-                            "No safety guarantees - the first block cleared might contain a mine.",
-                        );
+                    if ui.add(egui::Button::new("Back")).clicked() {
+                        next_state.set(GameState::MenuMain);
+                    }
                 });
+            });
+        });
+    });
+}
+
+/// Controls for every field of [GameSettings] and the choice of [ColorScheme],
+/// shared between the settings and pause menus.
+fn settings_controls(
+    ui: &mut egui::Ui,
+    settings: &mut GameSettings,
+    color_scheme: &mut ColorScheme,
+) {
+    egui::ScrollArea::vertical()
+        .max_height(ui.ctx().screen_rect().height() * 0.6)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let safety = &mut settings.safety;
+                ui.label("First Block Safety:");
+                ui.radio_value(safety, Safety::Clear, "Clear")
+                    .on_hover_text(
+                        "The first block cleared is guaranteed to reveal more than one space.",
+                    );
+                ui.radio_value(safety, Safety::Safe, "Safe")
+                    .on_hover_text(concat!(
+                        "The first block cleared is guaranteed to be safe, ",
+                        "but may only reveal one space."
+                    ));
+                ui.radio_value(safety, Safety::Random, "Random")
+                    .on_hover_text(
+                        "No safety guarantees - the first block cleared might contain a mine.",
+                    );
+            });
+            ui.horizontal(|ui| {
+                let reveal_on_loss = &mut settings.reveal_on_loss;
+                ui.label("On Loss, Show:");
+                ui.radio_value(reveal_on_loss, RevealOnLoss::HitOnly, "Hit Mine");
+                ui.radio_value(reveal_on_loss, RevealOnLoss::AllMines, "All Mines");
+                ui.radio_value(reveal_on_loss, RevealOnLoss::Everything, "Everything");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.annotate_mines, "Number Mines")
+                    .on_hover_text(concat!(
+                        "Once the game is over, also show how many mines are next to each mine ",
+                        "shown, to help see what happened.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.mark_tooltip, "Mark Tooltips")
+                    .on_hover_text(concat!(
+                        "Hover a marked block to see the numbers of the revealed blocks around ",
+                        "it, to check why it was marked.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                let mut limited = settings.max_flood_depth.is_some();
+                ui.checkbox(&mut limited, "Limit Flood Reveal")
+                    .on_hover_text(concat!(
+                        "Stop a flood reveal after this many rings of blocks, so one click can't ",
+                        "clear half the field. Applies from the next game.",
+                    ));
+                settings.max_flood_depth = limited.then(|| settings.max_flood_depth.unwrap_or(3));
+                if let Some(max) = &mut settings.max_flood_depth {
+                    ui.add(egui::DragValue::new(max).clamp_range(1..=20));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Assist Moves:");
+                ui.add(egui::DragValue::new(&mut settings.assist_moves).clamp_range(0..=10))
+                    .on_hover_text(concat!(
+                        "The first few mines cleared each game are moved elsewhere instead ",
+                        "of detonating. Applies from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Free Moves:");
+                ui.add(egui::DragValue::new(&mut settings.free_safe_moves).clamp_range(0..=10))
+                    .on_hover_text(concat!(
+                        "Times each game a key press clears a block the numbers show is safe, ",
+                        "or the least risky one when stuck. Applies from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.new_board_on_loss, "New Board After Loss")
+                    .on_hover_text(concat!(
+                        "Restarting after a loss plays a fresh board. Turn off to retry the ",
+                        "same board instead.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.no_guess, "No Guessing")
+                    .on_hover_text(concat!(
+                        "Generate fields that can be solved from the first block cleared ",
+                        "without ever having to guess. Applies from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.solve_obvious, "Solve Obvious Moves")
+                    .on_hover_text(concat!(
+                        "Press Enter to clear and mark every block that the numbers on the ",
+                        "board already decide, repeating until none are left.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.zen, "Zen Mode")
+                    .on_hover_text(concat!(
+                        "Play without any mines, to relax or to learn the controls. ",
+                        "Applies from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.limit_flags, "Limit Flags")
+                    .on_hover_text("Allow no more marks than there are mines.");
+            });
+            ui.horizontal(|ui| {
+                let win_condition = &mut settings.win_condition;
+                ui.label("Win Condition:");
+                ui.radio_value(win_condition, WinCondition::RevealAll, "Clear All")
+                    .on_hover_text("Win by clearing every block that doesn't contain a mine.");
+                ui.radio_value(win_condition, WinCondition::MarkAll, "Mark All")
+                    .on_hover_text("Win by marking every mine, without marking any other block.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Time Limit:");
+                ui.add(
+                    egui::DragValue::new(&mut settings.time_limit)
+                        .clamp_range(0.0..=3600.0)
+                        .speed(10.0)
+                        .suffix(" s"),
+                )
+                .on_hover_text("Lose if the game isn't won in time. Set to 0 for no limit.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Adjacent Mines:");
+                connectivity_radios(ui, &mut settings.connectivity);
+            })
+            .response
+            .on_hover_text("Which surrounding blocks count towards a block's number.");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.preset_connectivity, "Presets Choose Adjacency")
+                    .on_hover_text(concat!(
+                        "Small fields count only blocks sharing a face, Medium adds edges, ",
+                        "and Large adds corners, for both numbers and flood reveals. ",
+                        "Disable to use the options above for every field.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Flood Reveal:");
+                connectivity_radios(ui, &mut settings.flood_connectivity);
+            })
+            .response
+            .on_hover_text(concat!(
+                "Which surrounding blocks are revealed when a block with no adjacent mines ",
+                "is cleared. Doesn't change what the numbers mean.",
+            ));
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.flood_respects_flags, "Flood Skips Marked Blocks")
+                    .on_hover_text("Leave marked blocks hidden when a flood reveal reaches them.");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.chord_corrects_flags, "Chording Clears Wrong Marks")
+                    .on_hover_text(concat!(
+                        "Chording also clears marked neighbors that aren't mines. A wrong mark ",
+                        "means a mine is left unmarked, so this usually detonates it. ",
+                        "Applies from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                let reveal_style = &mut settings.reveal_style;
+                ui.label("Reveal Style:");
+                ui.radio_value(reveal_style, RevealStyle::Sphere, "Spheres")
+                    .on_hover_text("Clusters of spheres, with a ring for every five mines.");
+                ui.radio_value(reveal_style, RevealStyle::ShrunkCube, "Cubes")
+                    .on_hover_text("Small cubes colored by the number of adjacent mines.");
+                ui.radio_value(reveal_style, RevealStyle::NumberText, "Numbers")
+                    .on_hover_text("The number of adjacent mines, written out.");
+                ui.radio_value(reveal_style, RevealStyle::SphereWithNumber, "Both")
+                    .on_hover_text("Clusters of spheres, with the number written over them.");
+                ui.radio_value(reveal_style, RevealStyle::Billboard, "Discs")
+                    .on_hover_text("Flat discs that stay the same size on screen, with numbers.");
+            });
+            ui.horizontal(|ui| {
+                let reveal_anim = &mut settings.reveal_anim;
+                ui.label("Reveal Animation:");
+                ui.radio_value(reveal_anim, RevealAnim::Shrink, "Shrink")
+                    .on_hover_text("Cleared blocks shrink away in place.");
+                ui.radio_value(reveal_anim, RevealAnim::Sink, "Sink")
+                    .on_hover_text("Cleared blocks sink downwards as they shrink.");
+                ui.radio_value(reveal_anim, RevealAnim::FlipTowardCamera, "Flip")
+                    .on_hover_text("Cleared blocks tip over towards the camera as they shrink.");
+                ui.radio_value(reveal_anim, RevealAnim::Inward, "Inward")
+                    .on_hover_text("Cleared blocks fall in towards the center as they shrink.");
+            });
+            ui.horizontal(|ui| {
+                let spawn_order = &mut settings.spawn_order;
+                ui.label("Build Order:");
+                ui.radio_value(spawn_order, SpawnOrder::CenterOut, "Center Out")
+                    .on_hover_text("Blocks appear outwards from the center.");
+                ui.radio_value(spawn_order, SpawnOrder::Layers, "Layers")
+                    .on_hover_text("Blocks appear one layer at a time, from the bottom up.");
+                ui.radio_value(spawn_order, SpawnOrder::Spiral, "Spiral")
+                    .on_hover_text("Blocks appear in a spiral, from the bottom up.");
+                ui.radio_value(spawn_order, SpawnOrder::Random, "Random")
+                    .on_hover_text("Blocks appear in a random order.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Reveal Button:");
+                mouse_button_select(ui, "reveal_button", &mut settings.reveal_button);
+                ui.label("Flag Button:");
+                mouse_button_select(ui, "flag_button", &mut settings.flag_button);
+                ui.label("Rotate Button:");
+                mouse_button_select(ui, "rotate_button", &mut settings.rotate_button);
+            });
+            if [settings.reveal_button, settings.flag_button].contains(&settings.rotate_button) {
+                ui.colored_label(
+                    egui::Color32::LIGHT_RED,
+                    "Dragging to rotate will also click blocks with this button",
+                );
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.chording, "Chording")
+                    .on_hover_text(concat!(
+                        "Clear a revealed number whose mines are all marked to clear its ",
+                        "remaining neighbors. Hover the number to preview them.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.sweep_reveal, "Sweep Reveal")
+                    .on_hover_text(concat!(
+                        "Drag with the reveal button held to clear every block the cursor ",
+                        "passes over. Careful - this can detonate mines!"
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.camera_inertia, "Camera Inertia")
+                    .on_hover_text("Keep the camera spinning for a moment after a drag.");
+                ui.add_enabled(
+                    settings.camera_inertia,
+                    egui::Slider::new(&mut settings.camera_damping, 1.0..=20.0).text("Damping"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.idle_spin, "Idle Spin")
+                    .on_hover_text("Slowly orbit the field when left alone, until the next input.");
+                ui.add_enabled(
+                    settings.idle_spin,
+                    egui::Slider::new(&mut settings.idle_spin_delay, 5.0..=120.0)
+                        .text("Delay")
+                        .suffix(" s"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.zoom_to_cursor, "Zoom to Cursor")
+                    .on_hover_text(concat!(
+                        "Zoom in towards the block under the cursor, instead of the center of ",
+                        "the field.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.reticle_aim, "Aim With Reticle")
+                    .on_hover_text(concat!(
+                        "Pick blocks at a crosshair in the center of the screen instead of at ",
+                        "the cursor. Space clears and F marks the block under it.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut settings.revealed_blocks_occlude,
+                    "Revealed Blocks Occlude Clicks",
+                )
+                .on_hover_text(concat!(
+                    "Clicking where a block has been revealed does nothing, ",
+                    "instead of reaching hidden blocks behind it."
+                ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.inspect_allows_marking, "Mark While Inspecting")
+                    .on_hover_text("Still allow marking blocks in inspect mode (I).");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Edge Tolerance:");
+                ui.add(egui::Slider::new(&mut settings.pick_tolerance, 0.0..=0.5))
+                    .on_hover_text(concat!(
+                        "When clicking near an edge between blocks, pick the block the cursor ",
+                        "is most over, instead of strictly the nearest.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.animations_enabled, "Animations")
+                    .on_hover_text(
+                        "Show cosmetic effects, such as a ripple where blocks are clicked.",
+                    );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Block Size:");
+                ui.add(
+                    egui::Slider::new(&mut settings.cube_size, 0.25..=1.0)
+                        .min_decimals(2)
+                        .max_decimals(2),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("World Scale:");
+                ui.add(
+                    egui::Slider::new(&mut settings.world_scale, 0.25..=4.0)
+                        .min_decimals(2)
+                        .max_decimals(2),
+                )
+                .on_hover_text("Size of the whole field in the world. Applies from the next game.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Highlight:");
+                let mut rgb = [
+                    settings.highlight_color.r(),
+                    settings.highlight_color.g(),
+                    settings.highlight_color.b(),
+                ];
+                if ui.color_edit_button_rgb(&mut rgb).changed() {
+                    settings.highlight_color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+                }
+                ui.add(
+                    egui::Slider::new(&mut settings.highlight_intensity, 0.0..=1.0).text("Glow"),
+                )
+                .on_hover_text(concat!(
+                    "Color of blocks that chording would clear, and how brightly they glow. ",
+                    "Applies from the next game.",
+                ));
+            });
+            ui.horizontal(|ui| {
+                let mut custom = settings.count_gradient.is_some();
+                ui.checkbox(&mut custom, "Number Gradient")
+                    .on_hover_text(concat!(
+                        "Color numbers along a gradient from the fewest to the most adjacent ",
+                        "mines, instead of with the color scheme. Applies from the next game.",
+                    ));
+                settings.count_gradient = custom.then(|| {
+                    settings
+                        .count_gradient
+                        .unwrap_or((Color::rgb(0.2, 0.4, 1.0), Color::rgb(1.0, 0.1, 0.1)))
+                });
+                if let Some((low, high)) = &mut settings.count_gradient {
+                    for color in [low, high] {
+                        let mut rgb = [color.r(), color.g(), color.b()];
+                        if ui.color_edit_button_rgb(&mut rgb).changed() {
+                            *color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+                        }
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Extra Lights:");
+                ui.add(egui::DragValue::new(&mut settings.light_count).clamp_range(0..=8))
+                    .on_hover_text(concat!(
+                        "Lights around the field, so its far side isn't left in shadow. ",
+                        "Applies from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.show_ground, "Ground Grid")
+                    .on_hover_text("Show a grid below the field. Applies from the next game.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("UI Scale:");
+                ui.add(egui::Slider::new(&mut settings.ui_scale, 0.5..=2.0));
+                ui.checkbox(&mut settings.scale_ui_with_window, "Fit to Window")
+                    .on_hover_text("Shrink text and menus in small windows, and grow them in large ones.");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.show_axes, "Axis Indicator")
+                    .on_hover_text("Show which way the field's axes point in a corner of the screen.");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.screenshot_hud, "HUD in Screenshots")
+                    .on_hover_text("Keep the HUD visible in screenshots.");
+            });
+            ui.horizontal(|ui| {
+                let mut colorblind = color_scheme.is_colorblind();
+                if ui
+                    .checkbox(&mut colorblind, "Colorblind Colors")
+                    .on_hover_text("Colors that are easier to tell apart. Applies from the next game.")
+                    .changed()
+                {
+                    *color_scheme = if colorblind {
+                        ColorScheme::colorblind()
+                    } else {
+                        ColorScheme::default()
+                    };
+                }
+                ui.checkbox(&mut color_scheme.flag_pattern, "Striped Marks")
+                    .on_hover_text("Stripe marked blocks. Applies from the next game.");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.flat_shading, "Flat Shading").on_hover_text(
+                    "Plain, untextured blocks with uniformly shaded faces. Applies from the next game.",
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.high_contrast, "High Contrast")
+                    .on_hover_text(concat!(
+                        "Outline every block and use bold, clearly distinct colors, replacing ",
+                        "the color scheme. Colors apply from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                let initial_view = &mut settings.initial_view;
+                ui.label("Starting View:");
+                ui.radio_value(initial_view, InitialView::Isometric, "Corner");
+                ui.radio_value(initial_view, InitialView::TopDown, "Top");
+                ui.radio_value(initial_view, InitialView::Front, "Front");
+            });
+            ui.horizontal(|ui| {
+                let up_axis = &mut settings.up_axis;
+                ui.label("Vertical Axis:");
+                ui.radio_value(up_axis, UpAxis::Y, "Height")
+                    .on_hover_text("The field's height points up.");
+                ui.radio_value(up_axis, UpAxis::Z, "Depth")
+                    .on_hover_text(concat!(
+                        "The field's depth points up, so the layers in the layer summary ",
+                        "stack from the bottom up. Applies from the next game.",
+                    ));
+            });
+            ui.horizontal(|ui| {
+                let msaa = &mut settings.msaa;
+                ui.label("MSAA:");
+                ui.radio_value(msaa, Msaa::Off, "Off");
+                ui.radio_value(msaa, Msaa::Sample2, "2x")
+                    .on_hover_text("Not supported by all graphics hardware.");
+                ui.radio_value(msaa, Msaa::Sample4, "4x");
+                ui.radio_value(msaa, Msaa::Sample8, "8x")
+                    .on_hover_text("Not supported by all graphics hardware.");
+                ui.checkbox(&mut settings.fxaa, "FXAA")
+                    .on_hover_text("Smooths remaining jagged edges. Applies from the next game.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Autosave Every:");
+                ui.add(
+                    egui::Slider::new(&mut settings.autosave_interval, 0.0..=300.0)
+                        .step_by(15.0)
+                        .suffix(" s"),
+                )
+                .on_hover_text("Set to 0 to disable autosaving.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Sound Volume:");
+                ui.add(egui::Slider::new(&mut settings.sfx_volume, 0.0..=1.0).show_value(false));
+                ui.checkbox(&mut settings.adjacency_pitch, "Pitch by Number")
+                    .on_hover_text(
+                        "Blocks with more adjacent mines make a higher sound when cleared.",
+                    );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Jingle Volume:");
+                ui.add(egui::Slider::new(&mut settings.music_volume, 0.0..=1.0).show_value(false))
+                    .on_hover_text("Volume of the tunes played when a game is won or lost.");
+            });
+            ui.horizontal(|ui| {
+                let explosion_sound = &mut settings.explosion_sound;
+                ui.label("Explosion Sound:");
+                ui.radio_value(explosion_sound, ExplosionSound::None, "None");
+                ui.radio_value(explosion_sound, ExplosionSound::Pop, "Pop");
+                ui.radio_value(explosion_sound, ExplosionSound::Boom, "Boom");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Explosion Debris:");
+                ui.add(egui::DragValue::new(&mut settings.explosion_particles).clamp_range(0..=200))
+                    .on_hover_text("Pieces thrown out by a detonated mine, if animations are on.");
+                ui.checkbox(&mut settings.screen_shake, "Screen Shake")
+                    .on_hover_text("Shake the camera when a mine is detonated, if animations are on.");
+            });
+        });
+}
+
+fn connectivity_radios(ui: &mut egui::Ui, connectivity: &mut Connectivity) {
+    ui.radio_value(connectivity, Connectivity::Faces, "Faces (6)");
+    ui.radio_value(connectivity, Connectivity::Edges, "Edges (18)");
+    ui.radio_value(connectivity, Connectivity::Vertices, "Corners (26)");
+}
+
+fn toggle_pause(mut input_events: EventReader<InputEvent>, mut paused: ResMut<Paused>) {
+    for event in input_events.read() {
+        if matches!(event, InputEvent::Pause) {
+            paused.0 = !paused.0;
+            debug!("Paused: {}", paused.0);
+        }
+    }
+}
+
+fn display_pause_menu(
+    mut contexts: EguiContexts,
+    mut game_settings: ResMut<GameSettings>,
+    mut color_scheme: ResMut<ColorScheme>,
+    mut paused: ResMut<Paused>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let ctx = contexts.ctx_mut();
+    global_settings(ctx);
+    create_menu_window("Paused").show(ctx, |ui| {
+        ui.allocate_ui(egui::Vec2::new(0.0, 0.0), |ui| {
+            ui.vertical_centered(|ui| {
+                settings_controls(ui, &mut game_settings, &mut color_scheme);
                 ui.horizontal_centered(|ui| {
-                    if ui.add(egui::Button::new("Back")).clicked() {
+                    if ui.add(egui::Button::new("Resume")).clicked() {
+                        paused.0 = false;
+                    }
+                    if ui.add(egui::Button::new("Main Menu")).clicked() {
+                        paused.0 = false;
                         next_state.set(GameState::MenuMain);
                     }
                 });
@@ -161,20 +733,69 @@ fn display_settings_menu(
     });
 }
 
+fn mouse_button_select(ui: &mut egui::Ui, id: &str, button: &mut MouseButton) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(format!("{button:?}"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(button, MouseButton::Left, "Left");
+            ui.selectable_value(button, MouseButton::Right, "Right");
+            ui.selectable_value(button, MouseButton::Middle, "Middle");
+            ui.selectable_value(button, MouseButton::Back, "Back");
+            ui.selectable_value(button, MouseButton::Forward, "Forward");
+        });
+}
+
+/// Play again on the board just played, from [BoardSeed::current]
+fn retry_board(board_seed: &mut BoardSeed, next_state: &mut NextState<GameState>) {
+    board_seed.next = board_seed.current;
+    next_state.set(GameState::GameStart);
+}
+
+/// Play again on a fresh board
+fn new_board(board_seed: &mut BoardSeed, next_state: &mut NextState<GameState>) {
+    board_seed.next = None;
+    next_state.set(GameState::GameStart);
+}
+
+/// Restart on [InputEvent::RetryBoard] or [InputEvent::NewBoard]
+fn restart_keys(
+    mut input_events: EventReader<InputEvent>,
+    mut board_seed: ResMut<BoardSeed>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in input_events.read() {
+        match event {
+            InputEvent::RetryBoard => retry_board(&mut board_seed, &mut next_state),
+            InputEvent::NewBoard => new_board(&mut board_seed, &mut next_state),
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn display_game_over(
     mut contexts: EguiContexts,
     mut next_state: ResMut<NextState<GameState>>,
     mut exit_events: EventWriter<AppExit>,
+    mut field_settings: ResMut<FieldSettings>,
+    mut board_seed: ResMut<BoardSeed>,
+    campaign: Res<Campaign>,
     game_result: Res<GameResult>,
+    game_settings: Res<GameSettings>,
+    key_bindings: Res<KeyBindings>,
+    stats: Res<GameStats>,
 ) {
     let ctx = contexts.ctx_mut();
     global_settings(ctx);
+    let victory = matches!(*game_result, GameResult::Victory);
     egui::Window::new(match *game_result {
         GameResult::Unfinished => {
             error!("Should not be displaying game over menu when GameResult::Unfinished");
             "Game Over"
         }
         GameResult::Failure => "Game Over",
+        GameResult::Surrender => "Surrendered",
+        GameResult::Victory if campaign.active && campaign.completed() => "Campaign Complete!",
         GameResult::Victory => "Victory!",
     })
     .anchor(egui::Align2::CENTER_BOTTOM, [0.0, 0.0])
@@ -184,9 +805,57 @@ fn display_game_over(
     .show(ctx, |ui| {
         ui.allocate_ui(egui::Vec2::new(0.0, 0.0), |ui| {
             ui.vertical_centered(|ui| {
+                if let Some(efficiency) = stats.efficiency().filter(|_| victory) {
+                    ui.add(egui::Label::new(format!(
+                        "3BV {} in {} clicks ({efficiency:.0}% efficient)",
+                        stats.three_bv, stats.clicks
+                    )));
+                }
+                if let Some(seed) = board_seed.current {
+                    ui.horizontal_centered(|ui| {
+                        ui.add(egui::Label::new(format!("Seed: {seed}")));
+                        if ui.add(egui::Button::new("Copy")).clicked() {
+                            ui.output_mut(|output| output.copied_text = seed.to_string());
+                        }
+                        if ui
+                            .add(egui::Button::new("Same Board"))
+                            .on_hover_text("Play again with this seed. Clear the same block first to get the same mines.")
+                            .clicked()
+                        {
+                            retry_board(&mut board_seed, &mut next_state);
+                        }
+                    });
+                }
+                if !victory {
+                    ui.add(egui::Label::new(
+                        egui::RichText::new(format!(
+                            "{:?}: retry same board, {:?}: new board",
+                            key_bindings.retry_board, key_bindings.new_board
+                        ))
+                        .small()
+                        .weak(),
+                    ));
+                }
+                // Which board restarting after a loss plays
+                let restart = |board_seed: &mut BoardSeed, next_state: &mut NextState<GameState>| {
+                    if victory || game_settings.new_board_on_loss {
+                        new_board(board_seed, next_state);
+                    } else {
+                        retry_board(board_seed, next_state);
+                    }
+                };
                 ui.horizontal_centered(|ui| {
-                    if ui.add(egui::Button::new("Restart")).clicked() {
-                        next_state.set(GameState::GameStart);
+                    if campaign.active && victory && !campaign.completed() {
+                        if ui.add(egui::Button::new("Next Level")).clicked() {
+                            field_settings.set_if_neq(campaign.field_settings());
+                            next_state.set(GameState::GameStart);
+                        }
+                    } else if campaign.active && !victory {
+                        if ui.add(egui::Button::new("Retry")).clicked() {
+                            restart(&mut board_seed, &mut next_state);
+                        }
+                    } else if ui.add(egui::Button::new("Restart")).clicked() {
+                        restart(&mut board_seed, &mut next_state);
                     }
                     if ui.add(egui::Button::new("Main Menu")).clicked() {
                         next_state.set(GameState::MenuMain);
@@ -199,3 +868,46 @@ fn display_game_over(
         });
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::{field_app, mines, play};
+    use crate::game::{cleanup, SurrenderPrompt};
+    use crate::FieldEvent;
+
+    /// Leave the game for the menu, start the next one with `restart`, and clear the same
+    /// first block as every other game, returning where the mines were placed
+    fn replay(
+        app: &mut App,
+        restart: fn(&mut BoardSeed, &mut NextState<GameState>),
+    ) -> Vec<[usize; 3]> {
+        app.world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::MenuMain);
+        app.update();
+        app.world
+            .resource_scope(|world, mut board_seed: Mut<BoardSeed>| {
+                restart(
+                    &mut board_seed,
+                    &mut world.resource_mut::<NextState<GameState>>(),
+                );
+            });
+        app.update();
+        play(app, [FieldEvent::ClearBlock([0, 0, 0])]);
+        mines(app)
+    }
+
+    #[test]
+    fn retry_keeps_the_board_and_new_board_changes_it() {
+        let mut app = field_app(default(), FieldSettings::default());
+        app.init_resource::<Paused>()
+            .init_resource::<SurrenderPrompt>()
+            .add_systems(OnEnter(GameState::MenuMain), cleanup);
+        play(&mut app, [FieldEvent::ClearBlock([0, 0, 0])]);
+        let first = mines(&mut app);
+        assert!(!first.is_empty());
+        assert_eq!(replay(&mut app, retry_board), first);
+        assert_ne!(replay(&mut app, new_board), first);
+    }
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::window::PrimaryWindow;
+
+use crate::{storage, GameSettings, InputEvent};
+
+pub struct ScreenshotPlugin;
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Screenshot>()
+            .add_systems(Update, take_screenshot);
+    }
+}
+
+/// Progress of a screenshot requested with [InputEvent::Screenshot]
+#[derive(Debug, Default, Resource)]
+pub struct Screenshot(Option<ScreenshotStep>);
+impl Screenshot {
+    /// Whether the HUD should be hidden this frame, to keep it out of a screenshot
+    pub fn hides_hud(&self, game_settings: &GameSettings) -> bool {
+        self.0.is_some() && !game_settings.screenshot_hud
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ScreenshotStep {
+    /// Requested this frame, which may already have drawn the HUD
+    Requested,
+    /// Capturing this frame, with the HUD hidden
+    Capturing,
+}
+
+/// Where screenshots are saved, if anywhere
+fn screenshot_dir(game_settings: &GameSettings) -> Option<PathBuf> {
+    game_settings
+        .screenshot_dir
+        .clone()
+        .or_else(|| Some(storage::data_dir()?.join("screenshots")))
+}
+
+/// Save the frame after an [InputEvent::Screenshot] to a timestamped PNG in
+/// [GameSettings::screenshot_dir], so the HUD has a frame to disappear first.
+fn take_screenshot(
+    mut screenshot: ResMut<Screenshot>,
+    mut input_events: EventReader<InputEvent>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    game_settings: Res<GameSettings>,
+) {
+    let requested = input_events
+        .read()
+        .any(|event| matches!(event, InputEvent::Screenshot));
+    screenshot.0 = match screenshot.0 {
+        Some(ScreenshotStep::Requested) => {
+            capture(&mut screenshot_manager, &primary_window, &game_settings);
+            Some(ScreenshotStep::Capturing)
+        }
+        Some(ScreenshotStep::Capturing) | None => requested.then_some(ScreenshotStep::Requested),
+    };
+}
+
+fn capture(
+    screenshot_manager: &mut ScreenshotManager,
+    primary_window: &Query<Entity, With<PrimaryWindow>>,
+    game_settings: &GameSettings,
+) {
+    let Ok(window) = primary_window.get_single() else {
+        return;
+    };
+    let Some(dir) = screenshot_dir(game_settings) else {
+        error!("Unable to save screenshot: no screenshot directory available");
+        return;
+    };
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        error!(
+            "Unable to create screenshot directory {}: {err}",
+            dir.display()
+        );
+        return;
+    }
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default();
+    let path = dir.join(format!("sweeper-3d-{millis}.png"));
+    // Failures to write the file are logged once the frame has been captured
+    if let Err(err) = screenshot_manager.save_screenshot_to_disk(window, path) {
+        error!("Unable to take screenshot: {err}");
+    }
+}
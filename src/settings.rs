@@ -1,53 +1,455 @@
+use std::path::PathBuf;
+
 use bevy::prelude::*;
+use rand::seq::SliceRandom;
+
+/// Default for [GameSettings::max_cells], the size of the largest custom field
+pub const DEFAULT_MAX_CELLS: usize = 20 * 20 * 20;
 
 pub struct SettingsPlugin;
 impl Plugin for SettingsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GameSettings::default());
         app.insert_resource(FieldSettings::default());
+        app.insert_resource(BlockStyle::default());
+        app.insert_resource(ColorScheme::default());
+        app.add_systems(
+            Update,
+            apply_anti_aliasing.run_if(resource_changed::<GameSettings>),
+        );
     }
 }
 
-#[derive(Debug, Default, Resource)]
+#[derive(Debug, Resource)]
 pub struct GameSettings {
     /// Minefield generation constraints after first click
     pub safety: Safety,
+    /// Mouse button used to clear blocks
+    pub reveal_button: MouseButton,
+    /// Mouse button used to mark blocks
+    pub flag_button: MouseButton,
+    /// Mouse button held to rotate the camera by dragging
+    pub rotate_button: MouseButton,
+    /// Multisample anti-aliasing sample count
+    pub msaa: Msaa,
+    /// Whether to additionally apply FXAA to the main camera (applied when a game starts)
+    pub fxaa: bool,
+    /// Whether dragging with [GameSettings::reveal_button] held clears every block the cursor passes over
+    pub sweep_reveal: bool,
+    /// Whether blocks are picked at a reticle in the center of the screen instead of at the
+    /// cursor, for aiming without a mouse
+    pub reticle_aim: bool,
+    /// Edge length of each block, relative to the unit spacing of the field grid.
+    /// Values below 1.0 leave gaps between blocks.
+    pub cube_size: f32,
+    /// Whether the camera keeps spinning for a moment after a drag is released
+    pub camera_inertia: bool,
+    /// Exponential decay rate of the spin with [GameSettings::camera_inertia], per second.
+    /// Higher values stop it sooner.
+    pub camera_damping: f32,
+    /// Whether the camera slowly orbits the field once there's been no input for
+    /// [GameSettings::idle_spin_delay] seconds. Any input stops it.
+    pub idle_spin: bool,
+    /// Seconds without input before [GameSettings::idle_spin] starts
+    pub idle_spin_delay: f32,
+    /// Whether scrolling zooms in towards whatever is under the cursor, moving the point the
+    /// camera orbits along with it, instead of straight towards the center of the field.
+    /// Zooming out drifts back towards the center.
+    pub zoom_to_cursor: bool,
+    /// World units per cell of the field grid. Scales the blocks, their spacing, the camera
+    /// and the ground together, so nothing looks different but the size of the world
+    /// (applied when a game starts).
+    pub world_scale: f32,
+    /// Volume of sound effects, from 0.0 (muted) to 1.0
+    pub sfx_volume: f32,
+    /// Volume of the jingles played when a game is won or lost, from 0.0 (muted) to 1.0.
+    /// Separate from [GameSettings::sfx_volume], so they can be muted on their own.
+    pub music_volume: f32,
+    /// Number of pieces of debris thrown out by a detonated mine, if animations are enabled
+    pub explosion_particles: usize,
+    /// Sound played when a mine is detonated
+    pub explosion_sound: ExplosionSound,
+    /// Whether the camera shakes when a mine is detonated, if animations are enabled
+    pub screen_shake: bool,
+    /// What the player has to do to win
+    pub win_condition: WinCondition,
+    /// Whether games are played with no mines at all, for relaxing or learning the controls.
+    /// Every clear floods outwards, and the game is won once every block is cleared.
+    pub zen: bool,
+    /// Whether marks are refused once there are as many as there are mines
+    pub limit_flags: bool,
+    /// Seconds the player has to win each game before losing, or 0.0 for no limit
+    pub time_limit: f32,
+    /// How revealed blocks show their number of adjacent mines (applied to newly revealed blocks)
+    pub reveal_style: RevealStyle,
+    /// How cleared blocks animate away before showing their contents, if animations are enabled
+    pub reveal_anim: RevealAnim,
+    /// Order blocks appear in when a game starts, if animations are enabled
+    pub spawn_order: SpawnOrder,
+    /// Which axis of the field points up (applied when a game starts)
+    pub up_axis: UpAxis,
+    /// Whether to show purely cosmetic animations, such as the ripple when a block is clicked
+    pub animations_enabled: bool,
+    /// Largest number of cells a field may have. Larger fields are shrunk to fit when the game starts.
+    pub max_cells: usize,
+    /// Whether revealed blocks stop clicks from reaching hidden blocks behind them
+    pub revealed_blocks_occlude: bool,
+    /// Blocks hit within this distance of the nearest hit, relative to a cell, are also
+    /// candidates for a click. The one whose center is closest to the ray wins, so clicks
+    /// near a shared edge pick the block the cursor is more over.
+    pub pick_tolerance: f32,
+    /// Whether blocks can still be marked in inspect mode, see [crate::Inspecting]
+    pub inspect_allows_marking: bool,
+    /// Override for the camera's near clipping plane. By default it's close enough to never clip blocks.
+    pub camera_near: Option<f32>,
+    /// Override for the camera's far clipping plane. By default it's derived from the field size.
+    pub camera_far: Option<f32>,
+    /// World units to move the point the camera orbits around and looks at, from the
+    /// centroid of the field's cells (applied when a game starts).
+    pub camera_target_offset: Vec3,
+    /// Which neighboring cells count towards a block's number of adjacent mines
+    pub connectivity: Connectivity,
+    /// Which neighboring cells are revealed when a block with no adjacent mines is cleared.
+    /// Independent of [GameSettings::connectivity], so the cascade can be tuned without
+    /// changing what the numbers mean.
+    pub flood_connectivity: Connectivity,
+    /// Whether fields with a [FieldSettings::connectivity] of their own, like the Small,
+    /// Medium and Large presets, use it for both the numbers and the flood reveal.
+    /// If disabled, [GameSettings::connectivity] and [GameSettings::flood_connectivity]
+    /// always apply.
+    pub preset_connectivity: bool,
+    /// Whether the flood reveal leaves marked blocks alone. If disabled, it clears them,
+    /// removing the mark.
+    pub flood_respects_flags: bool,
+    /// Whether chording also clears marked neighbors that turn out not to be mines,
+    /// instead of leaving every marked neighbor alone
+    pub chord_corrects_flags: bool,
+    /// Whether the reveal sound is pitched higher for blocks with more adjacent mines
+    pub adjacency_pitch: bool,
+    /// Whether clicking a revealed number with all its mines marked clears its other neighbors
+    pub chording: bool,
+    /// Angle the camera views the field from when a game starts
+    pub initial_view: InitialView,
+    /// Most rings of blocks a single flood reveal spreads out from the block cleared,
+    /// leaving anything farther hidden. `None` floods without limit, as usual.
+    pub max_flood_depth: Option<usize>,
+    /// Number of times per game that clearing a mine moves it elsewhere instead of detonating.
+    /// Makes the game easier, but less fair, so it's off (0) by default.
+    pub assist_moves: usize,
+    /// Number of times per game that [crate::KeyBindings::free_move] clears a hidden block
+    /// the numbers show is safe, or the least risky one if none is. Off (0) by default.
+    pub free_safe_moves: usize,
+    /// Whether restarting after a loss plays a fresh board, instead of retrying the same one.
+    /// Either can still be picked with [crate::KeyBindings::retry_board] and
+    /// [crate::KeyBindings::new_board].
+    pub new_board_on_loss: bool,
+    /// Whether [crate::KeyBindings::solve_obvious] makes every move that follows directly
+    /// from the numbers on the board
+    pub solve_obvious: bool,
+    /// Whether fields are regenerated until they can be solved from the first block
+    /// cleared without guessing. See [crate::GenerationReport] for how it went.
+    pub no_guess: bool,
+    /// Whether hidden blocks use a plain untextured material, so each face is a uniform shade
+    /// and the edges between blocks are easier to see (applied when a game starts)
+    pub flat_shading: bool,
+    /// Whether to make everything as easy to tell apart as possible, for low vision: dark
+    /// outlines around hidden blocks, plain light blocks, [ColorScheme::high_contrast]
+    /// in place of the chosen colors, a stronger highlight and larger, outlined numbers.
+    /// The outlines and numbers change right away, the rest when a game starts.
+    pub high_contrast: bool,
+    /// Seconds between automatic saves of a game in progress. 0 disables autosaving.
+    pub autosave_interval: f32,
+    /// How much of the field is shown after a mine is detonated
+    pub reveal_on_loss: RevealOnLoss,
+    /// Whether mines shown at the end of a game are labeled with how many mines are next to
+    /// them, as empty blocks are
+    pub annotate_mines: bool,
+    /// Whether hovering a marked block shows the numbers of its revealed neighbors in a
+    /// tooltip, to double-check the reasoning behind the mark
+    pub mark_tooltip: bool,
+    /// Whether to show a ground plane with a grid below the field (applied when a game starts)
+    pub show_ground: bool,
+    /// Whether to show which way the field's axes point, in a corner of the screen
+    pub show_axes: bool,
+    /// Directory screenshots are saved to. By default, `screenshots` in the data directory.
+    pub screenshot_dir: Option<PathBuf>,
+    /// Whether screenshots include the HUD
+    pub screenshot_hud: bool,
+    /// Scale of all text and menus, on top of any scaling to fit the window
+    pub ui_scale: f32,
+    /// Whether text and menus shrink in small windows and grow in large ones
+    pub scale_ui_with_window: bool,
+    /// Number of point lights spaced in a ring around the field, in addition to the sun,
+    /// to light its far side (applied when a game starts). Their total brightness is the
+    /// same however many there are.
+    pub light_count: usize,
+    /// Tint of hidden blocks highlighted by the chord preview (applied when a game starts).
+    /// Should stand out from every color of the [ColorScheme].
+    pub highlight_color: Color,
+    /// How strongly highlighted blocks glow in [GameSettings::highlight_color], so they
+    /// stay visible whatever the lighting
+    pub highlight_intensity: f32,
+    /// Colors for the fewest and most adjacent mines, replacing [ColorScheme::counts] with a
+    /// gradient between them (applied when a game starts), if set
+    pub count_gradient: Option<(Color, Color)>,
+}
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            safety: Safety::default(),
+            reveal_button: MouseButton::Left,
+            flag_button: MouseButton::Right,
+            rotate_button: MouseButton::Middle,
+            msaa: Msaa::Sample4,
+            fxaa: false,
+            sweep_reveal: false,
+            reticle_aim: false,
+            cube_size: 1.0,
+            camera_inertia: false,
+            camera_damping: 5.0,
+            idle_spin: false,
+            idle_spin_delay: 30.0,
+            zoom_to_cursor: true,
+            world_scale: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 0.5,
+            explosion_particles: 24,
+            explosion_sound: ExplosionSound::default(),
+            screen_shake: true,
+            win_condition: WinCondition::default(),
+            zen: false,
+            limit_flags: false,
+            time_limit: 0.0,
+            reveal_style: RevealStyle::default(),
+            reveal_anim: RevealAnim::default(),
+            spawn_order: SpawnOrder::default(),
+            up_axis: UpAxis::default(),
+            animations_enabled: true,
+            max_cells: DEFAULT_MAX_CELLS,
+            revealed_blocks_occlude: false,
+            pick_tolerance: 0.1,
+            inspect_allows_marking: true,
+            camera_near: None,
+            camera_far: None,
+            camera_target_offset: Vec3::ZERO,
+            connectivity: Connectivity::default(),
+            flood_connectivity: Connectivity::default(),
+            preset_connectivity: true,
+            flood_respects_flags: true,
+            chord_corrects_flags: false,
+            adjacency_pitch: true,
+            chording: true,
+            initial_view: InitialView::default(),
+            max_flood_depth: None,
+            assist_moves: 0,
+            free_safe_moves: 0,
+            new_board_on_loss: true,
+            solve_obvious: false,
+            no_guess: false,
+            flat_shading: false,
+            high_contrast: false,
+            autosave_interval: 60.0,
+            reveal_on_loss: RevealOnLoss::default(),
+            annotate_mines: false,
+            mark_tooltip: false,
+            show_ground: true,
+            show_axes: false,
+            screenshot_dir: None,
+            screenshot_hud: false,
+            ui_scale: 1.0,
+            scale_ui_with_window: true,
+            light_count: 0,
+            highlight_color: Color::rgb(0.3, 1.0, 0.9),
+            highlight_intensity: 0.3,
+            count_gradient: None,
+        }
+    }
+}
+impl GameSettings {
+    /// Colors of revealed numbers from 1 up, taking [GameSettings::high_contrast] and
+    /// [GameSettings::count_gradient] into account. A gradient has a color for every number
+    /// possible with `connectivity`, and otherwise numbers past the last share its color.
+    pub fn count_colors(
+        &self,
+        color_scheme: &ColorScheme,
+        connectivity: Connectivity,
+    ) -> Vec<Color> {
+        if self.high_contrast {
+            return ColorScheme::high_contrast().counts.to_vec();
+        }
+        match self.count_gradient {
+            Some((low, high)) => {
+                let steps = connectivity.neighbor_count().max(2) - 1;
+                (0..=steps)
+                    .map(|n| lerp_hsv(low, high, n as f32 / steps as f32))
+                    .collect()
+            }
+            None => color_scheme.counts.to_vec(),
+        }
+    }
+    /// Neighbors counted towards each number on a field with these settings
+    pub fn connectivity_for(&self, field_settings: &FieldSettings) -> Connectivity {
+        self.preset_for(field_settings).unwrap_or(self.connectivity)
+    }
+    /// Neighbors revealed by the flood reveal on a field with these settings
+    pub fn flood_connectivity_for(&self, field_settings: &FieldSettings) -> Connectivity {
+        self.preset_for(field_settings)
+            .unwrap_or(self.flood_connectivity)
+    }
+    fn preset_for(&self, field_settings: &FieldSettings) -> Option<Connectivity> {
+        field_settings
+            .connectivity
+            .filter(|_| self.preset_connectivity)
+    }
 }
 
-#[derive(Debug, Resource, PartialEq)]
+/// Keep the [Msaa] resource in sync with [GameSettings::msaa].
+fn apply_anti_aliasing(game_settings: Res<GameSettings>, mut msaa: ResMut<Msaa>) {
+    msaa.set_if_neq(game_settings.msaa);
+}
+
+#[derive(Debug, Clone, Resource, PartialEq)]
 pub struct FieldSettings {
     /// Minefield dimensions
     pub field_size: [usize; 3],
     /// Average density of mines (number of mines/number of cells)
     pub mine_density: f32,
+    /// Which cells within [FieldSettings::field_size] contain a block
+    pub shape: FieldShape,
+    /// Neighbors that count as adjacent on this field, if it has its own.
+    /// Fewer neighbors make each number say more, so the presets get easier with fewer.
+    /// See [GameSettings::preset_connectivity].
+    pub connectivity: Option<Connectivity>,
 }
 impl FieldSettings {
     pub fn small() -> Self {
         Self {
             field_size: [3, 3, 3],
             mine_density: 0.2,
+            shape: FieldShape::Box,
+            connectivity: Some(Connectivity::Faces),
         }
     }
     pub fn medium() -> Self {
         Self {
             field_size: [5, 5, 5],
             mine_density: 0.1,
+            shape: FieldShape::Box,
+            connectivity: Some(Connectivity::Edges),
         }
     }
     pub fn large() -> Self {
         Self {
             field_size: [10, 10, 10],
             mine_density: 0.1,
+            shape: FieldShape::Box,
+            connectivity: Some(Connectivity::Vertices),
+        }
+    }
+    /// Total number of cells in the field
+    pub fn num_cells(&self) -> usize {
+        let total = self.field_size.iter().product();
+        match self.shape {
+            FieldShape::Box => total,
+            FieldShape::HollowShell => {
+                let interior: usize = self
+                    .field_size
+                    .iter()
+                    .map(|n| n.saturating_sub(2))
+                    .product();
+                total - interior
+            }
         }
     }
+    /// Average position of the field's cells, in cells from the center of its bounding box
+    /// along each field axis. Zero for shapes as symmetric as [FieldShape::Box].
+    pub fn centroid(&self) -> Vec3 {
+        let [x, y, z] = self.field_size;
+        let mut sum = Vec3::ZERO;
+        let mut count = 0;
+        for i in 0..x {
+            for j in 0..y {
+                for k in 0..z {
+                    if self.shape.contains([i, j, k], self.field_size) {
+                        sum += Vec3::new(i as f32, j as f32, k as f32);
+                        count += 1;
+                    }
+                }
+            }
+        }
+        if count == 0 {
+            return Vec3::ZERO;
+        }
+        let box_center = Vec3::new(x as f32 - 1.0, y as f32 - 1.0, z as f32 - 1.0) / 2.0;
+        sum / count as f32 - box_center
+    }
+    /// Number of mines the field will be generated with
+    pub fn num_mines(&self) -> usize {
+        (self.num_cells() as f64 * f64::from(self.mine_density)) as usize
+    }
+    /// Check that these settings describe a playable field.
+    /// Returns a description of the problem if not.
+    pub fn validate(&self) -> Result<(), String> {
+        let (cells, mines) = (self.num_cells(), self.num_mines());
+        if mines == 0 {
+            Err(format!(
+                "{cells} cells at this density would contain no mines"
+            ))
+        } else if mines >= cells {
+            Err(format!("{mines} mines do not fit in {cells} cells"))
+        } else {
+            Ok(())
+        }
+    }
+    /// Shrink the field until it has at most `max_cells` cells, reducing the largest dimension first.
+    /// Returns true if the field had to be shrunk.
+    pub fn clamp_cells(&mut self, max_cells: usize) -> bool {
+        let mut clamped = false;
+        while self.num_cells() > max_cells.max(1) {
+            let largest = (0..3).max_by_key(|&axis| self.field_size[axis]).unwrap();
+            self.field_size[largest] -= 1;
+            clamped = true;
+        }
+        clamped
+    }
     /// Split this struct into mutable fields that can be passed to UI elements
-    pub fn fields_mut(&mut self) -> (&mut [usize], &mut f32) {
-        (self.field_size.as_mut_slice(), &mut self.mine_density)
+    pub fn fields_mut(&mut self) -> (&mut [usize], &mut f32, &mut FieldShape) {
+        (
+            self.field_size.as_mut_slice(),
+            &mut self.mine_density,
+            &mut self.shape,
+        )
     }
 }
+/// The size of [FieldSettings::medium], but without a connectivity of its own
 impl Default for FieldSettings {
     fn default() -> Self {
-        Self::medium()
+        Self {
+            connectivity: None,
+            ..Self::medium()
+        }
+    }
+}
+
+/// Which cells of the field's bounding box contain a block.
+/// Cells outside the shape don't exist, and are never neighbors of anything.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FieldShape {
+    /// Every cell. The classic solid box.
+    #[default]
+    Box,
+    /// Only cells on the surface of the box, leaving it hollow.
+    HollowShell,
+}
+impl FieldShape {
+    /// Whether the cell at `index` exists in a field of this shape with dimensions `size`
+    pub fn contains(self, index: [usize; 3], size: [usize; 3]) -> bool {
+        match self {
+            FieldShape::Box => true,
+            FieldShape::HollowShell => {
+                (0..3).any(|axis| index[axis] == 0 || index[axis] + 1 == size[axis])
+            }
+        }
     }
 }
 
@@ -63,3 +465,545 @@ pub enum Safety {
     /// No guarantees - the first click could lose the game.
     Random,
 }
+
+/// Visual settings for blocks in the field.
+#[derive(Debug, Resource)]
+pub struct BlockStyle {
+    /// What to show in place of a revealed block with no adjacent mines
+    pub empty_marker: EmptyMarker,
+    /// Color of the empty marker. Should be translucent so deeper layers remain visible.
+    pub empty_marker_color: Color,
+    /// Perceptual roughness of hidden blocks, from glossy (0.0) to matte (1.0).
+    /// Scales the roughness of the block texture. Ignored with [GameSettings::flat_shading].
+    pub roughness: f32,
+    /// How metallic hidden blocks are, from 0.0 to 1.0. Ignored with [GameSettings::flat_shading].
+    pub metallic: f32,
+    /// Strength of specular highlights on hidden blocks, from 0.0 to 1.0.
+    /// Ignored with [GameSettings::flat_shading].
+    pub reflectance: f32,
+    /// Subdivisions of the icospheres used to show numbers with [RevealStyle::Sphere].
+    /// Lower values use fewer triangles, for large fields on slow hardware.
+    /// `None` uses the smoother modeled spheres.
+    pub sphere_detail: Option<usize>,
+}
+impl Default for BlockStyle {
+    fn default() -> Self {
+        Self {
+            empty_marker: EmptyMarker::default(),
+            empty_marker_color: Color::rgba(1.0, 1.0, 1.0, 0.15),
+            roughness: 1.0,
+            metallic: 0.0,
+            reflectance: 0.5,
+            sphere_detail: None,
+        }
+    }
+}
+
+/// Colors of block materials, applied when a game starts.
+/// Every state should have its own color, so no two can be mistaken for each other.
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct ColorScheme {
+    /// Blocks marked as mines
+    pub marked: Color,
+    /// Revealed numbers, for 1, 2, 3, 4, and 5 or more adjacent mines
+    pub counts: [Color; 5],
+    /// A mine that has been detonated, or was revealed at the end of the game
+    pub mine: Color,
+    /// A mine that was correctly marked, revealed at the end of the game
+    pub marked_mine: Color,
+    /// A mine that wasn't marked, revealed after a loss
+    pub missed_mine: Color,
+    /// Whether marked blocks are striped, so they can be told apart without relying on color
+    pub flag_pattern: bool,
+}
+impl Default for ColorScheme {
+    fn default() -> Self {
+        Self {
+            marked: Color::YELLOW,
+            counts: [
+                Color::BLUE,
+                Color::GREEN,
+                Color::RED,
+                Color::ORANGE,
+                Color::PURPLE,
+            ],
+            mine: Color::DARK_GRAY,
+            marked_mine: Color::TEAL,
+            missed_mine: Color::MAROON,
+            flag_pattern: false,
+        }
+    }
+}
+impl ColorScheme {
+    /// Colors that remain distinguishable with the common forms of color blindness,
+    /// from the Okabe-Ito palette. Marked blocks are striped as well.
+    pub fn colorblind() -> Self {
+        Self {
+            marked: Color::WHITE,
+            counts: [
+                Color::rgb(0.34, 0.71, 0.91),
+                Color::rgb(0.0, 0.62, 0.45),
+                Color::rgb(0.84, 0.37, 0.0),
+                Color::rgb(0.94, 0.89, 0.26),
+                Color::rgb(0.8, 0.47, 0.65),
+            ],
+            mine: Color::DARK_GRAY,
+            marked_mine: Color::rgb(0.0, 0.45, 0.7),
+            missed_mine: Color::rgb(0.9, 0.6, 0.0),
+            flag_pattern: true,
+        }
+    }
+    /// Colors for [GameSettings::high_contrast]. As WCAG measures contrast, numbers are at
+    /// least 8.3:1 and mines 5.8:1 against the black background, and marked blocks 7.9:1
+    /// against the light hidden blocks around them. Marked blocks are striped as well.
+    pub fn high_contrast() -> Self {
+        Self {
+            marked: Color::rgb(0.0, 0.0, 0.8),
+            counts: [
+                Color::rgb(0.0, 1.0, 1.0),
+                Color::rgb(1.0, 1.0, 0.0),
+                Color::rgb(1.0, 0.5, 0.0),
+                Color::rgb(1.0, 0.4, 1.0),
+                Color::WHITE,
+            ],
+            mine: Color::rgb(1.0, 0.2, 0.2),
+            marked_mine: Color::rgb(0.0, 1.0, 0.0),
+            missed_mine: Color::rgb(1.0, 0.6, 0.8),
+            flag_pattern: true,
+        }
+    }
+    /// Whether these are the [ColorScheme::colorblind] colors, regardless of
+    /// [ColorScheme::flag_pattern]
+    pub fn is_colorblind(&self) -> bool {
+        *self
+            == Self {
+                flag_pattern: self.flag_pattern,
+                ..Self::colorblind()
+            }
+    }
+    /// Every color in the scheme, with the name of what it's for
+    fn named(&self) -> Vec<(String, Color)> {
+        let mut colors = vec![
+            ("marked".to_string(), self.marked),
+            ("mine".to_string(), self.mine),
+            ("marked_mine".to_string(), self.marked_mine),
+            ("missed_mine".to_string(), self.missed_mine),
+        ];
+        for (n, color) in self.counts.iter().enumerate() {
+            colors.push((format!("count {}", n + 1), *color));
+        }
+        colors
+    }
+    /// Check that no two states share a color.
+    /// Returns a description of the first collision found if they do.
+    pub fn validate(&self) -> Result<(), String> {
+        let colors = self.named();
+        for (n, (name, color)) in colors.iter().enumerate() {
+            if let Some((other, _)) = colors[n + 1..].iter().find(|(_, c)| c == color) {
+                return Err(format!("{name} and {other} are both {color:?}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Marker left behind by a revealed block with no adjacent mines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyMarker {
+    /// Nothing is left behind; the block vanishes entirely.
+    None,
+    /// A tiny dot at the center of the cell.
+    #[default]
+    Dot,
+    /// A thin tile recessed into the floor of the cell.
+    Tile,
+}
+
+/// Define what it takes to win a game.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WinCondition {
+    /// Every block that isn't a mine has been cleared.
+    #[default]
+    RevealAll,
+    /// Every mine has been marked, and no block without a mine is marked.
+    MarkAll,
+}
+
+/// How a revealed block shows its number of adjacent mines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RevealStyle {
+    /// Clusters of colored spheres, with a ring for every five mines.
+    #[default]
+    Sphere,
+    /// A smaller cube colored by the number of adjacent mines, keeping the grid structure visible.
+    ShrunkCube,
+    /// The number itself, drawn over the block.
+    NumberText,
+    /// Sphere clusters with the number drawn over them, to help learn what the spheres mean.
+    SphereWithNumber,
+    /// A flat disc colored by the number of adjacent mines, with the number drawn over it.
+    /// It always faces the camera and stays about the same size on screen, so it's still
+    /// crisp when far away.
+    Billboard,
+}
+
+/// Sound played when a mine is detonated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExplosionSound {
+    /// No sound besides the usual one for clearing a block.
+    None,
+    /// A sharp pop.
+    Pop,
+    /// A deep, slowed down pop.
+    #[default]
+    Boom,
+}
+
+/// How a cleared block animates away before showing what it contained.
+/// Every style ends with the block shrunk to nothing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RevealAnim {
+    /// Shrink away in place.
+    #[default]
+    Shrink,
+    /// Sink downwards while shrinking.
+    Sink,
+    /// Tip over towards the camera while shrinking.
+    FlipTowardCamera,
+    /// Fall in towards the center of the field while shrinking.
+    Inward,
+}
+impl RevealAnim {
+    /// Where a block at `start` ends up once it has animated away, with `to_camera`
+    /// pointing from the block to the camera and `cell` the size of a cell
+    pub fn end_transform(&self, start: Transform, to_camera: Vec3, cell: f32) -> Transform {
+        let end = start.with_scale(Vec3::ZERO);
+        match self {
+            Self::Shrink => end,
+            Self::Sink => end.with_translation(start.translation - Vec3::Y * cell),
+            Self::FlipTowardCamera => {
+                // Tip the top of the block over towards the camera
+                let axis = Vec3::Y.cross(to_camera).try_normalize().unwrap_or(Vec3::X);
+                end.with_translation(start.translation + to_camera.normalize_or_zero() * cell * 0.5)
+                    .with_rotation(
+                        Quat::from_axis_angle(axis, std::f32::consts::FRAC_PI_2) * start.rotation,
+                    )
+            }
+            Self::Inward => end
+                .with_translation(start.translation - start.translation.normalize_or_zero() * cell),
+        }
+    }
+}
+
+/// Order blocks appear in at the start of a game.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnOrder {
+    /// Outwards from the center of the field.
+    #[default]
+    CenterOut,
+    /// One horizontal layer at a time, from the bottom up.
+    Layers,
+    /// Spiraling around the vertical axis, from the bottom up.
+    Spiral,
+    /// In a random order.
+    Random,
+}
+impl SpawnOrder {
+    /// Sort the indices of a field into this order, with `up_axis` pointing up
+    pub fn arrange(&self, indices: &mut [[usize; 3]], field_size: [usize; 3], up_axis: UpAxis) {
+        let center = field_size.map(|n| (n as f32 - 1.0) / 2.0);
+        // Offsets from the center in world directions, so layers are always horizontal
+        let offset = |index: [usize; 3]| {
+            up_axis.to_world(Vec3::from(std::array::from_fn(|n| {
+                index[n] as f32 - center[n]
+            })))
+        };
+        match self {
+            Self::CenterOut => {
+                indices.sort_by(|a, b| offset(*a).length().total_cmp(&offset(*b).length()))
+            }
+            Self::Layers => indices.sort_by(|a, b| {
+                let (a, b) = (offset(*a), offset(*b));
+                (a.y.total_cmp(&b.y))
+                    .then(a.z.total_cmp(&b.z))
+                    .then(a.x.total_cmp(&b.x))
+            }),
+            Self::Spiral => indices.sort_by(|a, b| {
+                let rings = up_axis.to_world(Vec3::from_array(field_size.map(|n| n as f32)));
+                let rings = rings.x.abs().max(rings.z.abs());
+                let key = |index: [usize; 3]| {
+                    let offset = offset(index);
+                    let turn = offset.z.atan2(offset.x) / std::f32::consts::TAU + 0.5;
+                    // One turn per ring, working outwards within each layer
+                    let ring = Vec2::new(offset.x, offset.z).length().round();
+                    offset.y.round() * rings + ring + turn
+                };
+                key(*a).total_cmp(&key(*b))
+            }),
+            Self::Random => indices.shuffle(&mut rand::thread_rng()),
+        }
+    }
+}
+
+/// Axis of the field that points up in the world.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    /// The second axis of [FieldSettings::field_size] is vertical, so its layers are stacked
+    /// from the bottom up.
+    #[default]
+    Y,
+    /// The third axis of [FieldSettings::field_size] is vertical, so the layers shown by the
+    /// layer summary are stacked from the bottom up.
+    Z,
+}
+impl UpAxis {
+    /// Turn a vector along the field's axes into world directions, keeping the field's
+    /// first axis along the world X axis
+    pub fn to_world(&self, field: Vec3) -> Vec3 {
+        match self {
+            Self::Y => field,
+            // A quarter turn around X, so the field stays right-handed
+            Self::Z => Vec3::new(field.x, field.z, -field.y),
+        }
+    }
+}
+
+/// Which of the cells surrounding a cell are considered its neighbors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// The 6 cells sharing a face.
+    Faces,
+    /// The 18 cells sharing a face or an edge.
+    Edges,
+    /// All 26 cells sharing a face, edge, or corner.
+    #[default]
+    Vertices,
+}
+impl Connectivity {
+    /// Whether the cell at this offset from another is its neighbor
+    pub fn includes(&self, offset: [isize; 3]) -> bool {
+        let axes_moved = offset.iter().filter(|&&o| o != 0).count();
+        let max_axes = match self {
+            Self::Faces => 1,
+            Self::Edges => 2,
+            Self::Vertices => 3,
+        };
+        axes_moved > 0 && axes_moved <= max_axes && offset.iter().all(|o| o.abs() <= 1)
+    }
+    /// Number of neighbors of a cell away from the edges of the field, which is the most
+    /// adjacent mines a cell can have
+    pub fn neighbor_count(&self) -> usize {
+        self.neighbors([1, 1, 1]).count()
+    }
+    /// Indices of the neighbors of `index`, not checking whether they're in the field
+    pub fn neighbors(&self, index: [usize; 3]) -> impl Iterator<Item = [usize; 3]> + '_ {
+        (-1..=1)
+            .flat_map(|i| (-1..=1).flat_map(move |j| (-1..=1).map(move |k| [i, j, k])))
+            .filter(|offset| self.includes(*offset))
+            .map(move |offset| std::array::from_fn(|n| index[n].wrapping_add_signed(offset[n])))
+    }
+}
+
+/// Angle the camera starts at, looking at the center of the field.
+/// The distance from the field is chosen to fit the field size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InitialView {
+    /// Looking down diagonally at a corner of the field.
+    #[default]
+    Isometric,
+    /// Looking almost straight down at the top of the field.
+    TopDown,
+    /// Looking at the front face of the field, from slightly above.
+    Front,
+}
+impl InitialView {
+    /// Unit vector from the center of the field towards the camera
+    pub fn direction(&self) -> Vec3 {
+        match self {
+            Self::Isometric => Vec3::ONE,
+            // Not exactly vertical, so the camera still has a well-defined up direction
+            Self::TopDown => Vec3::new(0.0, 1.0, 0.2),
+            Self::Front => Vec3::new(0.0, 0.3, 1.0),
+        }
+        .normalize()
+    }
+}
+
+/// How much of the field to show when the game is lost.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RevealOnLoss {
+    /// Only the mine that was detonated.
+    HitOnly,
+    /// Every mine, but no other hidden blocks.
+    #[default]
+    AllMines,
+    /// The contents of every block.
+    Everything,
+}
+
+/// Blend from `a` to `b` by `t` in HSV space, going the short way around the color wheel
+fn lerp_hsv(a: Color, b: Color, t: f32) -> Color {
+    let [h_a, s_a, v_a] = to_hsv(a);
+    let [h_b, s_b, v_b] = to_hsv(b);
+    let turn = (h_b - h_a + 540.0).rem_euclid(360.0) - 180.0;
+    let h = (h_a + turn * t).rem_euclid(360.0);
+    from_hsv([h, s_a + (s_b - s_a) * t, v_a + (v_b - v_a) * t])
+}
+
+/// Hue in degrees, saturation and value of a color
+fn to_hsv(color: Color) -> [f32; 3] {
+    let [r, g, b, _] = color.as_rgba_f32();
+    let max = r.max(g).max(b);
+    let range = max - r.min(g).min(b);
+    let hue = if range == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / range).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / range + 2.0)
+    } else {
+        60.0 * ((r - g) / range + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { range / max };
+    [hue, saturation, max]
+}
+
+fn from_hsv([hue, saturation, value]: [f32; 3]) -> Color {
+    let chroma = value * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    Color::rgb(r + m, g + m, b + m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_gradient_covers_every_number_in_order() {
+        let settings = GameSettings {
+            count_gradient: Some((Color::rgb(1.0, 0.0, 0.0), Color::rgb(0.0, 1.0, 0.0))),
+            ..default()
+        };
+        let scheme = ColorScheme::default();
+        for connectivity in [
+            Connectivity::Faces,
+            Connectivity::Edges,
+            Connectivity::Vertices,
+        ] {
+            let colors = settings.count_colors(&scheme, connectivity);
+            assert_eq!(colors.len(), connectivity.neighbor_count());
+            assert_eq!(colors[0], Color::rgb(1.0, 0.0, 0.0));
+            let hues: Vec<_> = colors.iter().map(|color| to_hsv(*color)[0]).collect();
+            assert!(
+                hues.windows(2).all(|pair| pair[0] < pair[1]),
+                "{connectivity:?} hues aren't increasing: {hues:?}"
+            );
+            assert!((hues[hues.len() - 1] - 120.0).abs() < 1e-3);
+        }
+        assert_eq!(Connectivity::Vertices.neighbor_count(), 26);
+    }
+
+    #[test]
+    fn presets_are_valid_and_extreme_densities_are_not() {
+        for preset in [
+            FieldSettings::small(),
+            FieldSettings::medium(),
+            FieldSettings::large(),
+        ] {
+            assert_eq!(preset.validate(), Ok(()), "{preset:?}");
+        }
+        let empty = FieldSettings {
+            mine_density: 0.0,
+            ..default()
+        };
+        assert!(empty.validate().is_err());
+        let full = FieldSettings {
+            mine_density: 1.0,
+            ..default()
+        };
+        assert!(full.validate().is_err());
+    }
+
+    #[test]
+    fn schemes_give_every_state_its_own_color() {
+        for scheme in [
+            ColorScheme::default(),
+            ColorScheme::colorblind(),
+            ColorScheme::high_contrast(),
+        ] {
+            assert_eq!(scheme.validate(), Ok(()), "{scheme:?}");
+        }
+        // A flag the same color as a 3 is caught
+        let scheme = ColorScheme {
+            marked: Color::RED,
+            ..default()
+        };
+        assert_eq!(
+            scheme.validate(),
+            Err(format!("marked and count 3 are both {:?}", Color::RED))
+        );
+    }
+
+    #[test]
+    fn presets_pick_their_own_connectivity_unless_overridden() {
+        let settings = |preset_connectivity| GameSettings {
+            connectivity: Connectivity::Vertices,
+            flood_connectivity: Connectivity::Faces,
+            preset_connectivity,
+            ..default()
+        };
+        let game_settings = settings(true);
+        for (preset, connectivity) in [
+            (FieldSettings::small(), Connectivity::Faces),
+            (FieldSettings::medium(), Connectivity::Edges),
+            (FieldSettings::large(), Connectivity::Vertices),
+        ] {
+            assert_eq!(game_settings.connectivity_for(&preset), connectivity);
+            assert_eq!(game_settings.flood_connectivity_for(&preset), connectivity);
+        }
+        // Custom fields, and presets with the override, use the game settings
+        let overridden = settings(false);
+        for (settings, field) in [
+            (&game_settings, FieldSettings::default()),
+            (&overridden, FieldSettings::small()),
+        ] {
+            assert_eq!(settings.connectivity_for(&field), Connectivity::Vertices);
+            assert_eq!(settings.flood_connectivity_for(&field), Connectivity::Faces);
+        }
+    }
+
+    #[test]
+    fn hollow_shells_and_boxes_are_centered_alike() {
+        for field_size in [[3, 3, 3], [4, 5, 6], [2, 7, 1], [10, 10, 10]] {
+            for shape in [FieldShape::Box, FieldShape::HollowShell] {
+                let settings = FieldSettings {
+                    field_size,
+                    shape,
+                    ..default()
+                };
+                let centroid = settings.centroid();
+                assert!(
+                    centroid.abs().max_element() < 1e-4,
+                    "{shape:?} {field_size:?} is centered on {centroid}"
+                );
+            }
+        }
+        // The shell really leaves out the middle
+        let shell = FieldSettings {
+            field_size: [3, 3, 3],
+            shape: FieldShape::HollowShell,
+            ..default()
+        };
+        assert_eq!(shell.num_cells(), 26);
+    }
+}
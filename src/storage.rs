@@ -0,0 +1,59 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+/// Directory where persistent game data is stored.
+///
+/// Uses `%APPDATA%` on Windows and `$XDG_DATA_HOME` (falling back to `~/.local/share`) elsewhere.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+    base.map(|dir| dir.join("sweeper-3d"))
+}
+
+/// Read a file from the data directory, if it exists.
+pub fn read(file: &str) -> Option<String> {
+    let path = data_dir()?.join(file);
+    match fs::read_to_string(&path) {
+        Ok(contents) => Some(contents),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => {
+            error!("Unable to read {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+/// Write a file to the data directory.
+///
+/// The contents are written to a temporary file first and then moved into place,
+/// so a crash mid-write never leaves a partially written file behind.
+pub fn write(file: &str, contents: &str) -> io::Result<()> {
+    let dir = data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory available"))?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(file);
+    let tmp = dir.join(format!("{file}.tmp"));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, &path)
+}
+
+/// Remove a file from the data directory. Succeeds if it doesn't exist.
+pub fn remove(file: &str) -> io::Result<()> {
+    let Some(dir) = data_dir() else {
+        return Ok(());
+    };
+    match fs::remove_file(dir.join(file)) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
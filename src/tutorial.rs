@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{storage, GameSettings, GameState, InputEvent, KeyBindings};
+
+/// File in the data directory whose presence means the tutorial has been seen
+const TUTORIAL_SEEN_FILE: &str = "tutorial_seen";
+
+pub struct TutorialPlugin;
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tutorial>()
+            .add_systems(Startup, start_on_first_run)
+            .add_systems(
+                Update,
+                (advance_tutorial, display_tutorial).chain().run_if(
+                    GameState::in_game()
+                        .and_then(|tutorial: Res<Tutorial>| tutorial.step.is_some()),
+                ),
+            );
+    }
+}
+
+/// Panels walking a new player through the controls, shown over the first game.
+/// Once finished or skipped, it isn't shown again unless [Tutorial::start] is called.
+#[derive(Debug, Default, Resource)]
+pub struct Tutorial {
+    /// Index of the panel being shown, if the tutorial is in progress
+    step: Option<usize>,
+}
+impl Tutorial {
+    /// Show the tutorial from the beginning
+    pub fn start(&mut self) {
+        self.step = Some(0);
+    }
+    /// Whether the tutorial is being shown
+    pub fn active(&self) -> bool {
+        self.step.is_some()
+    }
+    /// Close the tutorial, and remember not to show it on the next run
+    fn finish(&mut self) {
+        self.step = None;
+        if let Err(err) = storage::write(TUTORIAL_SEEN_FILE, "") {
+            error!("Unable to save that the tutorial was seen: {err}");
+        }
+    }
+}
+
+/// Title and text of each tutorial panel
+fn steps(key_bindings: &KeyBindings, game_settings: &GameSettings) -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "Look Around",
+            format!(
+                "Drag with the {:?} mouse button to orbit the field. Press {:?} to go \
+                back to where you started.",
+                game_settings.rotate_button, key_bindings.reset_camera
+            ),
+        ),
+        (
+            "Zoom",
+            format!(
+                "Scroll the mouse wheel, or press {:?} and {:?}, to move closer to or \
+                farther from the field.",
+                key_bindings.zoom_in, key_bindings.zoom_out
+            ),
+        ),
+        (
+            "Clear",
+            format!(
+                "Click a block with the {:?} mouse button to clear it. Cleared blocks show \
+                how many of the blocks around them contain mines, and clearing a mine \
+                loses the game.",
+                game_settings.reveal_button
+            ),
+        ),
+        (
+            "Mark",
+            format!(
+                "Click a block with the {:?} mouse button to mark it as a mine, so you \
+                don't clear it by accident. Click it again to unmark it.",
+                game_settings.flag_button
+            ),
+        ),
+        (
+            "Chord",
+            format!(
+                "Once a number has as many marked blocks around it as it shows, click it \
+                with the {:?} mouse button to clear the rest of its neighbors at once.{}",
+                game_settings.reveal_button,
+                if game_settings.chording {
+                    ""
+                } else {
+                    " Turn on Chording in the settings to use this."
+                }
+            ),
+        ),
+        (
+            "Win",
+            "Clear every block without a mine to win. Good luck!".to_string(),
+        ),
+    ]
+}
+
+fn start_on_first_run(mut tutorial: ResMut<Tutorial>) {
+    if storage::data_dir().is_some() && storage::read(TUTORIAL_SEEN_FILE).is_none() {
+        tutorial.start();
+    }
+}
+
+/// Move to the next panel on [InputEvent::TutorialNext]
+fn advance_tutorial(
+    mut input_events: EventReader<InputEvent>,
+    mut tutorial: ResMut<Tutorial>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettings>,
+) {
+    let count = steps(&key_bindings, &game_settings).len();
+    for event in input_events.read() {
+        if !matches!(event, InputEvent::TutorialNext) {
+            continue;
+        }
+        let Some(step) = tutorial.step else {
+            return;
+        };
+        if step + 1 < count {
+            tutorial.step = Some(step + 1);
+        } else {
+            tutorial.finish();
+        }
+    }
+}
+
+fn display_tutorial(
+    mut contexts: EguiContexts,
+    mut tutorial: ResMut<Tutorial>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettings>,
+) {
+    let Some(step) = tutorial.step else {
+        return;
+    };
+    let steps = steps(&key_bindings, &game_settings);
+    let Some((title, text)) = steps.get(step) else {
+        tutorial.finish();
+        return;
+    };
+    let last = step + 1 == steps.len();
+    egui::Window::new(format!("Tutorial: {title}"))
+        .id(egui::Id::new("tutorial"))
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -48.0])
+        .collapsible(false)
+        .movable(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.set_max_width(480.0);
+            ui.label(text);
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(format!("{}/{}", step + 1, steps.len()))
+                        .small()
+                        .weak(),
+                );
+                let next = if last { "Done" } else { "Next" };
+                if ui
+                    .button(next)
+                    .on_hover_text(format!("Or press {:?}", key_bindings.tutorial_next))
+                    .clicked()
+                {
+                    if last {
+                        tutorial.finish();
+                    } else {
+                        tutorial.step = Some(step + 1);
+                    }
+                }
+                if !last && ui.button("Skip").clicked() {
+                    tutorial.finish();
+                }
+            });
+        });
+}